@@ -0,0 +1,294 @@
+//! Client for the custodian's HTTP API (backend/app/app.py or blindr-server),
+//! the Rust counterpart of sdk/blindr_client.py. It runs the whole flow:
+//! create keys, open a session, blind the transaction, prove it fits the
+//! constraint, get it blind signed, unblind and check the signature.
+//!
+//! Requests that are safe to repeat are retried on transport errors and 5xx
+//! responses. Blind signing consumes the session, so it is only retried when
+//! the request never reached the server.
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+use blindr_common::{Constraint, Transaction};
+use blindsign::{request::BlindRequest, signature::WiredUnblindedSigData};
+use curve25519_dalek::ristretto::CompressedRistretto;
+use rand::RngCore;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
+
+pub mod transport;
+
+use transport::{Method, Request, Transport, TransportError};
+
+pub type Bytes32 = [u8; 32];
+
+#[derive(Debug)]
+pub enum ClientError {
+    Transport(TransportError),
+    /// The server answered with an error status.
+    Api { status: u16, message: String },
+    /// The server's response wasn't what the API promises.
+    Protocol(String),
+    Blindsign(blindsign::Error),
+    Prove(String),
+    /// The unblinded signature doesn't authenticate the transaction under the
+    /// server's public key.
+    SignatureInvalid,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::Transport(e) => write!(f, "{}", e),
+            ClientError::Api { status, message } => write!(f, "server error {}: {}", status, message),
+            ClientError::Protocol(e) => write!(f, "unexpected response: {}", e),
+            ClientError::Blindsign(e) => write!(f, "{}", e),
+            ClientError::Prove(e) => write!(f, "proving failed: {}", e),
+            ClientError::SignatureInvalid => write!(f, "signature verification failed"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<blindsign::Error> for ClientError {
+    fn from(e: blindsign::Error) -> Self {
+        ClientError::Blindsign(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+/// Produces the proof sent with /blind-sign.
+pub trait Prover: Send + Sync {
+    fn prove(&self, transaction: &Transaction, constraint: &Constraint, public_value: &Bytes32, blind_seed: &Bytes32) -> Result<String>;
+}
+
+/// Proves with blindr_zk, encoding the receipt in base64 like libblindr.
+/// The default proves a composite receipt and encodes it as is.
+#[cfg(feature = "prove")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ZkProver {
+    /// compress the receipt into a succinct one first, which needs the
+    /// `succinct` feature (a local prover)
+    pub succinct: bool,
+    pub encoding: blindr_zk_driver::proof::Encoding,
+}
+
+#[cfg(feature = "prove")]
+impl Prover for ZkProver {
+    fn prove(&self, transaction: &Transaction, constraint: &Constraint, public_value: &Bytes32, blind_seed: &Bytes32) -> Result<String> {
+        use base64::prelude::*;
+        let receipt = blindr_zk_driver::prove(transaction, constraint, public_value, blind_seed);
+        let receipt = match self.succinct {
+            true => compress(&receipt)?,
+            false => receipt,
+        };
+        let proof = blindr_zk_driver::proof::encode(&receipt, self.encoding).map_err(|e| ClientError::Prove(e.to_string()))?;
+        Ok(BASE64_STANDARD.encode(proof))
+    }
+}
+
+#[cfg(feature = "succinct")]
+fn compress(receipt: &blindr_zk_driver::Receipt) -> Result<blindr_zk_driver::Receipt> {
+    blindr_zk_driver::proof::compress(receipt).map_err(|e| ClientError::Prove(e.to_string()))
+}
+
+#[cfg(all(feature = "prove", not(feature = "succinct")))]
+fn compress(_: &blindr_zk_driver::Receipt) -> Result<blindr_zk_driver::Receipt> {
+    Err(ClientError::Prove("succinct proofs need the succinct feature".to_string()))
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Attempts per request, including the first.
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubling for every retry after it.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy { max_attempts: 3, backoff: Duration::from_millis(200) }
+    }
+}
+
+/// The keypair the custodian holds for a constraint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Keys {
+    pub public_key: Bytes32,
+    pub constraint_hash: Bytes32,
+}
+
+/// An open signing session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Session {
+    pub public_value: Bytes32,
+    pub public_key: Bytes32,
+    pub constraint_hash: Bytes32,
+}
+
+#[derive(Deserialize)]
+struct PublicKeyResponse {
+    public_key: String,
+}
+
+#[derive(Deserialize)]
+struct PublicValueResponse {
+    public_value: String,
+}
+
+#[derive(Deserialize)]
+struct BlindSignResponse {
+    blinded_signature: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+fn hex32(s: &str, what: &str) -> Result<Bytes32> {
+    hex::decode(s)
+        .ok()
+        .and_then(|bytes| Bytes32::try_from(bytes).ok())
+        .ok_or_else(|| ClientError::Protocol(format!("{} is not 32 bytes of hex", what)))
+}
+
+pub struct Client {
+    transport: Box<dyn Transport>,
+    prover: Box<dyn Prover>,
+    retry: RetryPolicy,
+}
+
+impl Client {
+    /// Talks to the custodian at `base_url` over HTTP, with a 30 second
+    /// timeout per request. Proving a transaction can take much longer than
+    /// that, but happens locally.
+    #[cfg(all(feature = "http", feature = "prove"))]
+    pub fn new(base_url: &str) -> Self {
+        let transport = transport::UreqTransport::new(base_url, Duration::from_secs(30));
+        Client::with_transport(transport, ZkProver::default())
+    }
+
+    pub fn with_transport(transport: impl Transport + 'static, prover: impl Prover + 'static) -> Self {
+        Client { transport: Box::new(transport), prover: Box::new(prover), retry: RetryPolicy::default() }
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn call<T: DeserializeOwned>(&self, method: Method, path: &'static str, body: serde_json::Value, idempotent: bool) -> Result<T> {
+        let request = Request { method, path, body: body.to_string().into_bytes() };
+        let mut attempt = 1;
+        loop {
+            let retryable = match self.transport.send(&request) {
+                Ok(response) if (200..300).contains(&response.status) => {
+                    return serde_json::from_slice(&response.body).map_err(|e| ClientError::Protocol(e.to_string()));
+                }
+                Ok(response) => {
+                    let message = serde_json::from_slice::<ErrorResponse>(&response.body)
+                        .map(|e| e.error)
+                        .unwrap_or_else(|_| String::from_utf8_lossy(&response.body).into_owned());
+                    let error = ClientError::Api { status: response.status, message };
+                    (idempotent && response.status >= 500, error)
+                }
+                Err(e) => (idempotent || matches!(e, TransportError::Connect(_)), ClientError::Transport(e)),
+            };
+            match retryable {
+                (true, _) if attempt < self.retry.max_attempts => {
+                    thread::sleep(self.retry.backoff * 2u32.pow(attempt - 1));
+                    attempt += 1;
+                }
+                (_, error) => return Err(error),
+            }
+        }
+    }
+
+    /// Gets (or has the custodian generate) the keypair for the constraint.
+    pub fn create_keys(&self, constraint: &Constraint) -> Result<Keys> {
+        let constraint_hash = constraint.hash();
+        let response: PublicKeyResponse = self.call(
+            Method::Post,
+            "/generate-keypair",
+            json!({ "constraint_hash": hex::encode(constraint_hash) }),
+            true,
+        )?;
+        Ok(Keys { public_key: hex32(&response.public_key, "public key")?, constraint_hash })
+    }
+
+    /// Gets the constraint's open session, opening one if needed.
+    pub fn create_or_restart_session(&self, constraint: &Constraint) -> Result<Session> {
+        let keys = self.create_keys(constraint)?;
+        let response: PublicValueResponse = self.call(
+            Method::Post,
+            "/create-sign-session",
+            json!({ "constraint_hash": hex::encode(keys.constraint_hash) }),
+            true,
+        )?;
+        Ok(Session {
+            public_value: hex32(&response.public_value, "public value")?,
+            public_key: keys.public_key,
+            constraint_hash: keys.constraint_hash,
+        })
+    }
+
+    /// Blinds the transaction against the session, proves it fits the
+    /// constraint, has it blind signed and returns the unblinded signature
+    /// once it checks out under the session's public key.
+    pub fn sign_message(&self, transaction: &Transaction, session: &Session, constraint: &Constraint) -> Result<WiredUnblindedSigData> {
+        // the proof recomputes the blinded message from this seed
+        let mut seed = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut seed);
+        let (blinded_message, request) = BlindRequest::new_specific_msg_seeded(&session.public_value, transaction.message(), &seed)?;
+
+        let proof = self.prover.prove(transaction, constraint, &session.public_value, &seed)?;
+
+        let response: BlindSignResponse = self.call(
+            Method::Post,
+            "/blind-sign",
+            json!({
+                "blinded_message": hex::encode(blinded_message),
+                "constraint_hash": hex::encode(session.constraint_hash),
+                "proof": proof,
+            }),
+            false,
+        )?;
+        let blinded_signature = hex32(&response.blinded_signature, "blinded signature")?;
+        let signature = request.gen_signed_msg(&blinded_signature)?;
+
+        let public_key = CompressedRistretto(session.public_key)
+            .decompress()
+            .ok_or_else(|| ClientError::Protocol("public key is not a valid ristretto point".to_string()))?;
+        if !signature.msg_authenticate::<(), _>(public_key, transaction.message()) {
+            return Err(ClientError::SignatureInvalid);
+        }
+
+        // signing already consumed the session on blindr-server, closing it
+        // is for the flask backend and it's fine if it fails
+        let _ = self.call::<serde_json::Value>(
+            Method::Post,
+            "/close-sign-session",
+            json!({ "constraint_hash": hex::encode(session.constraint_hash) }),
+            true,
+        );
+        Ok(WiredUnblindedSigData::from(signature))
+    }
+
+    pub fn create_session_and_sign(&self, transaction: &Transaction, constraint: &Constraint) -> Result<WiredUnblindedSigData> {
+        let session = self.create_or_restart_session(constraint)?;
+        self.sign_message(transaction, &session, constraint)
+    }
+
+    pub fn delete_key(&self, constraint: &Constraint) -> Result<()> {
+        self.call::<serde_json::Value>(
+            Method::Delete,
+            "/delete-key",
+            json!({ "constraint_hash": hex::encode(constraint.hash()) }),
+            true,
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+//! How requests reach the custodian. UreqTransport talks HTTP; tests (or
+//! embedders) can implement Transport to route requests anywhere else.
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    Post,
+    Delete,
+}
+
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: Method,
+    /// The endpoint, ie: "/blind-sign".
+    pub path: &'static str,
+    /// JSON body.
+    pub body: Vec<u8>,
+}
+
+/// Any HTTP response, including 4xx and 5xx ones.
+#[derive(Clone, Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum TransportError {
+    /// The request never reached the server (ie: connection refused), so it
+    /// is always safe to retry.
+    Connect(String),
+    /// The request may or may not have reached the server (ie: a timeout
+    /// waiting for the response).
+    Other(String),
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransportError::Connect(e) => write!(f, "could not connect: {}", e),
+            TransportError::Other(e) => write!(f, "transport error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+pub trait Transport: Send + Sync {
+    fn send(&self, request: &Request) -> Result<Response, TransportError>;
+}
+
+#[cfg(feature = "http")]
+pub use http::UreqTransport;
+
+#[cfg(feature = "http")]
+mod http {
+    use std::io::Read;
+    use std::time::Duration;
+    use super::{Method, Request, Response, Transport, TransportError};
+
+    pub struct UreqTransport {
+        base_url: String,
+        agent: ureq::Agent,
+    }
+
+    impl UreqTransport {
+        /// `timeout` bounds each request as a whole, from connecting to reading
+        /// the response.
+        pub fn new(base_url: &str, timeout: Duration) -> Self {
+            UreqTransport {
+                base_url: base_url.trim_end_matches('/').to_string(),
+                agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+            }
+        }
+    }
+
+    fn read_body(response: ureq::Response) -> Result<Vec<u8>, TransportError> {
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut body)
+            .map_err(|e| TransportError::Other(e.to_string()))?;
+        Ok(body)
+    }
+
+    impl Transport for UreqTransport {
+        fn send(&self, request: &Request) -> Result<Response, TransportError> {
+            let url = format!("{}{}", self.base_url, request.path);
+            let method = match request.method {
+                Method::Post => "POST",
+                Method::Delete => "DELETE",
+            };
+            let result = self
+                .agent
+                .request(method, &url)
+                .set("Content-Type", "application/json")
+                .send_bytes(&request.body);
+            match result {
+                Ok(response) => Ok(Response { status: response.status(), body: read_body(response)? }),
+                Err(ureq::Error::Status(status, response)) => Ok(Response { status, body: read_body(response)? }),
+                Err(ureq::Error::Transport(e)) => match e.kind() {
+                    ureq::ErrorKind::Dns | ureq::ErrorKind::ConnectionFailed => Err(TransportError::Connect(e.to_string())),
+                    _ => Err(TransportError::Other(e.to_string())),
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use blindr_client::{
+    transport::{Request, Response, Transport, TransportError, UreqTransport},
+    Bytes32, Client, ClientError, Prover, RetryPolicy,
+};
+use blindr_common::{Amount, Auth, Constraint, Transaction};
+use blindr_server::{router, AppState, Config, ProofVerifier};
+use curve25519_dalek::ristretto::CompressedRistretto;
+
+// Proving needs the zkvm, so the server accepts the proof "valid" and the
+// client's prover produces it for transactions within the limit.
+struct FakeVerifier;
+
+impl ProofVerifier for FakeVerifier {
+    fn verify(&self, proof: &str, _: &Bytes32, _: &Bytes32) -> Result<bool, String> {
+        Ok(proof == "valid")
+    }
+
+    fn verify_batch(&self, proof: &str, _: &[Bytes32], _: &Bytes32) -> Result<bool, String> {
+        Ok(proof == "valid")
+    }
+}
+
+struct FakeProver;
+
+impl Prover for FakeProver {
+    fn prove(&self, transaction: &Transaction, constraint: &Constraint, _: &Bytes32, _: &Bytes32) -> blindr_client::Result<String> {
+        let fits = constraint.check(&transaction.amount).is_ok();
+        Ok(if fits { "valid" } else { "invalid" }.to_string())
+    }
+}
+
+/// Runs blindr-server on a background thread, returning its base url.
+fn spawn_server() -> String {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let listener = runtime.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+    let mut state = AppState::in_memory();
+    state.verifier = Arc::new(FakeVerifier);
+    let app = router(state, &Config::default());
+    std::thread::spawn(move || runtime.block_on(async { axum::serve(listener, app).await.unwrap() }));
+    base_url
+}
+
+fn client(base_url: &str) -> Client {
+    Client::with_transport(UreqTransport::new(base_url, Duration::from_secs(5)), FakeProver)
+}
+
+fn constraint() -> Constraint {
+    Constraint::new(Auth(vec![("pet".to_string(), "rex".to_string())]), 100)
+}
+
+fn transaction(amount: u64) -> Transaction {
+    Transaction::new("alice".to_string(), "bob".to_string(), Amount::native(amount))
+}
+
+#[test]
+fn test_sign_flow() {
+    let client = client(&spawn_server());
+    let constraint = constraint();
+
+    let session = client.create_or_restart_session(&constraint).unwrap();
+    assert_eq!(session.constraint_hash, constraint.hash());
+
+    let spend = transaction(50);
+    let signature = client.sign_message(&spend, &session, &constraint).unwrap();
+    let public_key = CompressedRistretto(session.public_key).decompress().unwrap();
+    let signature = signature.to_internal_format().unwrap();
+    assert!(signature.msg_authenticate::<(), _>(public_key, spend.message()));
+
+    // a fresh session is needed for the next signature
+    client.create_session_and_sign(&spend, &constraint).unwrap();
+
+    match client.create_session_and_sign(&transaction(500), &constraint) {
+        Err(ClientError::Api { status: 400, message }) => assert_eq!(message, "Verification failed"),
+        Err(other) => panic!("expected a verification failure, got {}", other),
+        Ok(_) => panic!("signed a transaction over the limit"),
+    }
+
+    client.delete_key(&constraint).unwrap();
+    assert!(matches!(client.delete_key(&constraint), Err(ClientError::Api { status: 404, .. })));
+}
+
+/// Fails the first `failures` sends before reaching the server.
+struct FlakyTransport {
+    inner: UreqTransport,
+    failures: u32,
+    sends: AtomicU32,
+}
+
+impl Transport for FlakyTransport {
+    fn send(&self, request: &Request) -> Result<Response, TransportError> {
+        if self.sends.fetch_add(1, Ordering::SeqCst) < self.failures {
+            return Err(TransportError::Connect("connection refused".to_string()));
+        }
+        self.inner.send(request)
+    }
+}
+
+#[test]
+fn test_retries() {
+    let base_url = spawn_server();
+    let flaky = |failures| FlakyTransport {
+        inner: UreqTransport::new(&base_url, Duration::from_secs(5)),
+        failures,
+        sends: AtomicU32::new(0),
+    };
+    let retry = RetryPolicy { max_attempts: 3, backoff: Duration::from_millis(1) };
+
+    let client = Client::with_transport(flaky(2), FakeProver).retry(retry);
+    client.create_keys(&constraint()).unwrap();
+
+    let client = Client::with_transport(flaky(3), FakeProver).retry(retry);
+    assert!(matches!(client.create_keys(&constraint()), Err(ClientError::Transport(TransportError::Connect(_)))));
+}
@@ -0,0 +1,130 @@
+//! Amounts of a specific asset. Comparisons and arithmetic only happen
+//! between amounts of the same asset, after bringing both to the larger
+//! number of decimals, and fail instead of wrapping around.
+use std::cmp::Ordering;
+use std::fmt;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Amount {
+    /// empty for the domain's native asset
+    pub asset: String,
+    /// in units of 10^-decimals of the asset
+    pub value: u128,
+    pub decimals: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AmountError {
+    AssetMismatch { expected: String, found: String },
+    Overflow,
+    /// the constraint has no limit for the asset
+    NoLimit(String),
+    LimitExceeded,
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AmountError::AssetMismatch { expected, found } => {
+                write!(f, "can't compare asset {:?} with {:?}", found, expected)
+            }
+            AmountError::Overflow => write!(f, "amount overflow"),
+            AmountError::NoLimit(asset) => write!(f, "no limit for asset {:?}", asset),
+            AmountError::LimitExceeded => write!(f, "amount over the limit"),
+        }
+    }
+}
+
+impl std::error::Error for AmountError {}
+
+impl Amount {
+    pub fn new(asset: &str, value: u128, decimals: u8) -> Self {
+        Amount { asset: asset.to_string(), value, decimals }
+    }
+
+    /// Whole units of the native asset, what a bare integer amount means.
+    pub fn native(value: u64) -> Self {
+        Amount::new("", value as u128, 0)
+    }
+
+    /// The same amount with more `decimals`.
+    /// Fails with Overflow if `decimals` is fewer than the amount has.
+    pub fn rescale(&self, decimals: u8) -> Result<Amount, AmountError> {
+        let shift = decimals.checked_sub(self.decimals).ok_or(AmountError::Overflow)?;
+        let value = 10u128
+            .checked_pow(shift as u32)
+            .and_then(|factor| self.value.checked_mul(factor))
+            .ok_or(AmountError::Overflow)?;
+        Ok(Amount { asset: self.asset.clone(), value, decimals })
+    }
+
+    // both amounts at the larger number of decimals
+    fn aligned(&self, other: &Amount) -> Result<(u128, u128, u8), AmountError> {
+        if self.asset != other.asset {
+            return Err(AmountError::AssetMismatch { expected: self.asset.clone(), found: other.asset.clone() });
+        }
+        let decimals = self.decimals.max(other.decimals);
+        Ok((self.rescale(decimals)?.value, other.rescale(decimals)?.value, decimals))
+    }
+
+    pub fn checked_cmp(&self, other: &Amount) -> Result<Ordering, AmountError> {
+        let (a, b, _) = self.aligned(other)?;
+        Ok(a.cmp(&b))
+    }
+
+    pub fn checked_add(&self, other: &Amount) -> Result<Amount, AmountError> {
+        let (a, b, decimals) = self.aligned(other)?;
+        let value = a.checked_add(b).ok_or(AmountError::Overflow)?;
+        Ok(Amount { asset: self.asset.clone(), value, decimals })
+    }
+
+    pub fn checked_sub(&self, other: &Amount) -> Result<Amount, AmountError> {
+        let (a, b, decimals) = self.aligned(other)?;
+        let value = a.checked_sub(b).ok_or(AmountError::Overflow)?;
+        Ok(Amount { asset: self.asset.clone(), value, decimals })
+    }
+}
+
+// JSON keeps the value as a decimal string, since u128 doesn't survive JS
+// numbers, and reads a bare integer as Amount::native. The zkvm's serde
+// format isn't human readable and gets the plain fields.
+#[derive(Serialize, Deserialize)]
+struct Fields<V> {
+    asset: String,
+    value: V,
+    decimals: u8,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Json {
+    Native(u64),
+    Fields(Fields<String>),
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            Fields { asset: self.asset.clone(), value: self.value.to_string(), decimals: self.decimals }.serialize(serializer)
+        } else {
+            Fields { asset: self.asset.clone(), value: self.value, decimals: self.decimals }.serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if !deserializer.is_human_readable() {
+            let Fields { asset, value, decimals } = Fields::<u128>::deserialize(deserializer)?;
+            return Ok(Amount { asset, value, decimals });
+        }
+        match Json::deserialize(deserializer)? {
+            Json::Native(value) => Ok(Amount::native(value)),
+            Json::Fields(Fields { asset, value, decimals }) => {
+                let value = value.parse().map_err(serde::de::Error::custom)?;
+                Ok(Amount { asset, value, decimals })
+            }
+        }
+    }
+}
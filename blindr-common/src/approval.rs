@@ -0,0 +1,265 @@
+//! Second factors a constraint can require on top of its limits. The
+//! constraint registers them (ie: a device's public key, the hash of a TOTP
+//! secret, a passkey or an OIDC identity) and the guest checks the Approvals
+//! given with the message before blinding it, so the custodian only signs
+//! what they approved.
+use std::fmt;
+use base64::prelude::*;
+use ed25519_dalek::{Signature, VerifyingKey};
+use hmac::{Hmac, Mac};
+use p256::ecdsa::signature::Verifier;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest as _, Sha256};
+use crate::identity::{check_identity, IdentityApproval};
+use crate::{Constraint, Message};
+
+/// Seconds per TOTP time step.
+pub const TOTP_PERIOD: u64 = 30;
+/// Digits of a TOTP code.
+pub const TOTP_DIGITS: u32 = 6;
+
+/// The second factors' approvals of one message.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Approvals {
+    /// the device key's hex ed25519 signature over Message::signed_bytes
+    #[serde(default)]
+    pub device_signature: Option<String>,
+    #[serde(default)]
+    pub totp: Option<TotpApproval>,
+    #[serde(default)]
+    pub passkey: Option<PasskeyAssertion>,
+    #[serde(default)]
+    pub identity: Option<IdentityApproval>,
+}
+
+/// A TOTP code with the secret it comes from. The secret is private input
+/// to the guest, which only commits the time step: the proof shows the
+/// prover holds the secret the constraint registers the hash of, for the
+/// server's current time step.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TotpApproval {
+    /// hex secret, the constraint has its SHA-256
+    pub secret: String,
+    pub code: u32,
+    /// unix seconds / TOTP_PERIOD, as the server gave it
+    pub step: u64,
+}
+
+/// A WebAuthn credential registered with a constraint.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Passkey {
+    /// hex SEC1 encoded P-256 public key of the credential
+    pub public_key: String,
+    /// the relying party the credential is scoped to, ie: example.com
+    pub rp_id: String,
+}
+
+/// What navigator.credentials.get returns for the challenge
+/// passkey_challenge(signed bytes), so the assertion approves that message
+/// only.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasskeyAssertion {
+    /// hex
+    pub authenticator_data: String,
+    /// as the authenticator signed it, byte for byte
+    pub client_data_json: String,
+    /// hex DER encoded ECDSA signature
+    pub signature: String,
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ApprovalError {
+    /// the constraint's device key isn't an ed25519 public key
+    DeviceKeyMalformed,
+    DeviceSignatureMissing,
+    DeviceSignatureInvalid,
+    TotpMissing,
+    /// the secret isn't the one the constraint registers
+    TotpSecretMismatch,
+    TotpCodeInvalid,
+    /// the constraint's passkey isn't a P-256 public key
+    PasskeyMalformed,
+    PasskeyAssertionMissing,
+    /// the authenticator data or client data can't be parsed
+    PasskeyAssertionMalformed,
+    /// the assertion is for another relying party
+    PasskeyRpMismatch,
+    /// the authenticator didn't check the user was present
+    PasskeyUserNotPresent,
+    /// the assertion isn't for the message
+    PasskeyChallengeMismatch,
+    PasskeySignatureInvalid,
+    IdentityMissing,
+    /// the key set isn't the one the constraint registers
+    JwksMismatch,
+    JwksMalformed,
+    IdTokenMalformed,
+    /// neither RS256 nor ES256
+    IdTokenAlgorithmUnsupported,
+    /// no key of the key set can have signed the token
+    IdTokenKeyUnknown,
+    IdTokenSignatureInvalid,
+    IdTokenIssuerMismatch,
+    IdTokenAudienceMismatch,
+    IdTokenSubjectMismatch,
+    /// the token isn't for the message
+    IdTokenNonceMismatch,
+}
+
+impl fmt::Display for ApprovalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApprovalError::DeviceKeyMalformed => write!(f, "device key is not an ed25519 public key"),
+            ApprovalError::DeviceSignatureMissing => write!(f, "the constraint needs the device's approval"),
+            ApprovalError::DeviceSignatureInvalid => write!(f, "device signature does not approve the message"),
+            ApprovalError::TotpMissing => write!(f, "the constraint needs a TOTP code"),
+            ApprovalError::TotpSecretMismatch => write!(f, "TOTP secret does not match the constraint"),
+            ApprovalError::TotpCodeInvalid => write!(f, "wrong TOTP code for the time step"),
+            ApprovalError::PasskeyMalformed => write!(f, "passkey is not a P-256 public key"),
+            ApprovalError::PasskeyAssertionMissing => write!(f, "the constraint needs a passkey assertion"),
+            ApprovalError::PasskeyAssertionMalformed => write!(f, "malformed passkey assertion"),
+            ApprovalError::PasskeyRpMismatch => write!(f, "passkey assertion is for another relying party"),
+            ApprovalError::PasskeyUserNotPresent => write!(f, "passkey assertion without user presence"),
+            ApprovalError::PasskeyChallengeMismatch => write!(f, "passkey assertion is not for the message"),
+            ApprovalError::PasskeySignatureInvalid => write!(f, "passkey signature does not verify"),
+            ApprovalError::IdentityMissing => write!(f, "the constraint needs an ID token"),
+            ApprovalError::JwksMismatch => write!(f, "key set does not match the constraint"),
+            ApprovalError::JwksMalformed => write!(f, "malformed key set"),
+            ApprovalError::IdTokenMalformed => write!(f, "malformed ID token"),
+            ApprovalError::IdTokenAlgorithmUnsupported => write!(f, "ID token is not signed with RS256 or ES256"),
+            ApprovalError::IdTokenKeyUnknown => write!(f, "ID token's key is not in the key set"),
+            ApprovalError::IdTokenSignatureInvalid => write!(f, "ID token signature does not verify"),
+            ApprovalError::IdTokenIssuerMismatch => write!(f, "ID token is from another issuer"),
+            ApprovalError::IdTokenAudienceMismatch => write!(f, "ID token is for another client"),
+            ApprovalError::IdTokenSubjectMismatch => write!(f, "ID token is for another account"),
+            ApprovalError::IdTokenNonceMismatch => write!(f, "ID token is not for the message"),
+        }
+    }
+}
+
+impl std::error::Error for ApprovalError {}
+
+impl Message {
+    /// The bytes that get blinded and signed: the transaction's message, a
+    /// bitcoin transaction's commitment or an ethereum transaction's signing
+    /// hash. What second factors approve.
+    pub fn signed_bytes(&self) -> Option<Vec<u8>> {
+        match self {
+            Message::Transaction(transaction) => Some(transaction.message()),
+            Message::Bitcoin(transaction) => transaction.tx_commitment().ok().map(|commitment| commitment.to_vec()),
+            Message::Ethereum(transaction) => transaction.signing_hash().ok().map(|hash| hash.to_vec()),
+        }
+    }
+}
+
+fn device_key(hex_key: &str) -> Result<VerifyingKey, ApprovalError> {
+    let key = hex::decode(hex_key).ok().and_then(|key| <[u8; 32]>::try_from(key).ok());
+    key.and_then(|key| VerifyingKey::from_bytes(&key).ok()).ok_or(ApprovalError::DeviceKeyMalformed)
+}
+
+/// The RFC 6238 code of the secret for the time step: HOTP (RFC 4226) with
+/// HMAC-SHA1 over the step, truncated to TOTP_DIGITS digits.
+pub fn totp(secret: &[u8], step: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("hmac takes keys of any length");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let code = u32::from_be_bytes([hash[offset], hash[offset + 1], hash[offset + 2], hash[offset + 3]]) & 0x7fff_ffff;
+    code % 10u32.pow(TOTP_DIGITS)
+}
+
+fn check_totp(secret_hash: &str, approval: &TotpApproval) -> Result<(), ApprovalError> {
+    let secret = hex::decode(&approval.secret).map_err(|_| ApprovalError::TotpSecretMismatch)?;
+    if !hex::encode(Sha256::digest(&secret)).eq_ignore_ascii_case(secret_hash) {
+        return Err(ApprovalError::TotpSecretMismatch);
+    }
+    match totp(&secret, approval.step) == approval.code {
+        true => Ok(()),
+        false => Err(ApprovalError::TotpCodeInvalid),
+    }
+}
+
+/// The WebAuthn challenge approving a message: the base64url (unpadded)
+/// SHA-256 of its signed bytes. The relying party asks for it instead of a
+/// random challenge; the transaction's expiry and the single use sign
+/// session keep the assertion from being replayed.
+pub fn passkey_challenge(signed_bytes: &[u8]) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(signed_bytes))
+}
+
+// the WebAuthn assertion verification steps (https://www.w3.org/TR/webauthn-2/#sctn-verifying-assertion)
+// that don't need the relying party's state: client data type and challenge,
+// rp ID hash, user presence and the signature
+fn check_passkey(passkey: &Passkey, signed_bytes: &[u8], assertion: &PasskeyAssertion) -> Result<(), ApprovalError> {
+    let key = hex::decode(&passkey.public_key)
+        .ok()
+        .and_then(|key| p256::ecdsa::VerifyingKey::from_sec1_bytes(&key).ok())
+        .ok_or(ApprovalError::PasskeyMalformed)?;
+    let authenticator_data = hex::decode(&assertion.authenticator_data).map_err(|_| ApprovalError::PasskeyAssertionMalformed)?;
+    let client_data: ClientData =
+        serde_json::from_str(&assertion.client_data_json).map_err(|_| ApprovalError::PasskeyAssertionMalformed)?;
+    // rp ID hash (32 bytes), flags, signature counter (4 bytes)
+    if authenticator_data.len() < 37 || client_data.kind != "webauthn.get" {
+        return Err(ApprovalError::PasskeyAssertionMalformed);
+    }
+    if authenticator_data[..32] != Sha256::digest(passkey.rp_id.as_bytes())[..] {
+        return Err(ApprovalError::PasskeyRpMismatch);
+    }
+    if authenticator_data[32] & 0x01 == 0 {
+        return Err(ApprovalError::PasskeyUserNotPresent);
+    }
+    if client_data.challenge != passkey_challenge(signed_bytes) {
+        return Err(ApprovalError::PasskeyChallengeMismatch);
+    }
+
+    let signature = hex::decode(&assertion.signature)
+        .ok()
+        .and_then(|signature| p256::ecdsa::Signature::from_der(&signature).ok())
+        .ok_or(ApprovalError::PasskeySignatureInvalid)?;
+    let mut signed = authenticator_data;
+    signed.extend_from_slice(&Sha256::digest(assertion.client_data_json.as_bytes()));
+    key.verify(&signed, &signature).map_err(|_| ApprovalError::PasskeySignatureInvalid)
+}
+
+impl Constraint {
+    /// Whether the approvals satisfy the second factors the constraint
+    /// requires, for a message whose signed bytes are `signed_bytes`.
+    /// Approvals the constraint doesn't ask for are ignored.
+    pub fn check_approvals(&self, signed_bytes: &[u8], approvals: &Approvals) -> Result<(), ApprovalError> {
+        if let Some(hex_key) = &self.device_key {
+            let key = device_key(hex_key)?;
+            let signature = approvals.device_signature.as_ref().ok_or(ApprovalError::DeviceSignatureMissing)?;
+            let signature = hex::decode(signature)
+                .ok()
+                .and_then(|signature| Signature::from_slice(&signature).ok())
+                .ok_or(ApprovalError::DeviceSignatureInvalid)?;
+            // strict: no small order keys or non-canonical signatures
+            key.verify_strict(signed_bytes, &signature).map_err(|_| ApprovalError::DeviceSignatureInvalid)?;
+        }
+        if let Some(secret_hash) = &self.totp_secret_hash {
+            check_totp(secret_hash, approvals.totp.as_ref().ok_or(ApprovalError::TotpMissing)?)?;
+        }
+        if let Some(passkey) = &self.passkey {
+            check_passkey(passkey, signed_bytes, approvals.passkey.as_ref().ok_or(ApprovalError::PasskeyAssertionMissing)?)?;
+        }
+        if let Some(identity) = &self.identity {
+            check_identity(identity, signed_bytes, approvals.identity.as_ref().ok_or(ApprovalError::IdentityMissing)?)?;
+        }
+        Ok(())
+    }
+
+    /// The time step of the approvals' TOTP code, if the constraint asks for
+    /// one. The guest commits it for the verifier to check against its clock.
+    pub fn totp_step(&self, approvals: &Approvals) -> Option<u64> {
+        self.totp_secret_hash.as_ref()?;
+        approvals.totp.as_ref().map(|totp| totp.step)
+    }
+}
@@ -0,0 +1,475 @@
+//! Bitcoin transactions as the signed message, given as a PSBT (version 0)
+//! or an unsigned raw transaction. Only what the constraint needs is parsed:
+//! the inputs' outpoints and the outputs' values and scripts, and for PSBTs
+//! the outputs each input spends.
+//!
+//! The signed message is the transaction's commitment. For a PSBT it is the
+//! SIGHASH_ALL digest of its input (legacy, BIP143, or BIP341 key path with
+//! SIGHASH_DEFAULT), ie: what a wallet signs, or with several inputs the
+//! double SHA-256 of their digests in order. Every input must carry its
+//! witness or non-witness UTXO. A raw transaction doesn't carry the outputs
+//! it spends, so its commitment is the double SHA-256 of its unsigned
+//! serialization, ie: its txid in internal byte order, which commits to
+//! every input and output but is not a sighash.
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use crate::{Amount, AmountError, Constraint};
+
+const PSBT_MAGIC: &[u8] = b"psbt\xff";
+const PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const PSBT_IN_NON_WITNESS_UTXO: u8 = 0x00;
+const PSBT_IN_WITNESS_UTXO: u8 = 0x01;
+const PSBT_IN_SIGHASH_TYPE: u8 = 0x03;
+const PSBT_IN_REDEEM_SCRIPT: u8 = 0x04;
+const PSBT_IN_WITNESS_SCRIPT: u8 = 0x05;
+
+const SIGHASH_DEFAULT: u32 = 0x00;
+const SIGHASH_ALL: u32 = 0x01;
+
+/// The asset amounts of bitcoin are checked as, in satoshis.
+pub const BITCOIN_ASSET: &str = "btc";
+pub const BITCOIN_DECIMALS: u8 = 8;
+
+/// A PSBT or unsigned raw transaction, as serialized.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BitcoinTransaction(pub Vec<u8>);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxIn {
+    pub prev_txid: [u8; 32],
+    pub prev_vout: u32,
+    pub sequence: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxOut {
+    /// in satoshis
+    pub value: u64,
+    pub script_pubkey: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsignedTransaction {
+    pub version: i32,
+    pub inputs: Vec<TxIn>,
+    pub outputs: Vec<TxOut>,
+    pub lock_time: u32,
+}
+
+/// Which outputs a constraint lets a bitcoin transaction pay. Scripts are
+/// hex scriptPubKeys.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BitcoinPolicy {
+    /// outputs back to the sender, they don't count towards the limit
+    pub change_scripts: Vec<String>,
+    /// the only scripts other outputs may pay, any if empty
+    pub receivers: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BitcoinError {
+    Malformed(&'static str),
+    ReceiverNotAllowed(String),
+    Amount(AmountError),
+}
+
+impl fmt::Display for BitcoinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BitcoinError::Malformed(e) => write!(f, "malformed bitcoin transaction: {}", e),
+            BitcoinError::ReceiverNotAllowed(script) => write!(f, "receiver {} is not allowed", script),
+            BitcoinError::Amount(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for BitcoinError {}
+
+impl From<AmountError> for BitcoinError {
+    fn from(e: AmountError) -> Self {
+        BitcoinError::Amount(e)
+    }
+}
+
+type Result<T> = std::result::Result<T, BitcoinError>;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if n > self.bytes.len() {
+            return Err(BitcoinError::Malformed("unexpected end"));
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    // CompactSize, which must be minimally encoded
+    fn varint(&mut self) -> Result<u64> {
+        let (n, min) = match self.u8()? {
+            0xfd => (u16::from_le_bytes(self.array()?) as u64, 0xfd),
+            0xfe => (u32::from_le_bytes(self.array()?) as u64, 0x1_0000),
+            0xff => (self.u64()?, 0x1_0000_0000),
+            n => return Ok(n as u64),
+        };
+        if n < min {
+            return Err(BitcoinError::Malformed("non-minimal varint"));
+        }
+        Ok(n)
+    }
+
+    // a length prefixed byte string
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.varint()?;
+        let len = usize::try_from(len).map_err(|_| BitcoinError::Malformed("length too large"))?;
+        self.take(len)
+    }
+
+    fn done(&self) -> Result<()> {
+        match self.bytes.is_empty() {
+            true => Ok(()),
+            false => Err(BitcoinError::Malformed("trailing bytes")),
+        }
+    }
+}
+
+fn sha256d(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(bytes)).into()
+}
+
+fn write_varint(out: &mut Vec<u8>, n: usize) {
+    match n {
+        0..=0xfc => out.push(n as u8),
+        0xfd..=0xffff => {
+            out.push(0xfd);
+            out.extend((n as u16).to_le_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(0xfe);
+            out.extend((n as u32).to_le_bytes());
+        }
+        _ => {
+            out.push(0xff);
+            out.extend((n as u64).to_le_bytes());
+        }
+    }
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(out, bytes.len());
+    out.extend(bytes);
+}
+
+impl TxOut {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend(self.value.to_le_bytes());
+        write_bytes(out, &self.script_pubkey);
+    }
+}
+
+fn parse_unsigned(bytes: &[u8]) -> Result<UnsignedTransaction> {
+    let mut reader = Reader { bytes };
+    let version = reader.u32()? as i32;
+    // zero inputs reads as the segwit marker, which unsigned transactions
+    // don't have
+    let inputs = (0..reader.varint()?)
+        .map(|_| {
+            let prev_txid = reader.array()?;
+            let prev_vout = reader.u32()?;
+            if !reader.bytes()?.is_empty() {
+                return Err(BitcoinError::Malformed("input is signed"));
+            }
+            Ok(TxIn { prev_txid, prev_vout, sequence: reader.u32()? })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if inputs.is_empty() {
+        return Err(BitcoinError::Malformed("no inputs (or a witness serialization)"));
+    }
+    let outputs = (0..reader.varint()?)
+        .map(|_| Ok(TxOut { value: reader.u64()?, script_pubkey: reader.bytes()?.to_vec() }))
+        .collect::<Result<Vec<_>>>()?;
+    let lock_time = reader.u32()?;
+    reader.done()?;
+    Ok(UnsignedTransaction { version, inputs, outputs, lock_time })
+}
+
+// the txid of a network serialized transaction, with or without witnesses,
+// and its output `vout`
+fn spent_output(bytes: &[u8], vout: u32) -> Result<([u8; 32], TxOut)> {
+    let mut reader = Reader { bytes };
+    let version = reader.array::<4>()?;
+    let segwit = reader.bytes.starts_with(&[0x00, 0x01]);
+    if segwit {
+        reader.take(2)?;
+    }
+    let body = reader.bytes;
+    let inputs = reader.varint()?;
+    for _ in 0..inputs {
+        reader.take(36)?;
+        reader.bytes()?;
+        reader.u32()?;
+    }
+    let outputs = (0..reader.varint()?)
+        .map(|_| Ok(TxOut { value: reader.u64()?, script_pubkey: reader.bytes()?.to_vec() }))
+        .collect::<Result<Vec<_>>>()?;
+    let body = &body[..body.len() - reader.bytes.len()];
+    if segwit {
+        for _ in 0..inputs {
+            for _ in 0..reader.varint()? {
+                reader.bytes()?;
+            }
+        }
+    }
+    let lock_time = reader.array::<4>()?;
+    reader.done()?;
+
+    let txid = sha256d(&[&version[..], body, &lock_time].concat());
+    let output = outputs.into_iter().nth(vout as usize).ok_or(BitcoinError::Malformed("spent output out of range"))?;
+    Ok((txid, output))
+}
+
+// the key-value pairs of a PSBT map, up to its separator
+fn psbt_map<'a>(reader: &mut Reader<'a>) -> Result<Vec<(&'a [u8], &'a [u8])>> {
+    let mut map: Vec<(&[u8], &[u8])> = Vec::new();
+    loop {
+        let key = reader.bytes()?;
+        if key.is_empty() {
+            return Ok(map);
+        }
+        if map.iter().any(|(present, _)| *present == key) {
+            return Err(BitcoinError::Malformed("duplicate psbt key"));
+        }
+        map.push((key, reader.bytes()?));
+    }
+}
+
+fn psbt_value<'a>(map: &[(&'a [u8], &'a [u8])], key_type: u8) -> Option<&'a [u8]> {
+    map.iter().find(|(key, _)| *key == [key_type]).map(|(_, value)| *value)
+}
+
+// what the PSBT says about one of its inputs
+struct PsbtInput<'a> {
+    spent: TxOut,
+    sighash_type: Option<u32>,
+    redeem_script: Option<&'a [u8]>,
+    witness_script: Option<&'a [u8]>,
+}
+
+impl<'a> PsbtInput<'a> {
+    fn parse(map: &[(&'a [u8], &'a [u8])], input: &TxIn) -> Result<Self> {
+        // the non-witness UTXO is checked against the outpoint, so it is
+        // preferred
+        let spent = match (psbt_value(map, PSBT_IN_NON_WITNESS_UTXO), psbt_value(map, PSBT_IN_WITNESS_UTXO)) {
+            (Some(previous_tx), _) => {
+                let (txid, spent) = spent_output(previous_tx, input.prev_vout)?;
+                if txid != input.prev_txid {
+                    return Err(BitcoinError::Malformed("non-witness utxo is not the spent transaction"));
+                }
+                spent
+            }
+            (None, Some(utxo)) => {
+                let mut reader = Reader { bytes: utxo };
+                let spent = TxOut { value: reader.u64()?, script_pubkey: reader.bytes()?.to_vec() };
+                reader.done()?;
+                spent
+            }
+            (None, None) => return Err(BitcoinError::Malformed("psbt input has no utxo")),
+        };
+        let sighash_type = psbt_value(map, PSBT_IN_SIGHASH_TYPE)
+            .map(|value| value.try_into().map(u32::from_le_bytes))
+            .transpose()
+            .map_err(|_| BitcoinError::Malformed("sighash type is not 4 bytes"))?;
+        let redeem_script = psbt_value(map, PSBT_IN_REDEEM_SCRIPT);
+        let witness_script = psbt_value(map, PSBT_IN_WITNESS_SCRIPT);
+        Ok(PsbtInput { spent, sighash_type, redeem_script, witness_script })
+    }
+}
+
+struct Psbt<'a> {
+    unsigned_tx: &'a [u8],
+    inputs: Vec<PsbtInput<'a>>,
+}
+
+// the unsigned transaction in the PSBT's global map, and its input maps
+fn parse_psbt(psbt: &[u8]) -> Result<Psbt<'_>> {
+    let mut reader = Reader { bytes: &psbt[PSBT_MAGIC.len()..] };
+    let global = psbt_map(&mut reader)?;
+    let unsigned_tx =
+        psbt_value(&global, PSBT_GLOBAL_UNSIGNED_TX).ok_or(BitcoinError::Malformed("psbt has no unsigned transaction"))?;
+    let inputs = parse_unsigned(unsigned_tx)?
+        .inputs
+        .iter()
+        .map(|input| PsbtInput::parse(&psbt_map(&mut reader)?, input))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Psbt { unsigned_tx, inputs })
+}
+
+fn tagged_hash(tag: &str, message: &[u8]) -> [u8; 32] {
+    let tag = Sha256::digest(tag.as_bytes());
+    Sha256::new().chain_update(tag).chain_update(tag).chain_update(message).finalize().into()
+}
+
+// the SIGHASH_ALL digests of the transaction's inputs. Other sighash types
+// are refused: they let the signature cover less than the outputs the
+// constraint was checked against.
+fn sighashes(transaction: &UnsignedTransaction, inputs: &[PsbtInput]) -> Result<Vec<[u8; 32]>> {
+    let mut outputs = Vec::new();
+    transaction.outputs.iter().for_each(|output| output.write(&mut outputs));
+    let outpoint = |input: &TxIn| [&input.prev_txid[..], &input.prev_vout.to_le_bytes()].concat();
+    let prevouts = transaction.inputs.iter().flat_map(outpoint).collect::<Vec<_>>();
+    let sequences = transaction.inputs.iter().flat_map(|input| input.sequence.to_le_bytes()).collect::<Vec<_>>();
+
+    let mut digests = Vec::new();
+    for (index, (input, psbt_input)) in transaction.inputs.iter().zip(inputs).enumerate() {
+        let spent = &psbt_input.spent.script_pubkey;
+        let script = match spent[..] {
+            [0xa9, 0x14, .., 0x87] if spent.len() == 23 => {
+                psbt_input.redeem_script.ok_or(BitcoinError::Malformed("p2sh input has no redeem script"))?
+            }
+            _ => spent,
+        };
+        let digest = match *script {
+            // BIP341, key path
+            [0x51, 0x20, ..] if script.len() == 34 => {
+                let hash_type = match psbt_input.sighash_type {
+                    None => SIGHASH_DEFAULT,
+                    Some(hash_type @ (SIGHASH_DEFAULT | SIGHASH_ALL)) => hash_type,
+                    Some(_) => return Err(BitcoinError::Malformed("sighash type is not ALL")),
+                };
+                let amounts = inputs.iter().flat_map(|input| input.spent.value.to_le_bytes()).collect::<Vec<_>>();
+                let mut scripts = Vec::new();
+                inputs.iter().for_each(|input| write_bytes(&mut scripts, &input.spent.script_pubkey));
+                let mut message = vec![0x00, hash_type as u8];
+                message.extend(transaction.version.to_le_bytes());
+                message.extend(transaction.lock_time.to_le_bytes());
+                for field in [&prevouts, &amounts, &scripts, &sequences, &outputs] {
+                    message.extend(Sha256::digest(field));
+                }
+                // no annex
+                message.push(0x00);
+                message.extend((index as u32).to_le_bytes());
+                tagged_hash("TapSighash", &message)
+            }
+            // BIP143
+            [0x00, 0x14 | 0x20, ..] if script.len() == script[1] as usize + 2 => {
+                if !matches!(psbt_input.sighash_type, None | Some(SIGHASH_ALL)) {
+                    return Err(BitcoinError::Malformed("sighash type is not ALL"));
+                }
+                let script_code = match script.len() {
+                    22 => [&[0x76, 0xa9, 0x14][..], &script[2..], &[0x88, 0xac]].concat(),
+                    _ => psbt_input.witness_script.ok_or(BitcoinError::Malformed("p2wsh input has no witness script"))?.to_vec(),
+                };
+                let mut preimage = transaction.version.to_le_bytes().to_vec();
+                preimage.extend(sha256d(&prevouts));
+                preimage.extend(sha256d(&sequences));
+                preimage.extend(outpoint(input));
+                write_bytes(&mut preimage, &script_code);
+                preimage.extend(psbt_input.spent.value.to_le_bytes());
+                preimage.extend(input.sequence.to_le_bytes());
+                preimage.extend(sha256d(&outputs));
+                preimage.extend(transaction.lock_time.to_le_bytes());
+                preimage.extend(SIGHASH_ALL.to_le_bytes());
+                sha256d(&preimage)
+            }
+            [0x00 | 0x51..=0x60, 0x02..=0x28, ..] if script.len() == script[1] as usize + 2 => {
+                return Err(BitcoinError::Malformed("unknown witness program"));
+            }
+            // legacy, the script code in place of the input's script
+            _ => {
+                if !matches!(psbt_input.sighash_type, None | Some(SIGHASH_ALL)) {
+                    return Err(BitcoinError::Malformed("sighash type is not ALL"));
+                }
+                let mut preimage = transaction.version.to_le_bytes().to_vec();
+                write_varint(&mut preimage, transaction.inputs.len());
+                for (other, input) in transaction.inputs.iter().enumerate() {
+                    preimage.extend(outpoint(input));
+                    write_bytes(&mut preimage, if other == index { script } else { &[] });
+                    preimage.extend(input.sequence.to_le_bytes());
+                }
+                write_varint(&mut preimage, transaction.outputs.len());
+                preimage.extend(&outputs);
+                preimage.extend(transaction.lock_time.to_le_bytes());
+                preimage.extend(SIGHASH_ALL.to_le_bytes());
+                sha256d(&preimage)
+            }
+        };
+        digests.push(digest);
+    }
+    Ok(digests)
+}
+
+impl BitcoinTransaction {
+    fn unsigned_bytes(&self) -> Result<&[u8]> {
+        match self.0.starts_with(PSBT_MAGIC) {
+            true => Ok(parse_psbt(&self.0)?.unsigned_tx),
+            false => Ok(&self.0),
+        }
+    }
+
+    pub fn parse(&self) -> Result<UnsignedTransaction> {
+        parse_unsigned(self.unsigned_bytes()?)
+    }
+
+    /// The SIGHASH_ALL digest of each input, in order. None for a raw
+    /// transaction, which doesn't carry the outputs it spends.
+    pub fn sighashes(&self) -> Result<Option<Vec<[u8; 32]>>> {
+        if !self.0.starts_with(PSBT_MAGIC) {
+            return Ok(None);
+        }
+        let psbt = parse_psbt(&self.0)?;
+        Ok(Some(sighashes(&parse_unsigned(psbt.unsigned_tx)?, &psbt.inputs)?))
+    }
+
+    /// The signed message, see the module docs.
+    pub fn tx_commitment(&self) -> Result<[u8; 32]> {
+        match self.sighashes()? {
+            Some(sighashes) if sighashes.len() == 1 => Ok(sighashes[0]),
+            Some(sighashes) => Ok(sha256d(&sighashes.concat())),
+            None => {
+                parse_unsigned(&self.0)?;
+                Ok(sha256d(&self.0))
+            }
+        }
+    }
+}
+
+impl Constraint {
+    /// Whether the transaction's outputs fit the constraint: every output
+    /// that isn't change pays an allowed receiver, and together they are
+    /// within the constraint's limit for BITCOIN_ASSET.
+    pub fn check_bitcoin(&self, transaction: &UnsignedTransaction) -> Result<()> {
+        let policy = self.bitcoin.clone().unwrap_or_default();
+        let mut spent = Amount::new(BITCOIN_ASSET, 0, BITCOIN_DECIMALS);
+        for output in &transaction.outputs {
+            let script = hex::encode(&output.script_pubkey);
+            if policy.change_scripts.iter().any(|change| change.eq_ignore_ascii_case(&script)) {
+                continue;
+            }
+            if !policy.receivers.is_empty() && !policy.receivers.iter().any(|receiver| receiver.eq_ignore_ascii_case(&script)) {
+                return Err(BitcoinError::ReceiverNotAllowed(script));
+            }
+            spent = spent.checked_add(&Amount::new(BITCOIN_ASSET, output.value as u128, BITCOIN_DECIMALS))?;
+        }
+        Ok(self.check(&spent)?)
+    }
+}
@@ -0,0 +1,75 @@
+//! Checks on a signed transaction beyond the signature itself.
+use std::fmt;
+use blindsign::signature::UnblindedSigData;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use crate::{Transaction, TRANSACTION_VERSION};
+
+/// What the verifier expects of a transaction.
+#[derive(Clone, Debug)]
+pub struct TransactionCheck {
+    /// unix seconds, compared against valid_until
+    pub now: u64,
+    /// the verifier's own domain, transactions for other domains are refused
+    pub domain: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransactionError {
+    SignatureInvalid,
+    UnsupportedVersion(u32),
+    Expired { valid_until: u64, now: u64 },
+    WrongDomain { expected: String, found: String },
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransactionError::SignatureInvalid => write!(f, "signature does not authenticate the transaction"),
+            TransactionError::UnsupportedVersion(version) => write!(f, "unsupported transaction version {}", version),
+            TransactionError::Expired { valid_until, now } => {
+                write!(f, "transaction expired at {} (now {})", valid_until, now)
+            }
+            TransactionError::WrongDomain { expected, found } => {
+                write!(f, "transaction is for domain {:?}, expected {:?}", found, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TransactionError {}
+
+/// msg_authenticate for transactions, also checking the version, expiry and
+/// domain.
+pub trait AuthenticateTransaction {
+    fn authenticate_transaction(
+        &self,
+        pub_key: RistrettoPoint,
+        transaction: &Transaction,
+        check: &TransactionCheck,
+    ) -> Result<(), TransactionError>;
+}
+
+impl AuthenticateTransaction for UnblindedSigData {
+    fn authenticate_transaction(
+        &self,
+        pub_key: RistrettoPoint,
+        transaction: &Transaction,
+        check: &TransactionCheck,
+    ) -> Result<(), TransactionError> {
+        if !self.msg_authenticate::<(), _>(pub_key, transaction.message()) {
+            return Err(TransactionError::SignatureInvalid);
+        }
+        if transaction.version != TRANSACTION_VERSION {
+            return Err(TransactionError::UnsupportedVersion(transaction.version));
+        }
+        if let Some(valid_until) = transaction.valid_until {
+            if check.now > valid_until {
+                return Err(TransactionError::Expired { valid_until, now: check.now });
+            }
+        }
+        if transaction.domain != check.domain {
+            return Err(TransactionError::WrongDomain { expected: check.domain.clone(), found: transaction.domain.clone() });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,491 @@
+//! Ethereum transactions as the signed message, given as an unsigned
+//! EIP-1559 transaction or EIP-712 typed data shaped like a Safe transaction
+//! (a primary message with `to`, `value` and optionally `data` and
+//! `operation`).
+//!
+//! Either way the ether sent and, when the calldata is an ERC-20 `transfer`,
+//! the tokens sent are checked against the constraint. Calldata that is
+//! anything else is refused, since it could move funds in ways the
+//! constraint can't see.
+//!
+//! The signed message is the keccak signing hash: keccak256(0x02 || rlp(tx))
+//! for EIP-1559, keccak256(0x19 0x01 || domainSeparator || hashStruct(message))
+//! for EIP-712.
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha3::{Digest as _, Keccak256};
+use crate::{Amount, AmountError, Constraint};
+
+const EIP1559_TYPE: u8 = 0x02;
+const ERC20_TRANSFER: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+
+/// The asset ether amounts are checked as, in wei.
+pub const ETHER_ASSET: &str = "eth";
+pub const ETHER_DECIMALS: u8 = 18;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EthereumTransaction {
+    /// 0x02 || rlp([chain_id, nonce, max_priority_fee_per_gas,
+    /// max_fee_per_gas, gas_limit, to, value, data, access_list])
+    Eip1559(Vec<u8>),
+    /// the eth_signTypedData_v4 JSON
+    TypedData(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Erc20Transfer {
+    pub recipient: [u8; 20],
+    /// in the token's base units
+    pub amount: u128,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EthereumTransfer {
+    pub chain_id: Option<u64>,
+    /// the recipient of the ether, and the token contract for an ERC-20
+    /// transfer
+    pub to: [u8; 20],
+    /// in wei
+    pub value: u128,
+    pub erc20: Option<Erc20Transfer>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EthereumError {
+    Malformed(String),
+    /// calldata other than an ERC-20 transfer, or a delegate call
+    UnsupportedCall,
+    Amount(AmountError),
+}
+
+impl fmt::Display for EthereumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EthereumError::Malformed(e) => write!(f, "malformed ethereum transaction: {}", e),
+            EthereumError::UnsupportedCall => write!(f, "only ether and ERC-20 transfers are supported"),
+            EthereumError::Amount(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for EthereumError {}
+
+impl From<AmountError> for EthereumError {
+    fn from(e: AmountError) -> Self {
+        EthereumError::Amount(e)
+    }
+}
+
+type Result<T> = std::result::Result<T, EthereumError>;
+
+fn malformed<T>(e: impl fmt::Display) -> Result<T> {
+    Err(EthereumError::Malformed(e.to_string()))
+}
+
+fn keccak(bytes: &[u8]) -> [u8; 32] {
+    Keccak256::digest(bytes).into()
+}
+
+// 256 bit big endian numbers that must fit in a u128
+fn u128_from_be(bytes: &[u8]) -> Result<u128> {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    if bytes.len() - start > 16 {
+        return Err(AmountError::Overflow.into());
+    }
+    Ok(bytes[start..].iter().fold(0, |n, b| (n << 8) | *b as u128))
+}
+
+// (to, value, data) as a transfer, decoding ERC-20 transfer calldata
+fn transfer(chain_id: Option<u64>, to: [u8; 20], value: u128, data: &[u8]) -> Result<EthereumTransfer> {
+    let erc20 = if data.is_empty() {
+        None
+    } else if data.len() == 4 + 64 && data[..4] == ERC20_TRANSFER {
+        let (recipient, amount) = (&data[4..36], &data[36..68]);
+        if recipient[..12].iter().any(|b| *b != 0) {
+            return malformed("transfer recipient isn't an address");
+        }
+        let recipient = recipient[12..].try_into().unwrap();
+        Some(Erc20Transfer { recipient, amount: u128_from_be(amount)? })
+    } else {
+        return Err(EthereumError::UnsupportedCall);
+    };
+    Ok(EthereumTransfer { chain_id, to, value, erc20 })
+}
+
+// the deepest lists of an EIP-1559 transaction are its access list entries'
+// storage keys, inside the entry, the access list and the transaction
+const MAX_RLP_DEPTH: usize = 4;
+
+enum Rlp<'a> {
+    Bytes(&'a [u8]),
+    List(Vec<Rlp<'a>>),
+}
+
+impl<'a> Rlp<'a> {
+    // canonical RLP only, so that a transaction has a single encoding. depth
+    // is the number of lists around the item, deeper nesting is refused
+    // rather than recursed into
+    fn decode(input: &'a [u8], depth: usize) -> Result<(Rlp<'a>, &'a [u8])> {
+        let (&prefix, rest) = input.split_first().ok_or_else(|| EthereumError::Malformed("unexpected end".into()))?;
+        let (is_list, len, rest) = match prefix {
+            0x00..=0x7f => return Ok((Rlp::Bytes(&input[..1]), rest)),
+            0x80..=0xb7 => (false, (prefix - 0x80) as usize, rest),
+            0xc0..=0xf7 => (true, (prefix - 0xc0) as usize, rest),
+            _ => {
+                let is_list = prefix >= 0xf8;
+                let len_len = (prefix - if is_list { 0xf7 } else { 0xb7 }) as usize;
+                if rest.len() < len_len || rest[0] == 0 || len_len > 4 {
+                    return malformed("bad rlp length");
+                }
+                let len = rest[..len_len].iter().fold(0usize, |n, b| (n << 8) | *b as usize);
+                if len <= 55 {
+                    return malformed("non-canonical rlp length");
+                }
+                (is_list, len, &rest[len_len..])
+            }
+        };
+        if rest.len() < len {
+            return malformed("unexpected end");
+        }
+        let (payload, rest) = rest.split_at(len);
+        if is_list {
+            if depth >= MAX_RLP_DEPTH {
+                return malformed("rlp lists nested too deep");
+            }
+            let mut items = Vec::new();
+            let mut payload = payload;
+            while !payload.is_empty() {
+                let (item, remaining) = Rlp::decode(payload, depth + 1)?;
+                items.push(item);
+                payload = remaining;
+            }
+            return Ok((Rlp::List(items), rest));
+        }
+        if len == 1 && payload[0] < 0x80 {
+            return malformed("non-canonical rlp byte");
+        }
+        Ok((Rlp::Bytes(payload), rest))
+    }
+
+    fn bytes(&self) -> Result<&'a [u8]> {
+        match self {
+            Rlp::Bytes(bytes) => Ok(bytes),
+            Rlp::List(_) => malformed("expected bytes, found a list"),
+        }
+    }
+
+    fn uint(&self) -> Result<u128> {
+        let bytes = self.bytes()?;
+        if bytes.first() == Some(&0) {
+            return malformed("integer with leading zeros");
+        }
+        u128_from_be(bytes)
+    }
+}
+
+fn decode_eip1559(encoded: &[u8]) -> Result<EthereumTransfer> {
+    let payload = match encoded.split_first() {
+        Some((&EIP1559_TYPE, payload)) => payload,
+        _ => return malformed("not an EIP-1559 transaction"),
+    };
+    let fields = match Rlp::decode(payload, 0)? {
+        (Rlp::List(fields), []) => fields,
+        (Rlp::List(_), _) => return malformed("trailing bytes"),
+        _ => return malformed("expected a list"),
+    };
+    // a signed transaction has y_parity, r and s as well
+    let [chain_id, nonce, max_priority_fee, max_fee, gas_limit, to, value, data, access_list] = &fields[..] else {
+        return malformed("expected the 9 fields of an unsigned transaction");
+    };
+    for field in [nonce, max_priority_fee, max_fee, gas_limit] {
+        field.uint()?;
+    }
+    if !matches!(access_list, Rlp::List(_)) {
+        return malformed("access list isn't a list");
+    }
+    let chain_id = u64::try_from(chain_id.uint()?).or_else(|_| malformed("chain id too large"))?;
+    let to = to.bytes()?.try_into().or_else(|_| malformed("contract creation isn't a transfer"))?;
+    transfer(Some(chain_id), to, value.uint()?, data.bytes()?)
+}
+
+// EIP-712 hashing of eth_signTypedData_v4 JSON
+struct TypedData<'a> {
+    types: &'a Map<String, Value>,
+}
+
+fn field<'a>(object: &'a Value, name: &str) -> Result<&'a Value> {
+    object.get(name).ok_or_else(|| EthereumError::Malformed(format!("missing {}", name)))
+}
+
+fn hex_bytes(value: &Value) -> Result<Vec<u8>> {
+    let s = value.as_str().ok_or_else(|| EthereumError::Malformed("expected a hex string".into()))?;
+    hex::decode(s.strip_prefix("0x").unwrap_or(s)).or_else(malformed)
+}
+
+// a JSON number, or a decimal or 0x hex string, as a 256 bit word
+fn word(value: &Value, signed: bool) -> Result<[u8; 32]> {
+    let (negative, digits) = match value {
+        Value::Number(n) if n.is_u64() => (false, n.to_string()),
+        Value::Number(n) if n.is_i64() => (true, n.to_string()[1..].to_string()),
+        Value::String(s) => match s.strip_prefix('-') {
+            Some(digits) => (true, digits.to_string()),
+            None => (false, s.clone()),
+        },
+        _ => return malformed("expected an integer"),
+    };
+    if negative && !signed {
+        return malformed("negative unsigned integer");
+    }
+    let (radix, digits) = match digits.strip_prefix("0x") {
+        Some(hex) => (16, hex),
+        None => (10, digits.as_str()),
+    };
+    if digits.is_empty() {
+        return malformed("empty integer");
+    }
+    let mut word = [0u8; 32];
+    for digit in digits.chars() {
+        let mut carry = digit.to_digit(radix).ok_or_else(|| EthereumError::Malformed("bad digit".into()))?;
+        for byte in word.iter_mut().rev() {
+            let n = *byte as u32 * radix + carry;
+            *byte = n as u8;
+            carry = n >> 8;
+        }
+        if carry != 0 {
+            return Err(AmountError::Overflow.into());
+        }
+    }
+    if negative {
+        // two's complement
+        let mut carry = 1;
+        for byte in word.iter_mut().rev() {
+            let n = (!*byte) as u16 + carry;
+            *byte = n as u8;
+            carry = n >> 8;
+        }
+    }
+    Ok(word)
+}
+
+impl<'a> TypedData<'a> {
+    fn fields(&self, ty: &str) -> Option<&'a Vec<Value>> {
+        self.types.get(ty).and_then(Value::as_array)
+    }
+
+    // the struct types ty refers to, directly or not
+    fn dependencies(&self, ty: &str, found: &mut Vec<String>) -> Result<()> {
+        let ty = ty.split('[').next().unwrap();
+        let Some(fields) = self.fields(ty) else { return Ok(()) };
+        if found.iter().any(|dependency| dependency == ty) {
+            return Ok(());
+        }
+        found.push(ty.to_string());
+        for field_type in fields {
+            let field_type = field(field_type, "type")?.as_str().unwrap_or_default();
+            self.dependencies(field_type, found)?;
+        }
+        Ok(())
+    }
+
+    fn encode_type(&self, ty: &str) -> Result<String> {
+        let mut dependencies = Vec::new();
+        self.dependencies(ty, &mut dependencies)?;
+        dependencies[1..].sort();
+        let mut encoded = String::new();
+        for dependency in &dependencies {
+            let fields = self
+                .fields(dependency)
+                .unwrap()
+                .iter()
+                .map(|f| Ok(format!("{} {}", field(f, "type")?.as_str().unwrap_or_default(), field(f, "name")?.as_str().unwrap_or_default())))
+                .collect::<Result<Vec<_>>>()?;
+            encoded += &format!("{}({})", dependency, fields.join(","));
+        }
+        Ok(encoded)
+    }
+
+    fn hash_struct(&self, ty: &str, value: &Value) -> Result<[u8; 32]> {
+        let fields = self.fields(ty).ok_or_else(|| EthereumError::Malformed(format!("unknown type {}", ty)))?;
+        let mut encoded = keccak(self.encode_type(ty)?.as_bytes()).to_vec();
+        for f in fields {
+            let name = field(f, "name")?.as_str().unwrap_or_default();
+            let field_type = field(f, "type")?.as_str().unwrap_or_default();
+            encoded.extend(self.encode_value(field_type, field(value, name)?)?);
+        }
+        Ok(keccak(&encoded))
+    }
+
+    fn encode_value(&self, ty: &str, value: &Value) -> Result<[u8; 32]> {
+        if let Some(element) = ty.strip_suffix(']') {
+            let (element, len) = element.rsplit_once('[').ok_or_else(|| EthereumError::Malformed(ty.to_string()))?;
+            let items = value.as_array().ok_or_else(|| EthereumError::Malformed(format!("expected an array for {}", ty)))?;
+            if !len.is_empty() && len.parse() != Ok(items.len()) {
+                return malformed(format!("expected {} items for {}", len, ty));
+            }
+            let mut encoded = Vec::new();
+            for item in items {
+                encoded.extend(self.encode_value(element, item)?);
+            }
+            return Ok(keccak(&encoded));
+        }
+        if self.fields(ty).is_some() {
+            return self.hash_struct(ty, value);
+        }
+        let mut word = [0u8; 32];
+        match ty {
+            "string" => return Ok(keccak(value.as_str().ok_or_else(|| EthereumError::Malformed("expected a string".into()))?.as_bytes())),
+            "bytes" => return Ok(keccak(&hex_bytes(value)?)),
+            "bool" => word[31] = value.as_bool().ok_or_else(|| EthereumError::Malformed("expected a bool".into()))? as u8,
+            "address" => {
+                let address = hex_bytes(value)?;
+                if address.len() != 20 {
+                    return malformed("address isn't 20 bytes");
+                }
+                word[12..].copy_from_slice(&address);
+            }
+            _ if ty.starts_with("bytes") => {
+                let len: usize = ty[5..].parse().or_else(malformed)?;
+                if !(1..=32).contains(&len) {
+                    return malformed(format!("unknown type {}", ty));
+                }
+                let bytes = hex_bytes(value)?;
+                if bytes.len() != len {
+                    return malformed(format!("expected {}", ty));
+                }
+                word[..bytes.len()].copy_from_slice(&bytes);
+            }
+            _ if ty.starts_with("uint") => word = word_of(value, false, &ty[4..])?,
+            _ if ty.starts_with("int") => word = word_of(value, true, &ty[3..])?,
+            _ => return malformed(format!("unknown type {}", ty)),
+        }
+        Ok(word)
+    }
+}
+
+// a uintN or intN value, checking it has at most N bits
+fn word_of(value: &Value, signed: bool, bits: &str) -> Result<[u8; 32]> {
+    let bits: usize = if bits.is_empty() { 256 } else { bits.parse().or_else(malformed)? };
+    if bits == 0 || bits > 256 || bits & 7 != 0 {
+        return malformed("bad integer size");
+    }
+    let word = word(value, signed)?;
+    let (high, low) = word.split_at(32 - bits / 8);
+    let fill = if signed && low[0] & 0x80 != 0 { 0xff } else { 0 };
+    if high.iter().any(|b| *b != fill) {
+        return Err(AmountError::Overflow.into());
+    }
+    Ok(word)
+}
+
+struct ParsedTypedData {
+    typed: Value,
+}
+
+impl ParsedTypedData {
+    fn parse(json: &str) -> Result<Self> {
+        let typed: Value = serde_json::from_str(json).or_else(malformed)?;
+        field(&typed, "types")?.as_object().ok_or_else(|| EthereumError::Malformed("types isn't an object".into()))?;
+        field(&typed, "primaryType")?.as_str().ok_or_else(|| EthereumError::Malformed("primaryType isn't a string".into()))?;
+        field(&typed, "domain")?;
+        field(&typed, "message")?;
+        Ok(ParsedTypedData { typed })
+    }
+
+    fn parts(&self) -> (TypedData<'_>, &str, &Value, &Value) {
+        let types = TypedData { types: self.typed["types"].as_object().unwrap() };
+        (types, self.typed["primaryType"].as_str().unwrap(), &self.typed["domain"], &self.typed["message"])
+    }
+
+    fn signing_hash(&self) -> Result<[u8; 32]> {
+        let (types, primary_type, domain, message) = self.parts();
+        let mut signed = vec![0x19, 0x01];
+        signed.extend(types.hash_struct("EIP712Domain", domain)?);
+        signed.extend(types.hash_struct(primary_type, message)?);
+        Ok(keccak(&signed))
+    }
+
+    fn transfer(&self) -> Result<EthereumTransfer> {
+        let (types, primary_type, domain, message) = self.parts();
+        // the fields are typed, check they have the types the transfer reads
+        // them as
+        let field_type = |name: &str| {
+            let fields = types.fields(primary_type)?;
+            let f = fields.iter().find(|f| f.get("name").and_then(Value::as_str) == Some(name))?;
+            f.get("type").and_then(Value::as_str)
+        };
+        if field_type("to") != Some("address") || field_type("value") != Some("uint256") {
+            return Err(EthereumError::UnsupportedCall);
+        }
+        if let Some(operation) = field_type("operation") {
+            // 1 is a delegate call, which runs arbitrary code as the sender
+            if types.encode_value(operation, field(message, "operation")?)? != [0; 32] {
+                return Err(EthereumError::UnsupportedCall);
+            }
+        }
+        if let Some(gas_price) = field_type("gasPrice") {
+            // Safe pays gas refunds out of the wallet
+            if types.encode_value(gas_price, field(message, "gasPrice")?)? != [0; 32] {
+                return Err(EthereumError::UnsupportedCall);
+            }
+        }
+        let data = match field_type("data") {
+            Some("bytes") => hex_bytes(field(message, "data")?)?,
+            Some(_) => return Err(EthereumError::UnsupportedCall),
+            None => Vec::new(),
+        };
+        let chain_id = match domain.get("chainId") {
+            Some(chain_id) => {
+                let chain_id = u128_from_be(&word(chain_id, false)?)?;
+                Some(u64::try_from(chain_id).or_else(|_| malformed("chain id too large"))?)
+            }
+            None => None,
+        };
+        let to = hex_bytes(field(message, "to")?)?.try_into().or_else(|_| malformed("to isn't 20 bytes"))?;
+        let value = u128_from_be(&word(field(message, "value")?, false)?)?;
+        transfer(chain_id, to, value, &data)
+    }
+}
+
+impl EthereumTransaction {
+    /// What the transaction sends, refusing anything but ether and ERC-20
+    /// transfers.
+    pub fn decode(&self) -> Result<EthereumTransfer> {
+        match self {
+            EthereumTransaction::Eip1559(encoded) => decode_eip1559(encoded),
+            EthereumTransaction::TypedData(json) => ParsedTypedData::parse(json)?.transfer(),
+        }
+    }
+
+    /// The signed message, see the module docs. This only checks the
+    /// encoding, decode checks what the transaction does.
+    pub fn signing_hash(&self) -> Result<[u8; 32]> {
+        match self {
+            EthereumTransaction::Eip1559(encoded) => Ok(keccak(encoded)),
+            EthereumTransaction::TypedData(json) => ParsedTypedData::parse(json)?.signing_hash(),
+        }
+    }
+}
+
+/// The asset name of an ERC-20 token in constraint limits, its contract
+/// address as lowercase 0x hex.
+pub fn erc20_asset(token: &[u8; 20]) -> String {
+    format!("0x{}", hex::encode(token))
+}
+
+impl Constraint {
+    /// Whether the ether and tokens the transaction sends are within the
+    /// constraint's limits. ERC-20 amounts are in base units, so they are
+    /// read with the decimals of the constraint's limit for the token.
+    pub fn check_ethereum(&self, transfer: &EthereumTransfer) -> Result<()> {
+        if transfer.value > 0 {
+            self.check(&Amount::new(ETHER_ASSET, transfer.value, ETHER_DECIMALS))?;
+        }
+        if let Some(erc20) = &transfer.erc20 {
+            let asset = erc20_asset(&transfer.to);
+            let limit = self.limit(&asset).ok_or(AmountError::NoLimit(asset))?;
+            self.check(&Amount { value: erc20.amount, ..limit })?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+//! OIDC identities as a second factor: the constraint names an issuer, a
+//! client ID, the hash of an account's subject and the issuer's key set, and
+//! each message needs an ID token of that account whose nonce is the
+//! message's hash. The guest checks the token and only commits the issuer,
+//! so the signer never learns whose account it is.
+//!
+//! Tokens are verified as the fork's examples/jwt-validator does (RS256 over
+//! a JWK), plus ES256, with the rsa and p256 crates directly.
+use base64::prelude::*;
+use p256::ecdsa::signature::Verifier;
+use rsa::{BigUint, Pkcs1v15Sign, RsaPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use crate::ApprovalError;
+
+/// The account a constraint requires ID tokens of.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identity {
+    /// the tokens' iss, ie: https://accounts.google.com
+    pub issuer: String,
+    /// the client ID the tokens are issued to (aud), so tokens the account
+    /// got for other applications don't pass
+    pub audience: String,
+    /// hex SHA-256 of the account's sub
+    pub subject_hash: String,
+    /// hex SHA-256 of the issuer's JWKS document, as IdentityApproval::jwks
+    pub jwks_digest: String,
+}
+
+/// An ID token approving a message, with the issuer's key set to check it
+/// against.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentityApproval {
+    /// the compact JWT
+    pub token: String,
+    /// the JWKS document whose digest the constraint registers, byte for
+    /// byte
+    pub jwks: String,
+}
+
+#[derive(Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    #[serde(default)]
+    kid: Option<String>,
+    // RSA
+    #[serde(default)]
+    n: Option<String>,
+    #[serde(default)]
+    e: Option<String>,
+    // EC
+    #[serde(default)]
+    crv: Option<String>,
+    #[serde(default)]
+    x: Option<String>,
+    #[serde(default)]
+    y: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    #[serde(default)]
+    kid: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    iss: String,
+    sub: String,
+    // a string or an array of them
+    aud: serde_json::Value,
+    #[serde(default)]
+    nonce: Option<String>,
+}
+
+fn base64url(part: &str) -> Option<Vec<u8>> {
+    BASE64_URL_SAFE_NO_PAD.decode(part).ok()
+}
+
+impl Jwk {
+    fn verifies(&self, signing_input: &[u8], signature: &[u8]) -> bool {
+        match self.kty.as_str() {
+            "RSA" => {
+                let (Some(n), Some(e)) = (self.n.as_deref().and_then(base64url), self.e.as_deref().and_then(base64url)) else {
+                    return false;
+                };
+                let Ok(key) = RsaPublicKey::new(BigUint::from_bytes_be(&n), BigUint::from_bytes_be(&e)) else {
+                    return false;
+                };
+                key.verify(Pkcs1v15Sign::new::<Sha256>(), &Sha256::digest(signing_input), signature).is_ok()
+            }
+            "EC" if self.crv.as_deref() == Some("P-256") => {
+                let (Some(x), Some(y)) = (self.x.as_deref().and_then(base64url), self.y.as_deref().and_then(base64url)) else {
+                    return false;
+                };
+                let point = [&[0x04], &x[..], &y[..]].concat();
+                let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(&point);
+                // JWS signatures are r || s, not DER
+                let signature = p256::ecdsa::Signature::from_slice(signature);
+                matches!((key, signature), (Ok(key), Ok(signature)) if key.verify(signing_input, &signature).is_ok())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The nonce of an ID token approving a message: the hex SHA-256 of its
+/// signed bytes, the transaction hash.
+pub fn identity_nonce(signed_bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(signed_bytes))
+}
+
+// expiry isn't checked: the nonce ties the token to one message, which
+// expires on its own
+pub(crate) fn check_identity(identity: &Identity, signed_bytes: &[u8], approval: &IdentityApproval) -> Result<(), ApprovalError> {
+    if !hex::encode(Sha256::digest(approval.jwks.as_bytes())).eq_ignore_ascii_case(&identity.jwks_digest) {
+        return Err(ApprovalError::JwksMismatch);
+    }
+    let jwks: Jwks = serde_json::from_str(&approval.jwks).map_err(|_| ApprovalError::JwksMalformed)?;
+
+    let parts: Vec<&str> = approval.token.split('.').collect();
+    let [header, payload, signature] = parts[..] else {
+        return Err(ApprovalError::IdTokenMalformed);
+    };
+    let header: Header = base64url(header)
+        .and_then(|header| serde_json::from_slice(&header).ok())
+        .ok_or(ApprovalError::IdTokenMalformed)?;
+    let claims: Claims = base64url(payload)
+        .and_then(|payload| serde_json::from_slice(&payload).ok())
+        .ok_or(ApprovalError::IdTokenMalformed)?;
+    let signature = base64url(signature).ok_or(ApprovalError::IdTokenMalformed)?;
+
+    let kty = match header.alg.as_str() {
+        "RS256" => "RSA",
+        "ES256" => "EC",
+        _ => return Err(ApprovalError::IdTokenAlgorithmUnsupported),
+    };
+    let keys: Vec<&Jwk> =
+        jwks.keys.iter().filter(|key| key.kty == kty && (header.kid.is_none() || key.kid == header.kid)).collect();
+    if keys.is_empty() {
+        return Err(ApprovalError::IdTokenKeyUnknown);
+    }
+    let signing_input = &approval.token.as_bytes()[..approval.token.rfind('.').unwrap()];
+    if !keys.iter().any(|key| key.verifies(signing_input, &signature)) {
+        return Err(ApprovalError::IdTokenSignatureInvalid);
+    }
+
+    if claims.iss != identity.issuer {
+        return Err(ApprovalError::IdTokenIssuerMismatch);
+    }
+    let audience = serde_json::Value::String(identity.audience.clone());
+    if claims.aud != audience && !claims.aud.as_array().is_some_and(|aud| aud.contains(&audience)) {
+        return Err(ApprovalError::IdTokenAudienceMismatch);
+    }
+    if !hex::encode(Sha256::digest(claims.sub.as_bytes())).eq_ignore_ascii_case(&identity.subject_hash) {
+        return Err(ApprovalError::IdTokenSubjectMismatch);
+    }
+    match claims.nonce == Some(identity_nonce(signed_bytes)) {
+        true => Ok(()),
+        false => Err(ApprovalError::IdTokenNonceMismatch),
+    }
+}
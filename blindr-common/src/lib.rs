@@ -1,21 +1,117 @@
 use serde::{Serialize, Deserialize};
 use sha2::{Digest as _, Sha256};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+mod amount;
+mod approval;
+pub mod bitcoin;
+mod check;
+mod identity;
+pub mod ethereum;
+mod presentation;
+
+pub use amount::{Amount, AmountError};
+pub use approval::{
+    passkey_challenge, totp, ApprovalError, Approvals, Passkey, PasskeyAssertion, TotpApproval, TOTP_DIGITS, TOTP_PERIOD,
+};
+pub use bitcoin::{BitcoinPolicy, BitcoinTransaction};
+pub use ethereum::EthereumTransaction;
+pub use check::{AuthenticateTransaction, TransactionCheck, TransactionError};
+pub use identity::{identity_nonce, Identity, IdentityApproval};
+pub use presentation::{Presentation, PresentationError};
+
+/// The current Transaction version. JSON without a version (or any of the
+/// fields added with it) reads as this version with the defaults.
+pub const TRANSACTION_VERSION: u32 = 1;
+
+/// The layout of the zk guest's journal, committed first so verifiers can
+/// accept guests of several versions (see blindr_zk::ImageRegistry). Bump it
+/// whenever the guest commits something else.
+pub const GUEST_VERSION: u32 = 3;
+
+/// The most transactions one batch proof (and one /blind-sign-batch request)
+/// covers.
+pub const MAX_BATCH: usize = 16;
+
+fn transaction_version() -> u32 {
+    TRANSACTION_VERSION
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Transaction {
+    #[serde(default = "transaction_version")]
+    pub version: u32,
     pub sender: String,
     pub receiver: String,
-    pub amount: u64,
+    // a bare integer reads as whole units of the native asset
+    pub amount: Amount,
+    // distinguishes otherwise identical transfers
+    #[serde(default)]
+    pub nonce: u64,
+    // unix seconds, the last second the transaction is valid in
+    #[serde(default)]
+    pub valid_until: Option<u64>,
+    #[serde(default)]
+    pub memo: String,
+    // the chain (or other domain) the transaction is meant for
+    #[serde(default)]
+    pub domain: String,
 }
 
 impl Transaction {
+    pub fn new(sender: String, receiver: String, amount: Amount) -> Self {
+        Transaction {
+            version: TRANSACTION_VERSION,
+            sender,
+            receiver,
+            amount,
+            nonce: 0,
+            valid_until: None,
+            memo: String::new(),
+            domain: String::new(),
+        }
+    }
+
     pub fn from_str(s: &str) -> Self {
         serde_json::from_str(s).unwrap()
     }
 
+    /// The bytes that get blinded and signed. Every field, the version
+    /// included, is part of the message.
     pub fn message(&self) -> Vec<u8> {
         serde_json::to_string(&self).unwrap().as_bytes().to_vec()
     }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        matches!(self.valid_until, Some(valid_until) if now > valid_until)
+    }
+
+    /// The field's value as JSON, as it is in the message: `"bob"` for the
+    /// receiver, `{"asset":"","value":"50","decimals":0}` for the amount.
+    /// None for a field transactions don't have.
+    pub fn attribute(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "version" => serde_json::to_string(&self.version),
+            "sender" => serde_json::to_string(&self.sender),
+            "receiver" => serde_json::to_string(&self.receiver),
+            "amount" => serde_json::to_string(&self.amount),
+            "nonce" => serde_json::to_string(&self.nonce),
+            "valid_until" => serde_json::to_string(&self.valid_until),
+            "memo" => serde_json::to_string(&self.memo),
+            "domain" => serde_json::to_string(&self.domain),
+            _ => return None,
+        };
+        value.ok()
+    }
+}
+
+/// Anything the guest can check against a constraint and get blind signed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Message {
+    Transaction(Transaction),
+    // signed as its tx_commitment
+    Bitcoin(BitcoinTransaction),
+    // signed as its keccak signing hash
+    Ethereum(EthereumTransaction),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -24,16 +120,114 @@ pub struct Auth(pub Vec<(String, String)>);
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Constraint {
     pub auth: Auth,  // must be equal
-    pub withdrawal_limit: u64
+    // in whole units of the native asset
+    pub withdrawal_limit: u64,
+    // per-asset limits, the first one for an asset counts
+    #[serde(default)]
+    pub limits: Vec<Amount>,
+    // which outputs bitcoin transactions may pay
+    #[serde(default)]
+    pub bitcoin: Option<BitcoinPolicy>,
+    // hex ed25519 public key of a device that must approve each message,
+    // see check_approvals
+    #[serde(default)]
+    pub device_key: Option<String>,
+    // hex SHA-256 of a TOTP secret, replacing a static password in auth
+    #[serde(default)]
+    pub totp_secret_hash: Option<String>,
+    // a WebAuthn credential approving each message
+    #[serde(default)]
+    pub passkey: Option<Passkey>,
+    // an OIDC account approving each message, its issuer is public
+    #[serde(default)]
+    pub identity: Option<Identity>,
+}
+
+// what gets hashed: constraints without per-asset limits keep the hash they
+// had before there were any
+#[derive(Serialize)]
+struct HashedConstraint<'a> {
+    auth: &'a Auth,
+    withdrawal_limit: u64,
+    #[serde(skip_serializing_if = "<[Amount]>::is_empty")]
+    limits: &'a [Amount],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bitcoin: &'a Option<BitcoinPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device_key: &'a Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    totp_secret_hash: &'a Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    passkey: &'a Option<Passkey>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    identity: &'a Option<Identity>,
 }
 
 impl Constraint {
+    pub fn new(auth: Auth, withdrawal_limit: u64) -> Self {
+        Constraint {
+            auth,
+            withdrawal_limit,
+            limits: Vec::new(),
+            bitcoin: None,
+            device_key: None,
+            totp_secret_hash: None,
+            passkey: None,
+            identity: None,
+        }
+    }
+
     pub fn from_str(s: &str) -> Self {
         serde_json::from_str(s).unwrap()
     }
 
     pub fn hash(&self) -> [u8; 32] {
-        let constraint_string = serde_json::to_string(self).unwrap();
-        Sha256::digest(&constraint_string.as_bytes()).as_slice().try_into().unwrap()
+        let hashed = HashedConstraint {
+            auth: &self.auth,
+            withdrawal_limit: self.withdrawal_limit,
+            limits: &self.limits,
+            bitcoin: &self.bitcoin,
+            device_key: &self.device_key,
+            totp_secret_hash: &self.totp_secret_hash,
+            passkey: &self.passkey,
+            identity: &self.identity,
+        };
+        let constraint_string = serde_json::to_string(&hashed).unwrap();
+        Sha256::digest(&constraint_string.as_bytes()).into()
+    }
+
+    /// The limit for the amount's asset. The native asset falls back to
+    /// withdrawal_limit.
+    pub fn limit(&self, asset: &str) -> Option<Amount> {
+        match self.limits.iter().find(|limit| limit.asset == asset) {
+            Some(limit) => Some(limit.clone()),
+            None if asset.is_empty() => Some(Amount::native(self.withdrawal_limit)),
+            None => None,
+        }
+    }
+
+    /// Whether the amount is within the limit for its asset. Assets without
+    /// a limit are refused rather than compared against another asset's.
+    pub fn check(&self, amount: &Amount) -> Result<(), AmountError> {
+        let limit = self.limit(&amount.asset).ok_or_else(|| AmountError::NoLimit(amount.asset.clone()))?;
+        match amount.checked_cmp(&limit)? {
+            std::cmp::Ordering::Greater => Err(AmountError::LimitExceeded),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether each amount, and each asset's total, is within the limit, so
+    /// a batch can't split a withdrawal over the limit into several under
+    /// it.
+    pub fn check_batch(&self, amounts: &[Amount]) -> Result<(), AmountError> {
+        let mut totals: Vec<Amount> = Vec::new();
+        for amount in amounts {
+            self.check(amount)?;
+            match totals.iter_mut().find(|total| total.asset == amount.asset) {
+                Some(total) => *total = total.checked_add(amount)?,
+                None => totals.push(amount.clone()),
+            }
+        }
+        totals.iter().try_for_each(|total| self.check(total))
     }
 }
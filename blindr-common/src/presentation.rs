@@ -0,0 +1,87 @@
+//! Showing a signed transaction without revealing the signature: blindr-zk's
+//! presentation guest checks the signature and commits a Presentation, which
+//! has the signer's public key and only what the holder chose to disclose.
+//!
+//! Another guest can take a presentation as an assumption, with
+//! `env::verify(PRESENT_ID, &risc0_zkvm::serde::to_vec(&presentation)?)` in
+//! the guest and the presentation's receipt added with
+//! `ExecutorEnv::builder().add_assumption(..)` on the host.
+use std::fmt;
+use blindsign::signature::WiredUnblindedSigData;
+use curve25519_dalek::ristretto::CompressedRistretto;
+use serde::{Deserialize, Serialize};
+use crate::{Transaction, GUEST_VERSION};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PresentationError {
+    SignatureMalformed,
+    PublicKeyMalformed,
+    SignatureInvalid,
+    UnknownAttribute(String),
+}
+
+impl fmt::Display for PresentationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PresentationError::SignatureMalformed => write!(f, "malformed signature"),
+            PresentationError::PublicKeyMalformed => write!(f, "public key is not a valid ristretto point"),
+            PresentationError::SignatureInvalid => write!(f, "signature does not authenticate the transaction"),
+            PresentationError::UnknownAttribute(name) => write!(f, "transactions have no attribute {:?}", name),
+        }
+    }
+}
+
+impl std::error::Error for PresentationError {}
+
+/// What the presentation guest commits.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Presentation {
+    /// the journal version, GUEST_VERSION
+    pub version: u32,
+    pub public_key: [u8; 32],
+    /// chosen by the verifier, so a presentation can't be replayed to another
+    pub challenge: [u8; 32],
+    /// (name, value) of the disclosed fields, see Transaction::attribute
+    pub attributes: Vec<(String, String)>,
+    /// the signature's blindsign nullifier, to refuse showing it twice
+    pub nullifier: Option<[u8; 32]>,
+}
+
+impl Presentation {
+    /// Checks that the signature authenticates the transaction under the
+    /// public key (S*P == e*Qs + R with e = H(R || message)) and presents it
+    /// with the given attributes.
+    pub fn present(
+        transaction: &Transaction,
+        signature: &WiredUnblindedSigData,
+        public_key: &[u8; 32],
+        challenge: &[u8; 32],
+        attributes: &[String],
+        nullifier: bool,
+    ) -> Result<Self, PresentationError> {
+        let signature = signature.to_internal_format().map_err(|_| PresentationError::SignatureMalformed)?;
+        let signer = CompressedRistretto(*public_key).decompress().ok_or(PresentationError::PublicKeyMalformed)?;
+        if !signature.msg_authenticate::<(), _>(signer, transaction.message()) {
+            return Err(PresentationError::SignatureInvalid);
+        }
+        let attributes = attributes
+            .iter()
+            .map(|name| match transaction.attribute(name) {
+                Some(value) => Ok((name.clone(), value)),
+                None => Err(PresentationError::UnknownAttribute(name.clone())),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Presentation {
+            version: GUEST_VERSION,
+            public_key: *public_key,
+            challenge: *challenge,
+            attributes,
+            nullifier: nullifier.then(|| *signature.nullifier().as_bytes()),
+        })
+    }
+
+    /// The disclosed value of the attribute.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(attribute, _)| attribute == name).map(|(_, value)| value.as_str())
+    }
+}
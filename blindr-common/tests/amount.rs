@@ -0,0 +1,61 @@
+use std::cmp::Ordering;
+use blindr_common::{Amount, AmountError, Auth, Constraint};
+
+fn usdc(value: u128) -> Amount {
+    Amount::new("usdc", value, 6)
+}
+
+#[test]
+fn test_checked_arithmetic() {
+    // 1.5 usdc against 2 whole usdc
+    let whole = Amount::new("usdc", 2, 0);
+    assert_eq!(usdc(1_500_000).checked_cmp(&whole), Ok(Ordering::Less));
+    assert_eq!(usdc(1_500_000).checked_add(&whole), Ok(usdc(3_500_000)));
+    assert_eq!(whole.checked_sub(&usdc(1_500_000)), Ok(usdc(500_000)));
+
+    assert_eq!(usdc(1).checked_sub(&usdc(2)), Err(AmountError::Overflow));
+    assert_eq!(usdc(u128::MAX).checked_add(&usdc(1)), Err(AmountError::Overflow));
+    assert_eq!(Amount::new("usdc", u128::MAX, 0).checked_cmp(&usdc(1)), Err(AmountError::Overflow));
+    assert!(matches!(usdc(1).checked_cmp(&Amount::native(1)), Err(AmountError::AssetMismatch { .. })));
+}
+
+#[test]
+fn test_json() {
+    let amount: Amount = serde_json::from_str("50").unwrap();
+    assert_eq!(amount, Amount::native(50));
+
+    let amount = Amount::new("usdc", u128::MAX, 6);
+    let json = serde_json::to_string(&amount).unwrap();
+    assert_eq!(json, format!(r#"{{"asset":"usdc","value":"{}","decimals":6}}"#, u128::MAX));
+    assert_eq!(serde_json::from_str::<Amount>(&json).unwrap(), amount);
+}
+
+#[test]
+fn test_constraint_limits() {
+    let legacy = Constraint::from_str(r#"{"auth":[["pet","rex"]],"withdrawal_limit":100}"#);
+    let constraint = Constraint::new(Auth(vec![("pet".to_string(), "rex".to_string())]), 100);
+    // constraints without per-asset limits hash as they always have
+    assert_eq!(legacy.hash(), constraint.hash());
+
+    let constraint = Constraint { limits: vec![usdc(10_000_000)], ..constraint };
+    assert_ne!(legacy.hash(), constraint.hash());
+
+    assert_eq!(constraint.check(&Amount::native(100)), Ok(()));
+    assert_eq!(constraint.check(&Amount::native(101)), Err(AmountError::LimitExceeded));
+    assert_eq!(constraint.check(&Amount::new("usdc", 10, 0)), Ok(()));
+    assert_eq!(constraint.check(&usdc(10_000_001)), Err(AmountError::LimitExceeded));
+    assert_eq!(constraint.check(&Amount::new("dai", 1, 0)), Err(AmountError::NoLimit("dai".to_string())));
+}
+
+#[test]
+fn test_constraint_batch() {
+    let constraint = Constraint { limits: vec![usdc(10_000_000)], ..Constraint::new(Auth(vec![]), 100) };
+
+    assert_eq!(constraint.check_batch(&[Amount::native(60), usdc(10_000_000), Amount::native(40)]), Ok(()));
+    // each is under the limit, the total isn't
+    assert_eq!(constraint.check_batch(&[Amount::native(60), Amount::native(41)]), Err(AmountError::LimitExceeded));
+    assert_eq!(constraint.check_batch(&[usdc(5_000_000), Amount::new("usdc", 6, 0)]), Err(AmountError::LimitExceeded));
+    assert_eq!(constraint.check_batch(&[Amount::native(101)]), Err(AmountError::LimitExceeded));
+    assert_eq!(constraint.check_batch(&[Amount::new("dai", 1, 0)]), Err(AmountError::NoLimit("dai".to_string())));
+    assert_eq!(constraint.check_batch(&[]), Ok(()));
+}
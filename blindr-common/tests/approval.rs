@@ -0,0 +1,137 @@
+use blindr_common::{
+    passkey_challenge, totp, ApprovalError, Approvals, Auth, Constraint, Message, Passkey, PasskeyAssertion, TotpApproval, Transaction,
+};
+use ed25519_dalek::{Signer, SigningKey};
+use sha2::Digest;
+
+fn device() -> SigningKey {
+    SigningKey::from_bytes(&[7; 32])
+}
+
+fn approve(device: &SigningKey, signed_bytes: &[u8]) -> Approvals {
+    Approvals { device_signature: Some(hex::encode(device.sign(signed_bytes).to_bytes())), ..Approvals::default() }
+}
+
+#[test]
+fn test_device_approval() {
+    let transaction = Transaction::from_str(r#"{"sender": "alice", "receiver": "bob", "amount": 50}"#);
+    let signed_bytes = Message::Transaction(transaction.clone()).signed_bytes().unwrap();
+    assert_eq!(signed_bytes, transaction.message());
+
+    let unprotected = Constraint::new(Auth(vec![]), 100);
+    let constraint = Constraint { device_key: Some(hex::encode(device().verifying_key().to_bytes())), ..unprotected.clone() };
+    // the device key is part of the constraint hash
+    assert_ne!(constraint.hash(), unprotected.hash());
+    assert_eq!(unprotected.check_approvals(&signed_bytes, &Approvals::default()), Ok(()));
+
+    assert_eq!(constraint.check_approvals(&signed_bytes, &approve(&device(), &signed_bytes)), Ok(()));
+    assert_eq!(constraint.check_approvals(&signed_bytes, &Approvals::default()), Err(ApprovalError::DeviceSignatureMissing));
+    let other = Transaction { receiver: "mallory".to_string(), ..transaction };
+    assert_eq!(
+        constraint.check_approvals(&other.message(), &approve(&device(), &signed_bytes)),
+        Err(ApprovalError::DeviceSignatureInvalid)
+    );
+    let stranger = SigningKey::from_bytes(&[8; 32]);
+    assert_eq!(
+        constraint.check_approvals(&signed_bytes, &approve(&stranger, &signed_bytes)),
+        Err(ApprovalError::DeviceSignatureInvalid)
+    );
+    let garbled = Approvals { device_signature: Some("00".to_string()), ..Approvals::default() };
+    assert_eq!(constraint.check_approvals(&signed_bytes, &garbled), Err(ApprovalError::DeviceSignatureInvalid));
+
+    let malformed = Constraint { device_key: Some("not hex".to_string()), ..unprotected };
+    assert_eq!(
+        malformed.check_approvals(&signed_bytes, &approve(&device(), &signed_bytes)),
+        Err(ApprovalError::DeviceKeyMalformed)
+    );
+}
+
+#[test]
+fn test_totp() {
+    // RFC 6238's SHA1 test vectors, truncated to 6 digits
+    let secret = b"12345678901234567890";
+    assert_eq!(totp(secret, 59 / 30), 287082);
+    assert_eq!(totp(secret, 1111111109 / 30), 81804);
+    assert_eq!(totp(secret, 1234567890 / 30), 5924);
+    assert_eq!(totp(secret, 20000000000 / 30), 353130);
+
+    let transaction = Transaction::from_str(r#"{"sender": "alice", "receiver": "bob", "amount": 50}"#);
+    let signed_bytes = transaction.message();
+    let unprotected = Constraint::new(Auth(vec![]), 100);
+    let secret_hash = hex::encode(sha2::Sha256::digest(secret));
+    let constraint = Constraint { totp_secret_hash: Some(secret_hash), ..unprotected.clone() };
+    assert_ne!(constraint.hash(), unprotected.hash());
+
+    let step = 1234567890 / 30;
+    let approval = TotpApproval { secret: hex::encode(secret), code: 5924, step };
+    let approvals = Approvals { totp: Some(approval.clone()), ..Approvals::default() };
+    assert_eq!(constraint.check_approvals(&signed_bytes, &approvals), Ok(()));
+    assert_eq!(constraint.totp_step(&approvals), Some(step));
+    assert_eq!(unprotected.totp_step(&approvals), None);
+
+    assert_eq!(constraint.check_approvals(&signed_bytes, &Approvals::default()), Err(ApprovalError::TotpMissing));
+    let wrong_step = Approvals { totp: Some(TotpApproval { step: step + 1, ..approval.clone() }), ..Approvals::default() };
+    assert_eq!(constraint.check_approvals(&signed_bytes, &wrong_step), Err(ApprovalError::TotpCodeInvalid));
+    let other_secret = TotpApproval { secret: hex::encode(b"01234567890123456789"), ..approval };
+    let other_secret = Approvals { totp: Some(other_secret), ..Approvals::default() };
+    assert_eq!(constraint.check_approvals(&signed_bytes, &other_secret), Err(ApprovalError::TotpSecretMismatch));
+}
+
+// what an authenticator returns, signing with the credential's key
+fn assert_passkey(credential: &p256::ecdsa::SigningKey, rp_id: &str, flags: u8, client_data_json: &str) -> PasskeyAssertion {
+    let mut authenticator_data = sha2::Sha256::digest(rp_id.as_bytes()).to_vec();
+    authenticator_data.push(flags);
+    authenticator_data.extend_from_slice(&[0, 0, 0, 1]);
+    let mut signed = authenticator_data.clone();
+    signed.extend_from_slice(&sha2::Sha256::digest(client_data_json.as_bytes()));
+    let signature: p256::ecdsa::Signature = credential.sign(&signed);
+    PasskeyAssertion {
+        authenticator_data: hex::encode(authenticator_data),
+        client_data_json: client_data_json.to_string(),
+        signature: hex::encode(signature.to_der().as_bytes()),
+    }
+}
+
+#[test]
+fn test_passkey_approval() {
+    let credential = p256::ecdsa::SigningKey::from_slice(&[9; 32]).unwrap();
+    let transaction = Transaction::from_str(r#"{"sender": "alice", "receiver": "bob", "amount": 50}"#);
+    let signed_bytes = transaction.message();
+    let client_data = |challenge: &str| {
+        format!(r#"{{"type":"webauthn.get","challenge":"{}","origin":"https://example.com","crossOrigin":false}}"#, challenge)
+    };
+    let challenge = passkey_challenge(&signed_bytes);
+    let approve = |assertion| Approvals { passkey: Some(assertion), ..Approvals::default() };
+
+    let unprotected = Constraint::new(Auth(vec![]), 100);
+    let public_key = hex::encode(credential.verifying_key().to_encoded_point(false).as_bytes());
+    let passkey = Passkey { public_key, rp_id: "example.com".to_string() };
+    let constraint = Constraint { passkey: Some(passkey.clone()), ..unprotected.clone() };
+    assert_ne!(constraint.hash(), unprotected.hash());
+
+    let assertion = assert_passkey(&credential, "example.com", 0x05, &client_data(&challenge));
+    assert_eq!(constraint.check_approvals(&signed_bytes, &approve(assertion.clone())), Ok(()));
+    assert_eq!(constraint.check_approvals(&signed_bytes, &Approvals::default()), Err(ApprovalError::PasskeyAssertionMissing));
+    let other = Transaction { receiver: "mallory".to_string(), ..transaction };
+    assert_eq!(
+        constraint.check_approvals(&other.message(), &approve(assertion.clone())),
+        Err(ApprovalError::PasskeyChallengeMismatch)
+    );
+
+    let phished = assert_passkey(&credential, "examp1e.com", 0x05, &client_data(&challenge));
+    assert_eq!(constraint.check_approvals(&signed_bytes, &approve(phished)), Err(ApprovalError::PasskeyRpMismatch));
+    let absent = assert_passkey(&credential, "example.com", 0x00, &client_data(&challenge));
+    assert_eq!(constraint.check_approvals(&signed_bytes, &approve(absent)), Err(ApprovalError::PasskeyUserNotPresent));
+    let created = client_data(&challenge).replace("webauthn.get", "webauthn.create");
+    let created = assert_passkey(&credential, "example.com", 0x05, &created);
+    assert_eq!(constraint.check_approvals(&signed_bytes, &approve(created)), Err(ApprovalError::PasskeyAssertionMalformed));
+    let stranger = p256::ecdsa::SigningKey::from_slice(&[10; 32]).unwrap();
+    let forged = assert_passkey(&stranger, "example.com", 0x05, &client_data(&challenge));
+    assert_eq!(constraint.check_approvals(&signed_bytes, &approve(forged)), Err(ApprovalError::PasskeySignatureInvalid));
+    // the signature covers the client data as sent
+    let reformatted = PasskeyAssertion { client_data_json: client_data(&challenge).replace(",", ", "), ..assertion.clone() };
+    assert_eq!(constraint.check_approvals(&signed_bytes, &approve(reformatted)), Err(ApprovalError::PasskeySignatureInvalid));
+
+    let malformed = Constraint { passkey: Some(Passkey { public_key: "00".to_string(), ..passkey }), ..unprotected };
+    assert_eq!(malformed.check_approvals(&signed_bytes, &approve(assertion)), Err(ApprovalError::PasskeyMalformed));
+}
@@ -0,0 +1,196 @@
+use blindr_common::bitcoin::{BitcoinError, BITCOIN_ASSET, BITCOIN_DECIMALS};
+use blindr_common::{Amount, AmountError, Auth, BitcoinPolicy, BitcoinTransaction, Constraint};
+
+// one input, 60000 sats to RECEIVER and 30000 sats to CHANGE
+const RAW_TX: &str = "020000000111111111111111111111111111111111111111111111111111111111111111110000000000fdffffff0260ea000000000000160014aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa3075000000000000160014bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb00000000";
+// its txid, 5dab5a18...b0addd, in internal byte order
+const TX_COMMITMENT: &str = "ddadb0272b14ae4a1a6efd38e0997e572d701bd705d53d38b5ca1d2c185aab5d";
+const RECEIVER: &str = "0014aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+const CHANGE: &str = "0014bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+
+fn push_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    assert!(bytes.len() < 0xfd);
+    out.push(bytes.len() as u8);
+    out.extend(bytes);
+}
+
+// a PSBT of the transaction with the given input maps, and an empty map per
+// output
+fn psbt(raw_tx: &[u8], inputs: &[Vec<(u8, Vec<u8>)>], outputs: usize) -> Vec<u8> {
+    let mut psbt = b"psbt\xff".to_vec();
+    push_bytes(&mut psbt, &[0x00]);
+    push_bytes(&mut psbt, raw_tx);
+    psbt.push(0x00);
+    for input in inputs {
+        for (key, value) in input {
+            push_bytes(&mut psbt, &[*key]);
+            push_bytes(&mut psbt, value);
+        }
+        psbt.push(0x00);
+    }
+    psbt.extend(vec![0x00; outputs]);
+    psbt
+}
+
+fn witness_utxo(value: u64, script_pubkey: &str) -> (u8, Vec<u8>) {
+    let mut utxo = value.to_le_bytes().to_vec();
+    push_bytes(&mut utxo, &hex::decode(script_pubkey).unwrap());
+    (0x01, utxo)
+}
+
+fn constraint(limit: u128, policy: BitcoinPolicy) -> Constraint {
+    let limits = vec![Amount::new(BITCOIN_ASSET, limit, BITCOIN_DECIMALS)];
+    Constraint { limits, bitcoin: Some(policy), ..Constraint::new(Auth(vec![]), 0) }
+}
+
+#[test]
+fn test_parse() {
+    let raw = BitcoinTransaction(hex::decode(RAW_TX).unwrap());
+    let from_psbt = BitcoinTransaction(psbt(&raw.0, &[vec![witness_utxo(100_000, RECEIVER)]], 2));
+
+    let parsed = raw.parse().unwrap();
+    assert_eq!(parsed, from_psbt.parse().unwrap());
+    assert_eq!(parsed.inputs.len(), 1);
+    assert_eq!(parsed.outputs.iter().map(|output| output.value).collect::<Vec<_>>(), vec![60_000, 30_000]);
+    assert_eq!(hex::encode(&parsed.outputs[0].script_pubkey), RECEIVER);
+
+    // without the spent outputs a raw transaction commits as its txid
+    assert_eq!(raw.sighashes(), Ok(None));
+    assert_eq!(hex::encode(raw.tx_commitment().unwrap()), TX_COMMITMENT);
+    assert_ne!(hex::encode(from_psbt.tx_commitment().unwrap()), TX_COMMITMENT);
+
+    let truncated = BitcoinTransaction(raw.0[..raw.0.len() - 1].to_vec());
+    assert!(matches!(truncated.parse(), Err(BitcoinError::Malformed(_))));
+}
+
+// BIP143's native P2WPKH example: a P2PK and a P2WPKH input
+const BIP143_TX: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+// and its P2SH-P2WPKH example
+const BIP143_P2SH_TX: &str = "0100000001db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a54770100000000feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac92040000";
+
+#[test]
+fn test_sighashes() {
+    let tx = hex::decode(BIP143_TX).unwrap();
+    let p2pk = witness_utxo(625_000_000, "2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac");
+    let p2wpkh = witness_utxo(600_000_000, "00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1");
+    let transaction = BitcoinTransaction(psbt(&tx, &[vec![p2pk.clone()], vec![p2wpkh.clone()]], 2));
+    let sighashes = transaction.sighashes().unwrap().unwrap();
+    // the legacy one is what the example's P2PK signature signs
+    assert_eq!(
+        sighashes.iter().map(hex::encode).collect::<Vec<_>>(),
+        vec![
+            "63cec688ee06a91e913875356dd4dea2f8e0f2a2659885372da2a37e32c7532e",
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670",
+        ]
+    );
+    // several inputs are committed to together
+    let commitment = transaction.tx_commitment().unwrap();
+    assert!(!sighashes.contains(&commitment));
+
+    // the same transaction spending a P2WPKH and a taproot output
+    let taproot = witness_utxo(600_000_000, "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798");
+    let p2wpkh_first = witness_utxo(625_000_000, "00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1");
+    let taproot_sighash = |input: Vec<(u8, Vec<u8>)>| {
+        let transaction = BitcoinTransaction(psbt(&tx, &[vec![p2wpkh_first.clone()], input], 2));
+        transaction.sighashes().map(|sighashes| hex::encode(sighashes.unwrap()[1]))
+    };
+    assert_eq!(
+        taproot_sighash(vec![taproot.clone()]),
+        Ok("c9439266fdbe1901d78093a6481bba4aaa46e1a3097e8b120a28952b6c7b528f".to_string())
+    );
+    assert_eq!(
+        taproot_sighash(vec![taproot.clone(), (0x03, vec![1, 0, 0, 0])]),
+        Ok("3ca800f3b00bbc7d5e6168b86b076beee8d2a27a5b9821672292ba528d76d75c".to_string())
+    );
+    assert_eq!(
+        taproot_sighash(vec![taproot, (0x03, vec![0x83, 0, 0, 0])]),
+        Err(BitcoinError::Malformed("sighash type is not ALL"))
+    );
+
+    let tx = hex::decode(BIP143_P2SH_TX).unwrap();
+    let p2sh = witness_utxo(1_000_000_000, "a9144733f37cf4db86fbc2efed2500b4f4e49f31202387");
+    let redeem_script = (0x04, hex::decode("001479091972186c449eb1ded22b78e40d009bdf0089").unwrap());
+    let transaction = BitcoinTransaction(psbt(&tx, &[vec![p2sh.clone(), redeem_script]], 2));
+    // a single input's sighash is the message itself
+    assert_eq!(
+        hex::encode(transaction.tx_commitment().unwrap()),
+        "64f3b0f4dd2bb3aa1ce8566d220cc74dda9df97d8490cc81d89d735c92e59fb6"
+    );
+    assert_eq!(
+        BitcoinTransaction(psbt(&tx, &[vec![p2sh.clone()]], 2)).tx_commitment(),
+        Err(BitcoinError::Malformed("p2sh input has no redeem script"))
+    );
+
+    // every input needs its utxo, and only SIGHASH_ALL covers every output
+    assert_eq!(
+        BitcoinTransaction(psbt(&hex::decode(BIP143_TX).unwrap(), &[vec![p2pk.clone()], vec![]], 2)).tx_commitment(),
+        Err(BitcoinError::Malformed("psbt input has no utxo"))
+    );
+    let anyone_can_pay = (0x03, 0x81u32.to_le_bytes().to_vec());
+    assert_eq!(
+        BitcoinTransaction(psbt(&hex::decode(BIP143_TX).unwrap(), &[vec![p2pk], vec![p2wpkh, anyone_can_pay]], 2)).tx_commitment(),
+        Err(BitcoinError::Malformed("sighash type is not ALL"))
+    );
+}
+
+#[test]
+fn test_non_witness_utxo() {
+    // spends RAW_TX's first output, 60000 sats to RECEIVER
+    let spent_txid = hex::decode(TX_COMMITMENT).unwrap();
+    let spend = |vout: u8| {
+        let mut tx = hex::decode("0200000001").unwrap();
+        tx.extend(&spent_txid);
+        tx.extend([vout, 0, 0, 0, 0x00, 0xff, 0xff, 0xff, 0xff, 0x01]);
+        tx.extend(50_000u64.to_le_bytes());
+        push_bytes(&mut tx, &hex::decode(CHANGE).unwrap());
+        tx.extend([0; 4]);
+        tx
+    };
+    let non_witness_utxo = (0x00, hex::decode(RAW_TX).unwrap());
+
+    // the spent output is read from the spent transaction
+    let from_utxo = BitcoinTransaction(psbt(&spend(0), &[vec![witness_utxo(60_000, RECEIVER)]], 1));
+    let from_tx = BitcoinTransaction(psbt(&spend(0), &[vec![non_witness_utxo.clone()]], 1));
+    assert_eq!(from_tx.tx_commitment().unwrap(), from_utxo.tx_commitment().unwrap());
+    assert_eq!(
+        BitcoinTransaction(psbt(&spend(2), &[vec![non_witness_utxo.clone()]], 1)).tx_commitment(),
+        Err(BitcoinError::Malformed("spent output out of range"))
+    );
+
+    // which must be the one the input names
+    let mut other_tx = hex::decode(RAW_TX).unwrap();
+    other_tx[0] = 0x01;
+    assert_eq!(
+        BitcoinTransaction(psbt(&spend(0), &[vec![(0x00, other_tx)]], 1)).tx_commitment(),
+        Err(BitcoinError::Malformed("non-witness utxo is not the spent transaction"))
+    );
+}
+
+#[test]
+fn test_check_bitcoin() {
+    let parsed = BitcoinTransaction(hex::decode(RAW_TX).unwrap()).parse().unwrap();
+    let policy = |receivers: &[&str]| BitcoinPolicy {
+        change_scripts: vec![CHANGE.to_string()],
+        receivers: receivers.iter().map(|receiver| receiver.to_string()).collect(),
+    };
+
+    // change doesn't count towards the limit
+    assert_eq!(constraint(60_000, policy(&[RECEIVER])).check_bitcoin(&parsed), Ok(()));
+    assert_eq!(constraint(60_000, policy(&[])).check_bitcoin(&parsed), Ok(()));
+    assert_eq!(
+        constraint(59_999, policy(&[RECEIVER])).check_bitcoin(&parsed),
+        Err(BitcoinError::Amount(AmountError::LimitExceeded))
+    );
+    let elsewhere = "0014cccccccccccccccccccccccccccccccccccccccc";
+    assert_eq!(
+        constraint(60_000, policy(&[elsewhere])).check_bitcoin(&parsed),
+        Err(BitcoinError::ReceiverNotAllowed(RECEIVER.to_string()))
+    );
+
+    // without a bitcoin limit there is nothing to compare against
+    let unlimited = Constraint { bitcoin: Some(policy(&[])), ..Constraint::new(Auth(vec![]), 1_000_000) };
+    assert_eq!(
+        unlimited.check_bitcoin(&parsed),
+        Err(BitcoinError::Amount(AmountError::NoLimit(BITCOIN_ASSET.to_string())))
+    );
+}
@@ -0,0 +1,194 @@
+use blindr_common::ethereum::{erc20_asset, EthereumError, ETHER_ASSET, ETHER_DECIMALS};
+use blindr_common::{Amount, AmountError, Auth, Constraint, EthereumTransaction};
+
+// 0.5 ether to 0xbb..bb on mainnet
+const ETHER_TX: &str = "02ef0107843b9aca00847735940082520894bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb8806f05b59d3b2000080c0";
+// 2.5 usdc to 0xbb..bb
+const USDC_TX: &str = "02f86c0180843b9aca00847735940082ea6094a0b86991c6218b36c1d19d4a2e9eb0ce3606eb4880b844a9059cbb000000000000000000000000bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb00000000000000000000000000000000000000000000000000000000002625a0c0";
+const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+fn eip1559(tx: &str) -> EthereumTransaction {
+    EthereumTransaction::Eip1559(hex::decode(tx).unwrap())
+}
+
+fn constraint(limits: Vec<Amount>) -> Constraint {
+    Constraint { limits, ..Constraint::new(Auth(vec![]), 0) }
+}
+
+#[test]
+fn test_eip1559() {
+    let transfer = eip1559(ETHER_TX).decode().unwrap();
+    assert_eq!(transfer.chain_id, Some(1));
+    assert_eq!(transfer.to, [0xbb; 20]);
+    assert_eq!(transfer.value, 500_000_000_000_000_000);
+    assert_eq!(transfer.erc20, None);
+
+    let transfer = eip1559(USDC_TX).decode().unwrap();
+    assert_eq!(erc20_asset(&transfer.to), USDC);
+    let erc20 = transfer.erc20.clone().unwrap();
+    assert_eq!((erc20.recipient, erc20.amount), ([0xbb; 20], 2_500_000));
+
+    let half_ether = Amount::new(ETHER_ASSET, 5, 1);
+    assert_eq!(constraint(vec![half_ether.clone()]).check_ethereum(&eip1559(ETHER_TX).decode().unwrap()), Ok(()));
+    assert_eq!(
+        constraint(vec![Amount::new(ETHER_ASSET, 1, ETHER_DECIMALS - 17)]).check_ethereum(&eip1559(ETHER_TX).decode().unwrap()),
+        Err(EthereumError::Amount(AmountError::LimitExceeded))
+    );
+    // ether limits don't cover tokens
+    assert_eq!(
+        constraint(vec![half_ether]).check_ethereum(&transfer),
+        Err(EthereumError::Amount(AmountError::NoLimit(USDC.to_string())))
+    );
+    assert_eq!(constraint(vec![Amount::new(USDC, 3_000_000, 6)]).check_ethereum(&transfer), Ok(()));
+    assert_eq!(
+        constraint(vec![Amount::new(USDC, 2, 0)]).check_ethereum(&transfer),
+        Err(EthereumError::Amount(AmountError::LimitExceeded))
+    );
+}
+
+#[test]
+fn test_eip1559_refused() {
+    // an approve instead of a transfer
+    let approve = USDC_TX.replace("a9059cbb", "095ea7b3");
+    assert_eq!(eip1559(&approve).decode(), Err(EthereumError::UnsupportedCall));
+
+    // the nonce 7 encoded as a one byte string instead of a single byte
+    let non_canonical = ETHER_TX.replacen("02ef0107", "02f0018107", 1);
+    assert!(matches!(eip1559(&non_canonical).decode(), Err(EthereumError::Malformed(_))));
+
+    let truncated = &ETHER_TX[..ETHER_TX.len() - 2];
+    assert!(matches!(eip1559(truncated).decode(), Err(EthereumError::Malformed(_))));
+
+    // lists nested far deeper than an access list are refused, not recursed
+    // into until the stack overflows
+    // built back to front, each list's canonical length prefix wraps the rest
+    let mut nested = vec![0xc0];
+    for _ in 0..100_000 {
+        let len = nested.len().to_be_bytes();
+        let len = &len[len.iter().position(|b| *b != 0).unwrap()..];
+        match nested.len() {
+            0..=55 => nested.push(0xc0 + nested.len() as u8),
+            _ => {
+                nested.extend(len.iter().rev());
+                nested.push(0xf7 + len.len() as u8);
+            }
+        }
+    }
+    nested.push(0x02);
+    nested.reverse();
+    assert!(matches!(EthereumTransaction::Eip1559(nested).decode(), Err(EthereumError::Malformed(_))));
+}
+
+// the example from EIP-712
+const MAIL: &str = r#"{
+    "types": {
+        "EIP712Domain": [
+            {"name": "name", "type": "string"},
+            {"name": "version", "type": "string"},
+            {"name": "chainId", "type": "uint256"},
+            {"name": "verifyingContract", "type": "address"}
+        ],
+        "Person": [{"name": "name", "type": "string"}, {"name": "wallet", "type": "address"}],
+        "Mail": [
+            {"name": "from", "type": "Person"},
+            {"name": "to", "type": "Person"},
+            {"name": "contents", "type": "string"}
+        ]
+    },
+    "primaryType": "Mail",
+    "domain": {
+        "name": "Ether Mail",
+        "version": "1",
+        "chainId": 1,
+        "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+    },
+    "message": {
+        "from": {"name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"},
+        "to": {"name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"},
+        "contents": "Hello, Bob!"
+    }
+}"#;
+
+fn safe_tx(operation: u8) -> String {
+    format!(
+        r#"{{
+        "types": {{
+            "EIP712Domain": [
+                {{"name": "chainId", "type": "uint256"}},
+                {{"name": "verifyingContract", "type": "address"}}
+            ],
+            "SafeTx": [
+                {{"name": "to", "type": "address"}},
+                {{"name": "value", "type": "uint256"}},
+                {{"name": "data", "type": "bytes"}},
+                {{"name": "operation", "type": "uint8"}},
+                {{"name": "safeTxGas", "type": "uint256"}},
+                {{"name": "baseGas", "type": "uint256"}},
+                {{"name": "gasPrice", "type": "uint256"}},
+                {{"name": "gasToken", "type": "address"}},
+                {{"name": "refundReceiver", "type": "address"}},
+                {{"name": "nonce", "type": "uint256"}}
+            ]
+        }},
+        "primaryType": "SafeTx",
+        "domain": {{"chainId": "0x5", "verifyingContract": "0x1111111111111111111111111111111111111111"}},
+        "message": {{
+            "to": "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+            "value": "1000000000000000000",
+            "data": "0x",
+            "operation": {},
+            "safeTxGas": 0,
+            "baseGas": 0,
+            "gasPrice": "0",
+            "gasToken": "0x0000000000000000000000000000000000000000",
+            "refundReceiver": "0x0000000000000000000000000000000000000000",
+            "nonce": 3
+        }}
+    }}"#,
+        operation
+    )
+}
+
+#[test]
+fn test_eip712() {
+    let mail = EthereumTransaction::TypedData(MAIL.to_string());
+    assert_eq!(
+        hex::encode(mail.signing_hash().unwrap()),
+        "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+    );
+    // mail isn't a transfer
+    assert_eq!(mail.decode(), Err(EthereumError::UnsupportedCall));
+
+    let safe = EthereumTransaction::TypedData(safe_tx(0));
+    let transfer = safe.decode().unwrap();
+    assert_eq!((transfer.chain_id, transfer.to, transfer.value), (Some(5), [0xbb; 20], 1_000_000_000_000_000_000));
+    assert_eq!(constraint(vec![Amount::new(ETHER_ASSET, 1, 0)]).check_ethereum(&transfer), Ok(()));
+    assert_ne!(safe.signing_hash().unwrap(), EthereumTransaction::TypedData(safe_tx(1)).signing_hash().unwrap());
+
+    // delegate calls run arbitrary code
+    assert_eq!(EthereumTransaction::TypedData(safe_tx(1)).decode(), Err(EthereumError::UnsupportedCall));
+}
+
+fn bytes_field(ty: &str, len: usize) -> EthereumTransaction {
+    EthereumTransaction::TypedData(format!(
+        r#"{{
+        "types": {{"EIP712Domain": [{{"name": "chainId", "type": "uint256"}}], "Blob": [{{"name": "data", "type": "{}"}}]}},
+        "primaryType": "Blob",
+        "domain": {{"chainId": 1}},
+        "message": {{"data": "0x{}"}}
+    }}"#,
+        ty,
+        "11".repeat(len)
+    ))
+}
+
+#[test]
+fn test_eip712_bytes_sizes() {
+    assert!(bytes_field("bytes1", 1).signing_hash().is_ok());
+    assert!(bytes_field("bytes32", 32).signing_hash().is_ok());
+    assert!(matches!(bytes_field("bytes32", 31).signing_hash(), Err(EthereumError::Malformed(_))));
+    // fixed size byte strings are 1 to 32 bytes, a word
+    assert!(matches!(bytes_field("bytes33", 33).signing_hash(), Err(EthereumError::Malformed(_))));
+    assert!(matches!(bytes_field("bytes0", 0).signing_hash(), Err(EthereumError::Malformed(_))));
+    assert!(matches!(bytes_field("bytesx", 1).signing_hash(), Err(EthereumError::Malformed(_))));
+}
@@ -0,0 +1,129 @@
+use base64::prelude::*;
+use blindr_common::{identity_nonce, ApprovalError, Approvals, Auth, Constraint, Identity, IdentityApproval, Transaction};
+use rsa::{BigUint, Pkcs1v15Sign, RsaPrivateKey};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+// the fork's examples/jwt-validator RS256 key
+const RSA_KEY: &str = r#"{
+    "n": "zcQwXx3EevOSkfH0VSWqtfmWTL4c2oIzW6u83qKO1W7XjLgTqpryL5vNCaxbVTkpU-GZctit0n6kj570tfny_sy6pb2q9wlvFBmDVyD-nL5oNjP5s3qEfvy15Bl9vMGFf3zycqMaVg_7VRVwK5d8QzpnVC0AGT10QdHnyGCadfPJqazTuVRp1f3ecK7bg7596sgVb8d9Wpaz2XPykQPfphsEb40vcp1tPN95-eRCgA24PwfUaKYHQQFMEQY_atJWbffyJ91zsBRy8fEQdfuQVZIRVQgO7FTsmLmQAHxR1dl2jP8B6zonWmtqWoMHoZfa-kmTPB4wNHa8EaLvtQ1060qYFmQWWumfNFnG7HNq2gTHt1cN1HCwstRGIaU_ZHubM_FKH_gLfJPKNW0KWML9mQQzf4AVov0Yfvk89WxY8ilSRx6KodJuIKKqwVh_58PJPLmBqszEfkTjtyxPwP8X8xRXfSz-vTU6vESCk3O6TRknoJkC2BJZ_ONQ0U5dxLcx",
+    "e": "AQAB",
+    "d": "YuO1XZkYSwDRgauXQe6q1u8fET3S7x7g4N8uE49rdt7g3-O9q-Hwn_nQNiRr9o7Uslf7X8sL6txraQy7TdPUuSkaULpRNo2FoVLLoO2eACWwPtCG4n9wuvjnz7qCh9s3tfgOKxMA_riKkS8O7BxPH54rd7Ry1i6HN3TSYKYwxZxG4HFLhcewX6Q1KdGXdP7xVAsZ5lEpCQbhY5IKUzBZ5WIZpSTk10AadkVuwS622QT-9efk6PBWDyM48_udMdDo1HEcHsAdxrUMRdw_5uzVajQzZhNAmALXHCPT79P0qahzdYlUSHauT1XxU7z-KoCYVqt3z6epgYDcKmLzGkqIkSXUHxcVN-MTSGNET_dhio0tHG-jV3wB5jfsgayoIZCeTPF-F-nDwn8Cyz18uee_Y7U53NTtEXGqB9npZyu7SibTztwSeLs6zH965d1VTmUCxH8CWqizugfQY8ibNgVCd42naAuWbOmxYEjyelmHf_BS0Vb7NwpW9cuaODOjpjCz",
+    "p": "-TQVt9yl_0S0uvUM37L3WSDPkOn_gy34zpAEllhgx1HQUg_pVbqEDwKzEIpBlZfbrcszMlmiJhKL6q4y0_a6e3O5QnfB1vrGTjhLcfcaUK6o-I7bxabrpZmvLIsTqSdAgUijXe8yhQFIoCjc1MPD7icRPc-V7P9IYE2ls9X6sgo4lUZjQAuQtOo8ndlZ3uqP2sMKRR3CS7tHiF1r_zq_NXcf98Sve-1rRnqT6GpGcJRcvVFu2wy8TyCPMAvWh903",
+    "q": "02DUlUJrcTQ-mHMmg-V5qjxrtTKMmjqXpN0pgkXhM8_DWCrqKL9sXb1MKXQcbAZYr-lWmtBwzXeF4Qn66dRHpjlQLhSA947UxjuEtbhWx3wKGG460ZH026qcRr3QspcKZuiX2zISHb8suMl2lhDDSggCAjybs0l72pNHPIny9pucnwqc9ihrbeu68LlUpnQtS-Okt4j5ndVc1l1Vwv2PFt2PxrLmQkqdwRMla1F7r0vtgM7NIZz9XPszSrkxTILX"
+}"#;
+
+const ISSUER: &str = "https://accounts.example.com";
+const AUDIENCE: &str = "blindr-wallet";
+const SUBJECT: &str = "110169484474386276334";
+
+fn component(key: &Value, name: &str) -> BigUint {
+    BigUint::from_bytes_be(&BASE64_URL_SAFE_NO_PAD.decode(key[name].as_str().unwrap()).unwrap())
+}
+
+fn rsa_key() -> RsaPrivateKey {
+    let key: Value = serde_json::from_str(RSA_KEY).unwrap();
+    let primes = vec![component(&key, "p"), component(&key, "q")];
+    RsaPrivateKey::from_components(component(&key, "n"), component(&key, "e"), component(&key, "d"), primes).unwrap()
+}
+
+fn ec_key() -> p256::ecdsa::SigningKey {
+    p256::ecdsa::SigningKey::from_slice(&[11; 32]).unwrap()
+}
+
+// the issuer's key set, both keys
+fn jwks() -> String {
+    let rsa: Value = serde_json::from_str(RSA_KEY).unwrap();
+    let point = ec_key().verifying_key().to_encoded_point(false);
+    json!({ "keys": [
+        { "kty": "RSA", "kid": "rsa-1", "alg": "RS256", "use": "sig", "n": rsa["n"], "e": rsa["e"] },
+        {
+            "kty": "EC",
+            "kid": "ec-1",
+            "crv": "P-256",
+            "x": BASE64_URL_SAFE_NO_PAD.encode(point.x().unwrap()),
+            "y": BASE64_URL_SAFE_NO_PAD.encode(point.y().unwrap()),
+        },
+    ]})
+    .to_string()
+}
+
+fn token(alg: &str, kid: &str, claims: &Value) -> String {
+    let header = BASE64_URL_SAFE_NO_PAD.encode(json!({ "alg": alg, "kid": kid, "typ": "JWT" }).to_string());
+    let signing_input = format!("{}.{}", header, BASE64_URL_SAFE_NO_PAD.encode(claims.to_string()));
+    let signature = match alg {
+        "RS256" => rsa_key().sign(Pkcs1v15Sign::new::<Sha256>(), &Sha256::digest(&signing_input)).unwrap(),
+        _ => {
+            let signature: p256::ecdsa::Signature = p256::ecdsa::signature::Signer::sign(&ec_key(), signing_input.as_bytes());
+            signature.to_bytes().to_vec()
+        }
+    };
+    format!("{}.{}", signing_input, BASE64_URL_SAFE_NO_PAD.encode(signature))
+}
+
+fn claims(nonce: &str) -> Value {
+    json!({ "iss": ISSUER, "aud": AUDIENCE, "sub": SUBJECT, "nonce": nonce, "exp": 1_700_000_000u64 })
+}
+
+fn approve(token: String) -> Approvals {
+    Approvals { identity: Some(IdentityApproval { token, jwks: jwks() }), ..Approvals::default() }
+}
+
+#[test]
+fn test_identity_approval() {
+    let transaction = Transaction::from_str(r#"{"sender": "alice", "receiver": "bob", "amount": 50}"#);
+    let signed_bytes = transaction.message();
+    let nonce = identity_nonce(&signed_bytes);
+    assert_eq!(nonce, hex::encode(Sha256::digest(&signed_bytes)));
+
+    let unprotected = Constraint::new(Auth(vec![]), 100);
+    let identity = Identity {
+        issuer: ISSUER.to_string(),
+        audience: AUDIENCE.to_string(),
+        subject_hash: hex::encode(Sha256::digest(SUBJECT)),
+        jwks_digest: hex::encode(Sha256::digest(jwks())),
+    };
+    let constraint = Constraint { identity: Some(identity.clone()), ..unprotected.clone() };
+    assert_ne!(constraint.hash(), unprotected.hash());
+    let check = |approvals: &Approvals| constraint.check_approvals(&signed_bytes, approvals);
+
+    assert_eq!(check(&approve(token("RS256", "rsa-1", &claims(&nonce)))), Ok(()));
+    assert_eq!(check(&approve(token("ES256", "ec-1", &claims(&nonce)))), Ok(()));
+    let listed = json!({ "iss": ISSUER, "aud": ["other-app", AUDIENCE], "sub": SUBJECT, "nonce": nonce });
+    assert_eq!(check(&approve(token("ES256", "ec-1", &listed))), Ok(()));
+    assert_eq!(check(&Approvals::default()), Err(ApprovalError::IdentityMissing));
+
+    let other = Transaction { receiver: "mallory".to_string(), ..transaction };
+    let for_other = token("RS256", "rsa-1", &claims(&identity_nonce(&other.message())));
+    assert_eq!(check(&approve(for_other)), Err(ApprovalError::IdTokenNonceMismatch));
+    let mut wrong = claims(&nonce);
+    wrong["iss"] = json!("https://evil.example.com");
+    assert_eq!(check(&approve(token("RS256", "rsa-1", &wrong))), Err(ApprovalError::IdTokenIssuerMismatch));
+    let mut wrong = claims(&nonce);
+    wrong["aud"] = json!("other-app");
+    assert_eq!(check(&approve(token("RS256", "rsa-1", &wrong))), Err(ApprovalError::IdTokenAudienceMismatch));
+    let mut wrong = claims(&nonce);
+    wrong["sub"] = json!("someone else");
+    assert_eq!(check(&approve(token("RS256", "rsa-1", &wrong))), Err(ApprovalError::IdTokenSubjectMismatch));
+
+    // a token signed by the EC key but claiming the RSA one, and a tampered payload
+    let mislabeled = token("ES256", "rsa-1", &claims(&nonce));
+    assert_eq!(check(&approve(mislabeled)), Err(ApprovalError::IdTokenKeyUnknown));
+    let signed = token("RS256", "rsa-1", &claims(&nonce));
+    let parts: Vec<&str> = signed.split('.').collect();
+    let mut forged_claims = claims(&nonce);
+    forged_claims["exp"] = json!(u64::MAX);
+    let forged = format!("{}.{}.{}", parts[0], BASE64_URL_SAFE_NO_PAD.encode(forged_claims.to_string()), parts[2]);
+    assert_eq!(check(&approve(forged)), Err(ApprovalError::IdTokenSignatureInvalid));
+    for alg in ["none", "HS256"] {
+        let header = BASE64_URL_SAFE_NO_PAD.encode(json!({ "alg": alg }).to_string());
+        let unsigned = format!("{}.{}.", header, BASE64_URL_SAFE_NO_PAD.encode(claims(&nonce).to_string()));
+        assert_eq!(check(&approve(unsigned)), Err(ApprovalError::IdTokenAlgorithmUnsupported));
+    }
+    assert_eq!(check(&approve("not a token".to_string())), Err(ApprovalError::IdTokenMalformed));
+
+    // the key set is pinned by the constraint
+    let rotated = IdentityApproval { token: token("RS256", "rsa-1", &claims(&nonce)), jwks: jwks().replace("ec-1", "ec-2") };
+    let rotated = Approvals { identity: Some(rotated), ..Approvals::default() };
+    assert_eq!(check(&rotated), Err(ApprovalError::JwksMismatch));
+}
@@ -0,0 +1,90 @@
+use blindr_common::{
+    Amount, AuthenticateTransaction, Presentation, PresentationError, Transaction, TransactionCheck, TransactionError,
+    TRANSACTION_VERSION,
+};
+use blindsign::{
+    keypair::BlindKeypair,
+    request::BlindRequest,
+    session::BlindSession,
+    signature::{UnblindedSigData, WiredUnblindedSigData},
+};
+use curve25519_dalek::ristretto::RistrettoPoint;
+
+fn sign(transaction: &Transaction) -> (RistrettoPoint, UnblindedSigData) {
+    let keypair = BlindKeypair::generate().unwrap();
+    let (rp, session) = BlindSession::new().unwrap();
+    let (ep, request) = BlindRequest::new_specific_msg(&rp, transaction.message()).unwrap();
+    let sp = session.sign_ep(&ep, keypair.private()).unwrap();
+    (keypair.public(), request.gen_signed_msg(&sp).unwrap())
+}
+
+#[test]
+fn test_unversioned_json() {
+    let transaction = Transaction::from_str(r#"{"sender": "alice", "receiver": "bob", "amount": 50}"#);
+    assert_eq!(transaction, Transaction::new("alice".to_string(), "bob".to_string(), Amount::native(50)));
+    assert_eq!(transaction.version, TRANSACTION_VERSION);
+    assert!(!transaction.is_expired(u64::MAX));
+}
+
+#[test]
+fn test_authenticate_transaction() {
+    let transaction = Transaction {
+        nonce: 7,
+        valid_until: Some(1_000),
+        domain: "testnet".to_string(),
+        ..Transaction::new("alice".to_string(), "bob".to_string(), Amount::native(50))
+    };
+    let (public_key, signature) = sign(&transaction);
+    let check = |now: u64, domain: &str| TransactionCheck { now, domain: domain.to_string() };
+
+    assert_eq!(signature.authenticate_transaction(public_key, &transaction, &check(1_000, "testnet")), Ok(()));
+    assert_eq!(
+        signature.authenticate_transaction(public_key, &transaction, &check(1_001, "testnet")),
+        Err(TransactionError::Expired { valid_until: 1_000, now: 1_001 })
+    );
+    assert!(matches!(
+        signature.authenticate_transaction(public_key, &transaction, &check(0, "mainnet")),
+        Err(TransactionError::WrongDomain { .. })
+    ));
+
+    // every field is signed, the nonce included
+    let replayed = Transaction { nonce: 8, ..transaction.clone() };
+    assert_eq!(
+        signature.authenticate_transaction(public_key, &replayed, &check(0, "testnet")),
+        Err(TransactionError::SignatureInvalid)
+    );
+}
+
+#[test]
+fn test_presentation() {
+    let transaction = Transaction::from_str(r#"{"sender": "alice", "receiver": "bob", "amount": 50, "domain": "cess"}"#);
+    let (public_key, sig) = sign(&transaction);
+    let public_key = public_key.compress().to_bytes();
+    let wired = WiredUnblindedSigData::from(sig);
+    let attributes = ["receiver".to_string(), "domain".to_string()];
+
+    let presentation = Presentation::present(&transaction, &wired, &public_key, &[7; 32], &attributes, true).unwrap();
+    assert_eq!(presentation.attribute("receiver"), Some(r#""bob""#));
+    assert_eq!(presentation.attribute("domain"), Some(r#""cess""#));
+    assert_eq!(presentation.attribute("sender"), None);
+    assert_eq!(presentation.nullifier, Some(sig.nullifier().0));
+    assert_eq!(presentation.challenge, [7; 32]);
+    assert_eq!(Presentation::present(&transaction, &wired, &public_key, &[7; 32], &[], false).unwrap().nullifier, None);
+    assert_eq!(transaction.attribute("amount").unwrap(), r#"{"asset":"","value":"50","decimals":0}"#);
+
+    let other = Transaction::from_str(r#"{"sender": "alice", "receiver": "mallory", "amount": 50, "domain": "cess"}"#);
+    assert_eq!(
+        Presentation::present(&other, &wired, &public_key, &[7; 32], &attributes, true),
+        Err(PresentationError::SignatureInvalid)
+    );
+    let (other_key, _) = sign(&transaction);
+    let other_key = other_key.compress().to_bytes();
+    assert_eq!(
+        Presentation::present(&transaction, &wired, &other_key, &[7; 32], &attributes, true),
+        Err(PresentationError::SignatureInvalid)
+    );
+    assert_eq!(
+        Presentation::present(&transaction, &wired, &public_key, &[7; 32], &["pin".to_string()], true),
+        Err(PresentationError::UnknownAttribute("pin".to_string()))
+    );
+}
@@ -0,0 +1,248 @@
+/* Generated by cbindgen from blindr-ffi. Do not edit by hand. */
+
+#ifndef BLINDR_H
+#define BLINDR_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// Length of keys, session values, blinded messages and constraint hashes.
+#define BLINDR_VALUE_LEN 32
+
+// Length of an unblinded signature (e || S || R).
+#define BLINDR_SIGNATURE_LEN 96
+
+typedef enum BlindrStatus {
+  BLINDR_STATUS_OK = 0,
+  // A required pointer argument was null.
+  BLINDR_STATUS_NULL_POINTER = 1,
+  // An argument was malformed (ie: invalid JSON, not a canonical scalar or
+  // not a valid point).
+  BLINDR_STATUS_INVALID_ARGUMENT = 2,
+  // The underlying blind signature operation failed.
+  BLINDR_STATUS_CRYPTO = 3,
+  // The signer session was already used to sign.
+  BLINDR_STATUS_SESSION_USED = 4,
+  // A panic was caught at the boundary. This is a bug in blindr.
+  BLINDR_STATUS_PANIC = 5,
+} BlindrStatus;
+
+// The signer's long lived keypair.
+typedef struct BlindrKeypair BlindrKeypair;
+
+// The client's pending blind signing request, holding the blinding factors
+// needed to unblind the signer's response.
+typedef struct BlindrRequest BlindrRequest;
+
+// A single signing session (the nonce k and its public value R' = kP). A
+// session signs at most once: signing two messages with the same k reveals
+// the private key.
+typedef struct BlindrSession BlindrSession;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// The message of the last failed call on this thread, or null if the last
+// call succeeded. The string is owned by the library and stays valid until
+// the next blindr call on the same thread.
+const char *blindr_last_error_message(void);
+
+// Blinds the transaction (JSON) against the signer's 32 byte session public
+// value R', writing the 32 byte blinded message e' to send for signing. The
+// blinding factors come from a fresh seed, see blindr_request_seed.
+//
+// # Safety
+//
+// `transaction_json` must be a NUL terminated string, `public_value` must
+// point to 32 readable bytes, `out_blinded_message` to 32 writable bytes and
+// `out` must be valid for writing a handle pointer.
+enum BlindrStatus blindr_request_new(const char *transaction_json,
+                                     const uint8_t *public_value,
+                                     uint8_t *out_blinded_message,
+                                     struct BlindrRequest **out);
+
+// Writes the request's 32 byte blinded message e' again.
+//
+// # Safety
+//
+// `request` must be a live handle and `out` must point to 32 writable bytes.
+enum BlindrStatus blindr_request_blinded_message(const struct BlindrRequest *request, uint8_t *out);
+
+// Writes the request's 32 byte blinding seed, which the prover needs to
+// recompute the blinded message. It is as private as the request itself.
+//
+// # Safety
+//
+// `request` must be a live handle and `out` must point to 32 writable bytes.
+enum BlindrStatus blindr_request_seed(const struct BlindrRequest *request, uint8_t *out);
+
+// Unblinds the signer's 32 byte blinded signature S', writing the 96 byte
+// signature.
+//
+// # Safety
+//
+// `request` must be a live handle, `blinded_signature` must point to 32
+// readable bytes and `out_signature` to 96 writable bytes.
+enum BlindrStatus blindr_request_unblind(const struct BlindrRequest *request,
+                                         const uint8_t *blinded_signature,
+                                         uint8_t *out_signature);
+
+// Releases a request. Null is ignored.
+//
+// # Safety
+//
+// `request` must be null or a live handle, which is invalid afterwards.
+void blindr_request_free(struct BlindrRequest *request);
+
+// Checks a 96 byte signature against the signer's 32 byte public key.
+//
+// # Safety
+//
+// `signature` must point to 96 readable bytes, `public_key` to 32 readable
+// bytes and `out_valid` must be valid for writing a bool.
+enum BlindrStatus blindr_signature_verify(const uint8_t *signature,
+                                          const uint8_t *public_key,
+                                          bool *out_valid);
+
+// Checks a 96 byte signature against the signer's 32 byte public key and
+// that it signs the given transaction (JSON).
+//
+// # Safety
+//
+// `signature` must point to 96 readable bytes, `public_key` to 32 readable
+// bytes, `transaction_json` must be a NUL terminated string and `out_valid`
+// must be valid for writing a bool.
+enum BlindrStatus blindr_signature_verify_transaction(const uint8_t *signature,
+                                                      const uint8_t *public_key,
+                                                      const char *transaction_json,
+                                                      bool *out_valid);
+
+// Generates a new keypair.
+//
+// # Safety
+//
+// `out` must be valid for writing a handle pointer.
+enum BlindrStatus blindr_keypair_generate(struct BlindrKeypair **out);
+
+// Restores a keypair from its 32 byte private key.
+//
+// # Safety
+//
+// `private_key` must point to 32 readable bytes and `out` must be valid for
+// writing a handle pointer.
+enum BlindrStatus blindr_keypair_from_private_key(const uint8_t *private_key,
+                                                  struct BlindrKeypair **out);
+
+// Writes the 32 byte public key.
+//
+// # Safety
+//
+// `keypair` must be a live handle and `out` must point to 32 writable bytes.
+enum BlindrStatus blindr_keypair_public_key(const struct BlindrKeypair *keypair, uint8_t *out);
+
+// Writes the 32 byte private key, for storing the keypair.
+//
+// # Safety
+//
+// `keypair` must be a live handle and `out` must point to 32 writable bytes.
+enum BlindrStatus blindr_keypair_private_key(const struct BlindrKeypair *keypair, uint8_t *out);
+
+// Releases a keypair. Null is ignored.
+//
+// # Safety
+//
+// `keypair` must be null or a live handle, which is invalid afterwards.
+void blindr_keypair_free(struct BlindrKeypair *keypair);
+
+// Starts a new signing session.
+//
+// # Safety
+//
+// `out` must be valid for writing a handle pointer.
+enum BlindrStatus blindr_session_generate(struct BlindrSession **out);
+
+// Restores a session from its 32 byte private value k. Whether the session
+// was already used is not part of k, so the caller has to track it.
+//
+// # Safety
+//
+// `private_value` must point to 32 readable bytes and `out` must be valid
+// for writing a handle pointer.
+enum BlindrStatus blindr_session_from_private_value(const uint8_t *private_value,
+                                                    struct BlindrSession **out);
+
+// Writes the 32 byte public value R', sent to the client to create its blind
+// request.
+//
+// # Safety
+//
+// `session` must be a live handle and `out` must point to 32 writable bytes.
+enum BlindrStatus blindr_session_public_value(const struct BlindrSession *session, uint8_t *out);
+
+// Writes the 32 byte private value k, for storing the session.
+//
+// # Safety
+//
+// `session` must be a live handle and `out` must point to 32 writable bytes.
+enum BlindrStatus blindr_session_private_value(const struct BlindrSession *session, uint8_t *out);
+
+// Blindly signs the client's 32 byte blinded message e', writing the 32 byte
+// blinded signature S'. Fails with BLINDR_STATUS_SESSION_USED if the session
+// already signed.
+//
+// # Safety
+//
+// `session` and `keypair` must be live handles, `blinded_message` must point
+// to 32 readable bytes and `out_blinded_signature` to 32 writable bytes.
+enum BlindrStatus blindr_session_sign(struct BlindrSession *session,
+                                      const struct BlindrKeypair *keypair,
+                                      const uint8_t *blinded_message,
+                                      uint8_t *out_blinded_signature);
+
+// Whether the session already signed.
+//
+// # Safety
+//
+// `session` must be a live handle and `out_used` valid for writing a bool.
+enum BlindrStatus blindr_session_used(const struct BlindrSession *session, bool *out_used);
+
+// Releases a session. Null is ignored.
+//
+// # Safety
+//
+// `session` must be null or a live handle, which is invalid afterwards.
+void blindr_session_free(struct BlindrSession *session);
+
+// Writes the 32 byte hash of the constraint (JSON), which the custodian
+// knows the client by.
+//
+// # Safety
+//
+// `constraint_json` must be a NUL terminated string and `out_hash` must
+// point to 32 writable bytes.
+enum BlindrStatus blindr_constraint_hash(const char *constraint_json, uint8_t *out_hash);
+
+// Verifies a proof (in blindr_zk::proof's format or a plain bincode
+// receipt, ie: libblindr's base64 proof decoded) and that its journal
+// commits to the 32 byte blinded message and constraint hash. A proof that
+// doesn't verify sets `out_valid` to false, only a malformed proof is an
+// error.
+//
+// # Safety
+//
+// `proof` must point to `proof_len` readable bytes, `blinded_message` and
+// `constraint_hash` to 32 readable bytes each and `out_valid` must be valid
+// for writing a bool.
+enum BlindrStatus blindr_proof_verify(const uint8_t *proof,
+                                      uintptr_t proof_len,
+                                      const uint8_t *blinded_message,
+                                      const uint8_t *constraint_hash,
+                                      bool *out_valid);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* BLINDR_H */
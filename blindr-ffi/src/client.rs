@@ -0,0 +1,154 @@
+//! Client (requester) side of the protocol and signature authentication.
+use std::os::raw::c_char;
+use blindr_common::Transaction;
+use blindsign::{request::BlindRequest, signature::WiredUnblindedSigData};
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use rand::RngCore;
+use crate::{
+    array_in, array_out, guard, handle_free, handle_out, handle_ref, json_in, value_out, BlindrStatus, FfiError,
+    FfiResult,
+};
+
+/// The client's pending blind signing request, holding the blinding factors
+/// needed to unblind the signer's response.
+pub struct BlindrRequest {
+    request: BlindRequest,
+    blind_seed: [u8; 32],
+    blinded_message: [u8; 32],
+}
+
+fn public_key_point(public_key: *const u8) -> FfiResult<RistrettoPoint> {
+    // SAFETY: forwarded from the callers' contracts
+    let public_key = unsafe { array_in::<32>(public_key, "public key")? };
+    CompressedRistretto(*public_key)
+        .decompress()
+        .ok_or_else(|| FfiError::invalid("public key is not a valid ristretto point"))
+}
+
+/// Blinds the transaction (JSON) against the signer's 32 byte session public
+/// value R', writing the 32 byte blinded message e' to send for signing. The
+/// blinding factors come from a fresh seed, see blindr_request_seed.
+///
+/// # Safety
+///
+/// `transaction_json` must be a NUL terminated string, `public_value` must
+/// point to 32 readable bytes, `out_blinded_message` to 32 writable bytes and
+/// `out` must be valid for writing a handle pointer.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_request_new(
+    transaction_json: *const c_char,
+    public_value: *const u8,
+    out_blinded_message: *mut u8,
+    out: *mut *mut BlindrRequest,
+) -> BlindrStatus {
+    guard(|| {
+        let transaction: Transaction = json_in(transaction_json, "transaction")?;
+        let public_value = array_in::<32>(public_value, "public value")?;
+        let mut blind_seed = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut blind_seed);
+        // the proof recomputes the blinded message from this seed
+        let (blinded_message, request) =
+            BlindRequest::new_specific_msg_seeded(public_value, transaction.message(), &blind_seed)?;
+        array_out(out_blinded_message, &blinded_message, "blinded message")?;
+        handle_out(out, BlindrRequest { request, blind_seed, blinded_message })
+    })
+}
+
+/// Writes the request's 32 byte blinded message e' again.
+///
+/// # Safety
+///
+/// `request` must be a live handle and `out` must point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_request_blinded_message(request: *const BlindrRequest, out: *mut u8) -> BlindrStatus {
+    guard(|| {
+        let request = handle_ref(request, "request")?;
+        array_out(out, &request.blinded_message, "blinded message")
+    })
+}
+
+/// Writes the request's 32 byte blinding seed, which the prover needs to
+/// recompute the blinded message. It is as private as the request itself.
+///
+/// # Safety
+///
+/// `request` must be a live handle and `out` must point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_request_seed(request: *const BlindrRequest, out: *mut u8) -> BlindrStatus {
+    guard(|| {
+        let request = handle_ref(request, "request")?;
+        array_out(out, &request.blind_seed, "blind seed")
+    })
+}
+
+/// Unblinds the signer's 32 byte blinded signature S', writing the 96 byte
+/// signature.
+///
+/// # Safety
+///
+/// `request` must be a live handle, `blinded_signature` must point to 32
+/// readable bytes and `out_signature` to 96 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_request_unblind(
+    request: *const BlindrRequest,
+    blinded_signature: *const u8,
+    out_signature: *mut u8,
+) -> BlindrStatus {
+    guard(|| {
+        let request = handle_ref(request, "request")?;
+        let blinded_signature = array_in::<32>(blinded_signature, "blinded signature")?;
+        let sig = request.request.gen_signed_msg(blinded_signature)?;
+        array_out(out_signature, &WiredUnblindedSigData::from(sig).to_bytes(), "signature")
+    })
+}
+
+/// Releases a request. Null is ignored.
+///
+/// # Safety
+///
+/// `request` must be null or a live handle, which is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_request_free(request: *mut BlindrRequest) {
+    handle_free(request)
+}
+
+/// Checks a 96 byte signature against the signer's 32 byte public key.
+///
+/// # Safety
+///
+/// `signature` must point to 96 readable bytes, `public_key` to 32 readable
+/// bytes and `out_valid` must be valid for writing a bool.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_signature_verify(
+    signature: *const u8,
+    public_key: *const u8,
+    out_valid: *mut bool,
+) -> BlindrStatus {
+    guard(|| {
+        let sig = WiredUnblindedSigData(*array_in::<96>(signature, "signature")?).to_internal_format()?;
+        value_out(out_valid, sig.authenticate(public_key_point(public_key)?), "valid")
+    })
+}
+
+/// Checks a 96 byte signature against the signer's 32 byte public key and
+/// that it signs the given transaction (JSON).
+///
+/// # Safety
+///
+/// `signature` must point to 96 readable bytes, `public_key` to 32 readable
+/// bytes, `transaction_json` must be a NUL terminated string and `out_valid`
+/// must be valid for writing a bool.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_signature_verify_transaction(
+    signature: *const u8,
+    public_key: *const u8,
+    transaction_json: *const c_char,
+    out_valid: *mut bool,
+) -> BlindrStatus {
+    guard(|| {
+        let sig = WiredUnblindedSigData(*array_in::<96>(signature, "signature")?).to_internal_format()?;
+        let public_key = public_key_point(public_key)?;
+        let transaction: Transaction = json_in(transaction_json, "transaction")?;
+        value_out(out_valid, sig.msg_authenticate::<(), _>(public_key, transaction.message()), "valid")
+    })
+}
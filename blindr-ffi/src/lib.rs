@@ -0,0 +1,180 @@
+//! A C ABI over blindr: signer keypairs and sessions, client blind requests,
+//! unblinded signatures, constraint hashes and proof verification. The header
+//! is include/blindr.h (generated by cbindgen, see cbindgen.toml).
+//!
+//! Conventions:
+//!
+//! * every fallible function returns a BlindrStatus, and on anything but
+//!   BLINDR_STATUS_OK blindr_last_error_message() describes what went wrong
+//! * keypairs, sessions and requests are opaque handles created through an
+//!   out pointer and released with their matching *_free function
+//! * fixed size values (keys, R', e', S', constraint hashes) are 32 byte
+//!   buffers and signatures (e || S || R) are 96 byte buffers, the same
+//!   encodings as libblindr
+//! * transactions and constraints are passed as NUL terminated JSON
+//! * panics never cross the boundary, they are reported as BLINDR_STATUS_PANIC
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+pub mod client;
+pub mod signer;
+pub mod zk;
+
+/// Length of keys, session values, blinded messages and constraint hashes.
+pub const BLINDR_VALUE_LEN: usize = 32;
+
+/// Length of an unblinded signature (e || S || R).
+pub const BLINDR_SIGNATURE_LEN: usize = 96;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlindrStatus {
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullPointer = 1,
+    /// An argument was malformed (ie: invalid JSON, not a canonical scalar or
+    /// not a valid point).
+    InvalidArgument = 2,
+    /// The underlying blind signature operation failed.
+    Crypto = 3,
+    /// The signer session was already used to sign.
+    SessionUsed = 4,
+    /// A panic was caught at the boundary. This is a bug in blindr.
+    Panic = 5,
+}
+
+pub(crate) struct FfiError {
+    status: BlindrStatus,
+    message: String,
+}
+
+impl FfiError {
+    pub(crate) fn new(status: BlindrStatus, message: impl Into<String>) -> Self {
+        FfiError { status, message: message.into() }
+    }
+
+    pub(crate) fn invalid(message: impl Into<String>) -> Self {
+        FfiError::new(BlindrStatus::InvalidArgument, message)
+    }
+}
+
+impl From<blindsign::Error> for FfiError {
+    fn from(e: blindsign::Error) -> Self {
+        FfiError::new(BlindrStatus::Crypto, e.to_string())
+    }
+}
+
+pub(crate) type FfiResult<T> = Result<T, FfiError>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    // interior NULs would truncate the message, so drop them
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Runs the body of an exported function, turning errors and panics into a
+/// status and recording the message for blindr_last_error_message.
+pub(crate) fn guard<F: FnOnce() -> FfiResult<()>>(f: F) -> BlindrStatus {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => BlindrStatus::Ok,
+        Ok(Err(e)) => {
+            set_last_error(e.message);
+            e.status
+        }
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(format!("panic: {}", message));
+            BlindrStatus::Panic
+        }
+    }
+}
+
+/// The message of the last failed call on this thread, or null if the last
+/// call succeeded. The string is owned by the library and stays valid until
+/// the next blindr call on the same thread.
+#[no_mangle]
+pub extern "C" fn blindr_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| match &*last.borrow() {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+pub(crate) unsafe fn array_in<'a, const N: usize>(ptr: *const u8, what: &str) -> FfiResult<&'a [u8; N]> {
+    if ptr.is_null() {
+        return Err(FfiError::new(BlindrStatus::NullPointer, format!("{} is null", what)));
+    }
+    Ok(&*(ptr as *const [u8; N]))
+}
+
+pub(crate) unsafe fn array_out<const N: usize>(ptr: *mut u8, value: &[u8; N], what: &str) -> FfiResult<()> {
+    if ptr.is_null() {
+        return Err(FfiError::new(BlindrStatus::NullPointer, format!("{} out pointer is null", what)));
+    }
+    ptr::copy_nonoverlapping(value.as_ptr(), ptr, N);
+    Ok(())
+}
+
+pub(crate) unsafe fn bytes_in<'a>(ptr: *const u8, len: usize, what: &str) -> FfiResult<&'a [u8]> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if ptr.is_null() {
+        return Err(FfiError::new(BlindrStatus::NullPointer, format!("{} is null", what)));
+    }
+    Ok(std::slice::from_raw_parts(ptr, len))
+}
+
+pub(crate) unsafe fn json_in<T: serde::de::DeserializeOwned>(ptr: *const c_char, what: &str) -> FfiResult<T> {
+    if ptr.is_null() {
+        return Err(FfiError::new(BlindrStatus::NullPointer, format!("{} is null", what)));
+    }
+    let s = CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| FfiError::invalid(format!("{} is not valid UTF-8", what)))?;
+    serde_json::from_str(s).map_err(|e| FfiError::invalid(format!("invalid {} JSON: {}", what, e)))
+}
+
+pub(crate) unsafe fn value_out<T>(ptr: *mut T, value: T, what: &str) -> FfiResult<()> {
+    if ptr.is_null() {
+        return Err(FfiError::new(BlindrStatus::NullPointer, format!("{} out pointer is null", what)));
+    }
+    ptr.write(value);
+    Ok(())
+}
+
+pub(crate) unsafe fn handle_out<T>(out: *mut *mut T, value: T) -> FfiResult<()> {
+    if out.is_null() {
+        return Err(FfiError::new(BlindrStatus::NullPointer, "handle out pointer is null"));
+    }
+    *out = Box::into_raw(Box::new(value));
+    Ok(())
+}
+
+pub(crate) unsafe fn handle_ref<'a, T>(ptr: *const T, what: &str) -> FfiResult<&'a T> {
+    ptr.as_ref()
+        .ok_or_else(|| FfiError::new(BlindrStatus::NullPointer, format!("{} is null", what)))
+}
+
+pub(crate) unsafe fn handle_mut<'a, T>(ptr: *mut T, what: &str) -> FfiResult<&'a mut T> {
+    ptr.as_mut()
+        .ok_or_else(|| FfiError::new(BlindrStatus::NullPointer, format!("{} is null", what)))
+}
+
+pub(crate) unsafe fn handle_free<T>(ptr: *mut T) {
+    if !ptr.is_null() {
+        drop(Box::from_raw(ptr));
+    }
+}
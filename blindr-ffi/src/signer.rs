@@ -0,0 +1,203 @@
+//! Signer (custodian) side of the protocol.
+use blindsign::{keypair::BlindKeypair, session::BlindSession};
+use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, scalar::Scalar};
+use crate::{
+    array_in, array_out, guard, handle_free, handle_mut, handle_out, handle_ref, value_out, BlindrStatus, FfiError,
+};
+
+/// The signer's long lived keypair.
+pub struct BlindrKeypair {
+    keypair: BlindKeypair,
+}
+
+/// A single signing session (the nonce k and its public value R' = kP). A
+/// session signs at most once: signing two messages with the same k reveals
+/// the private key.
+pub struct BlindrSession {
+    session: BlindSession,
+    public_value: [u8; 32],
+    used: bool,
+}
+
+fn canonical_scalar(bytes: &[u8; 32], what: &str) -> Result<Scalar, FfiError> {
+    Scalar::from_canonical_bytes(*bytes)
+        .ok_or_else(|| FfiError::invalid(format!("{} is not a canonical scalar", what)))
+}
+
+/// Generates a new keypair.
+///
+/// # Safety
+///
+/// `out` must be valid for writing a handle pointer.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_keypair_generate(out: *mut *mut BlindrKeypair) -> BlindrStatus {
+    guard(|| {
+        let keypair = BlindKeypair::generate()?;
+        handle_out(out, BlindrKeypair { keypair })
+    })
+}
+
+/// Restores a keypair from its 32 byte private key.
+///
+/// # Safety
+///
+/// `private_key` must point to 32 readable bytes and `out` must be valid for
+/// writing a handle pointer.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_keypair_from_private_key(
+    private_key: *const u8,
+    out: *mut *mut BlindrKeypair,
+) -> BlindrStatus {
+    guard(|| {
+        let private = *array_in::<32>(private_key, "private key")?;
+        let public = (canonical_scalar(&private, "private key")? * RISTRETTO_BASEPOINT_POINT).compress().to_bytes();
+        let keypair = BlindKeypair::from_wired(private, public)?;
+        handle_out(out, BlindrKeypair { keypair })
+    })
+}
+
+/// Writes the 32 byte public key.
+///
+/// # Safety
+///
+/// `keypair` must be a live handle and `out` must point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_keypair_public_key(keypair: *const BlindrKeypair, out: *mut u8) -> BlindrStatus {
+    guard(|| {
+        let keypair = handle_ref(keypair, "keypair")?;
+        array_out(out, &keypair.keypair.public_wired(), "public key")
+    })
+}
+
+/// Writes the 32 byte private key, for storing the keypair.
+///
+/// # Safety
+///
+/// `keypair` must be a live handle and `out` must point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_keypair_private_key(keypair: *const BlindrKeypair, out: *mut u8) -> BlindrStatus {
+    guard(|| {
+        let keypair = handle_ref(keypair, "keypair")?;
+        array_out(out, &keypair.keypair.private_wired(), "private key")
+    })
+}
+
+/// Releases a keypair. Null is ignored.
+///
+/// # Safety
+///
+/// `keypair` must be null or a live handle, which is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_keypair_free(keypair: *mut BlindrKeypair) {
+    handle_free(keypair)
+}
+
+/// Starts a new signing session.
+///
+/// # Safety
+///
+/// `out` must be valid for writing a handle pointer.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_session_generate(out: *mut *mut BlindrSession) -> BlindrStatus {
+    guard(|| {
+        let (public_value, session) = BlindSession::new()?;
+        handle_out(out, BlindrSession { session, public_value, used: false })
+    })
+}
+
+/// Restores a session from its 32 byte private value k. Whether the session
+/// was already used is not part of k, so the caller has to track it.
+///
+/// # Safety
+///
+/// `private_value` must point to 32 readable bytes and `out` must be valid
+/// for writing a handle pointer.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_session_from_private_value(
+    private_value: *const u8,
+    out: *mut *mut BlindrSession,
+) -> BlindrStatus {
+    guard(|| {
+        let k = canonical_scalar(array_in::<32>(private_value, "private value")?, "private value")?;
+        let public_value = (k * RISTRETTO_BASEPOINT_POINT).compress().to_bytes();
+        handle_out(out, BlindrSession { session: BlindSession { k }, public_value, used: false })
+    })
+}
+
+/// Writes the 32 byte public value R', sent to the client to create its blind
+/// request.
+///
+/// # Safety
+///
+/// `session` must be a live handle and `out` must point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_session_public_value(session: *const BlindrSession, out: *mut u8) -> BlindrStatus {
+    guard(|| {
+        let session = handle_ref(session, "session")?;
+        array_out(out, &session.public_value, "public value")
+    })
+}
+
+/// Writes the 32 byte private value k, for storing the session.
+///
+/// # Safety
+///
+/// `session` must be a live handle and `out` must point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_session_private_value(session: *const BlindrSession, out: *mut u8) -> BlindrStatus {
+    guard(|| {
+        let session = handle_ref(session, "session")?;
+        array_out(out, &session.session.k.to_bytes(), "private value")
+    })
+}
+
+/// Blindly signs the client's 32 byte blinded message e', writing the 32 byte
+/// blinded signature S'. Fails with BLINDR_STATUS_SESSION_USED if the session
+/// already signed.
+///
+/// # Safety
+///
+/// `session` and `keypair` must be live handles, `blinded_message` must point
+/// to 32 readable bytes and `out_blinded_signature` to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_session_sign(
+    session: *mut BlindrSession,
+    keypair: *const BlindrKeypair,
+    blinded_message: *const u8,
+    out_blinded_signature: *mut u8,
+) -> BlindrStatus {
+    guard(|| {
+        let session = handle_mut(session, "session")?;
+        let keypair = handle_ref(keypair, "keypair")?;
+        if session.used {
+            return Err(FfiError::new(BlindrStatus::SessionUsed, "signer session was already used to sign"));
+        }
+        let blinded_message = array_in::<32>(blinded_message, "blinded message")?;
+        let blinded_signature = session.session.sign_ep(blinded_message, keypair.keypair.private())?;
+        session.used = true;
+        array_out(out_blinded_signature, &blinded_signature, "blinded signature")
+    })
+}
+
+/// Whether the session already signed.
+///
+/// # Safety
+///
+/// `session` must be a live handle and `out_used` valid for writing a bool.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_session_used(session: *const BlindrSession, out_used: *mut bool) -> BlindrStatus {
+    guard(|| {
+        let session = handle_ref(session, "session")?;
+        value_out(out_used, session.used, "used")
+    })
+}
+
+/// Releases a session. Null is ignored.
+///
+/// # Safety
+///
+/// `session` must be null or a live handle, which is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_session_free(session: *mut BlindrSession) {
+    handle_free(session)
+}
@@ -0,0 +1,48 @@
+//! Constraint hashes and verification of the proof that a blinded message
+//! hides a transaction fitting a constraint.
+use std::os::raw::c_char;
+use blindr_common::Constraint;
+use crate::{array_in, array_out, bytes_in, guard, json_in, value_out, BlindrStatus, FfiError};
+
+/// Writes the 32 byte hash of the constraint (JSON), which the custodian
+/// knows the client by.
+///
+/// # Safety
+///
+/// `constraint_json` must be a NUL terminated string and `out_hash` must
+/// point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_constraint_hash(constraint_json: *const c_char, out_hash: *mut u8) -> BlindrStatus {
+    guard(|| {
+        let constraint: Constraint = json_in(constraint_json, "constraint")?;
+        array_out(out_hash, &constraint.hash(), "constraint hash")
+    })
+}
+
+/// Verifies a proof (in blindr_zk::proof's format or a plain bincode
+/// receipt, ie: libblindr's base64 proof decoded) and that its journal
+/// commits to the 32 byte blinded message and constraint hash. A proof that
+/// doesn't verify sets `out_valid` to false, only a malformed proof is an
+/// error.
+///
+/// # Safety
+///
+/// `proof` must point to `proof_len` readable bytes, `blinded_message` and
+/// `constraint_hash` to 32 readable bytes each and `out_valid` must be valid
+/// for writing a bool.
+#[no_mangle]
+pub unsafe extern "C" fn blindr_proof_verify(
+    proof: *const u8,
+    proof_len: usize,
+    blinded_message: *const u8,
+    constraint_hash: *const u8,
+    out_valid: *mut bool,
+) -> BlindrStatus {
+    guard(|| {
+        let blinded_message = array_in::<32>(blinded_message, "blinded message")?;
+        let constraint_hash = array_in::<32>(constraint_hash, "constraint hash")?;
+        let receipt = blindr_zk_driver::proof::decode(bytes_in(proof, proof_len, "proof")?)
+            .map_err(|e| FfiError::invalid(e.to_string()))?;
+        value_out(out_valid, blindr_zk_driver::verify(&receipt, blinded_message, constraint_hash), "valid")
+    })
+}
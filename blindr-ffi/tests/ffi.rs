@@ -0,0 +1,121 @@
+use std::ffi::{CStr, CString};
+use std::ptr;
+use blindr::{client::*, signer::*, zk::*, *};
+
+const TRANSACTION: &str = r#"{"sender":"alice","receiver":"bob","amount":50}"#;
+
+fn last_error() -> String {
+    let message = blindr_last_error_message();
+    assert!(!message.is_null());
+    unsafe { CStr::from_ptr(message) }.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_sign_unblind_verify() {
+    unsafe {
+        let mut keypair = ptr::null_mut();
+        assert_eq!(blindr_keypair_generate(&mut keypair), BlindrStatus::Ok);
+        let mut public_key = [0u8; BLINDR_VALUE_LEN];
+        assert_eq!(blindr_keypair_public_key(keypair, public_key.as_mut_ptr()), BlindrStatus::Ok);
+
+        let mut session = ptr::null_mut();
+        assert_eq!(blindr_session_generate(&mut session), BlindrStatus::Ok);
+        let mut public_value = [0u8; 32];
+        assert_eq!(blindr_session_public_value(session, public_value.as_mut_ptr()), BlindrStatus::Ok);
+
+        let transaction = CString::new(TRANSACTION).unwrap();
+        let mut request = ptr::null_mut();
+        let mut blinded_message = [0u8; 32];
+        let status = blindr_request_new(transaction.as_ptr(), public_value.as_ptr(), blinded_message.as_mut_ptr(), &mut request);
+        assert_eq!(status, BlindrStatus::Ok);
+
+        // the guest recomputes the blinded message from the seed
+        let mut blind_seed = [0u8; 32];
+        assert_eq!(blindr_request_seed(request, blind_seed.as_mut_ptr()), BlindrStatus::Ok);
+        let message = serde_json::from_str::<blindr_common::Transaction>(TRANSACTION).unwrap().message();
+        let ep = blindsign::request::BlindRequest::blind_specific_msg_seeded(&public_value, message, &blind_seed).unwrap();
+        assert_eq!(ep, blinded_message);
+
+        let mut blinded_signature = [0u8; 32];
+        let status = blindr_session_sign(session, keypair, blinded_message.as_ptr(), blinded_signature.as_mut_ptr());
+        assert_eq!(status, BlindrStatus::Ok);
+        assert!(blindr_last_error_message().is_null());
+
+        // a session signs at most once
+        let status = blindr_session_sign(session, keypair, blinded_message.as_ptr(), blinded_signature.as_mut_ptr());
+        assert_eq!(status, BlindrStatus::SessionUsed);
+        assert!(last_error().contains("already used"));
+
+        let mut signature = [0u8; BLINDR_SIGNATURE_LEN];
+        let status = blindr_request_unblind(request, blinded_signature.as_ptr(), signature.as_mut_ptr());
+        assert_eq!(status, BlindrStatus::Ok);
+
+        let mut valid = false;
+        let status = blindr_signature_verify_transaction(signature.as_ptr(), public_key.as_ptr(), transaction.as_ptr(), &mut valid);
+        assert_eq!(status, BlindrStatus::Ok);
+        assert!(valid);
+
+        let other = CString::new(r#"{"sender":"alice","receiver":"eve","amount":50}"#).unwrap();
+        let status = blindr_signature_verify_transaction(signature.as_ptr(), public_key.as_ptr(), other.as_ptr(), &mut valid);
+        assert_eq!(status, BlindrStatus::Ok);
+        assert!(!valid);
+
+        blindr_request_free(request);
+        blindr_session_free(session);
+        blindr_keypair_free(keypair);
+    }
+}
+
+#[test]
+fn test_keypair_round_trip() {
+    unsafe {
+        let mut keypair = ptr::null_mut();
+        assert_eq!(blindr_keypair_generate(&mut keypair), BlindrStatus::Ok);
+        let (mut private_key, mut public_key) = ([0u8; 32], [0u8; 32]);
+        blindr_keypair_private_key(keypair, private_key.as_mut_ptr());
+        blindr_keypair_public_key(keypair, public_key.as_mut_ptr());
+
+        let mut restored = ptr::null_mut();
+        assert_eq!(blindr_keypair_from_private_key(private_key.as_ptr(), &mut restored), BlindrStatus::Ok);
+        let mut restored_public_key = [0u8; 32];
+        blindr_keypair_public_key(restored, restored_public_key.as_mut_ptr());
+        assert_eq!(public_key, restored_public_key);
+
+        blindr_keypair_free(restored);
+        blindr_keypair_free(keypair);
+    }
+}
+
+#[test]
+fn test_errors_are_reported() {
+    unsafe {
+        let mut keypair = ptr::null_mut();
+        assert_eq!(blindr_keypair_from_private_key(ptr::null(), &mut keypair), BlindrStatus::NullPointer);
+        assert!(keypair.is_null());
+
+        let non_canonical = [0xffu8; 32];
+        assert_eq!(blindr_keypair_from_private_key(non_canonical.as_ptr(), &mut keypair), BlindrStatus::InvalidArgument);
+        assert!(last_error().contains("canonical"));
+
+        let constraint = CString::new("not json").unwrap();
+        let mut hash = [0u8; 32];
+        assert_eq!(blindr_constraint_hash(constraint.as_ptr(), hash.as_mut_ptr()), BlindrStatus::InvalidArgument);
+
+        let mut valid = true;
+        let proof = [1u8, 2, 3];
+        let status = blindr_proof_verify(proof.as_ptr(), proof.len(), hash.as_ptr(), hash.as_ptr(), &mut valid);
+        assert_eq!(status, BlindrStatus::InvalidArgument);
+        assert!(last_error().starts_with("malformed proof"));
+
+        blindr_keypair_free(ptr::null_mut());
+    }
+}
+
+#[test]
+fn test_constraint_hash_matches_common() {
+    let json = r#"{"auth":[["pet","rex"]],"withdrawal_limit":100}"#;
+    let constraint = CString::new(json).unwrap();
+    let mut hash = [0u8; 32];
+    assert_eq!(unsafe { blindr_constraint_hash(constraint.as_ptr(), hash.as_mut_ptr()) }, BlindrStatus::Ok);
+    assert_eq!(hash, blindr_common::Constraint::from_str(json).hash());
+}
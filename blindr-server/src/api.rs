@@ -0,0 +1,305 @@
+//! Endpoint handlers. Storage and verification are blocking, so each handler
+//! runs its body on the blocking thread pool.
+use axum::{extract::State, http::StatusCode, response::{IntoResponse, Response}, Json};
+use std::time::{SystemTime, UNIX_EPOCH};
+use blindr_common::{MAX_BATCH, TOTP_PERIOD};
+use blindsign::{keypair::BlindKeypair, session::BlindSession};
+use curve25519_dalek::scalar::Scalar;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use crate::store::{session_key, Bytes32, KeyRecord, SessionRecord, StoreError};
+use crate::AppState;
+
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        ApiError { status, message: message.into() }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::NOT_FOUND, message)
+    }
+
+    fn internal(message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+}
+
+impl From<StoreError> for ApiError {
+    fn from(e: StoreError) -> Self {
+        ApiError::internal(e.to_string())
+    }
+}
+
+impl From<blindsign::Error> for ApiError {
+    fn from(e: blindsign::Error) -> Self {
+        ApiError::internal(e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "error": self.message }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+async fn blocking<T, F>(f: F) -> ApiResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, ApiError> + Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(result) => result.map(Json),
+        Err(e) => Err(ApiError::internal(format!("handler failed: {}", e))),
+    }
+}
+
+fn hex32(s: &str, what: &str) -> Result<Bytes32, ApiError> {
+    let bytes = hex::decode(s).map_err(|e| ApiError::bad_request(format!("{} is not valid hex: {}", what, e)))?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| ApiError::bad_request(format!("{} must be 32 bytes, got {}", what, bytes.len())))
+}
+
+fn canonical_scalar(bytes: Bytes32, what: &str) -> Result<Scalar, ApiError> {
+    Scalar::from_canonical_bytes(bytes).ok_or_else(|| ApiError::internal(format!("stored {} is not a canonical scalar", what)))
+}
+
+#[derive(Deserialize)]
+pub struct ConstraintRequest {
+    pub constraint_hash: String,
+}
+
+#[derive(Deserialize)]
+pub struct BlindSignRequest {
+    pub blinded_message: String,
+    pub constraint_hash: String,
+    pub proof: String,
+}
+
+#[derive(Deserialize)]
+pub struct SessionsRequest {
+    pub constraint_hash: String,
+    pub count: usize,
+}
+
+#[derive(Deserialize)]
+pub struct BlindSignBatchRequest {
+    pub blinded_messages: Vec<String>,
+    pub constraint_hash: String,
+    pub proof: String,
+}
+
+#[derive(Serialize)]
+pub struct PublicKeyResponse {
+    pub public_key: String,
+}
+
+#[derive(Serialize)]
+pub struct PublicValueResponse {
+    pub public_value: String,
+    /// the time step to prove a TOTP code for, if the constraint needs one
+    pub totp_step: u64,
+}
+
+#[derive(Serialize)]
+pub struct BlindSignResponse {
+    pub blinded_signature: String,
+}
+
+#[derive(Serialize)]
+pub struct PublicValuesResponse {
+    pub public_values: Vec<String>,
+    pub totp_step: u64,
+}
+
+#[derive(Serialize)]
+pub struct BlindSignBatchResponse {
+    pub blinded_signatures: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct SuccessResponse {
+    pub success: bool,
+}
+
+/// Returns the constraint hash's public key, generating the keypair on first
+/// use.
+pub async fn generate_keypair(State(state): State<AppState>, Json(req): Json<ConstraintRequest>) -> ApiResult<PublicKeyResponse> {
+    blocking(move || {
+        let constraint_hash = hex32(&req.constraint_hash, "constraint hash")?;
+        let keypair = BlindKeypair::generate()?;
+        let fresh = KeyRecord { private_key: keypair.private_wired(), public_key: keypair.public_wired() };
+        let record = state.keys.get_or_insert(&constraint_hash, fresh)?;
+        Ok(PublicKeyResponse { public_key: hex::encode(record.public_key) })
+    })
+    .await
+}
+
+// the current TOTP time step, which verification checks proofs against
+fn totp_step() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0) / TOTP_PERIOD
+}
+
+/// Returns the open session's public value R', opening a session if there
+/// isn't one, and the current TOTP time step.
+pub async fn create_sign_session(State(state): State<AppState>, Json(req): Json<ConstraintRequest>) -> ApiResult<PublicValueResponse> {
+    blocking(move || {
+        let constraint_hash = hex32(&req.constraint_hash, "constraint hash")?;
+        if state.keys.get(&constraint_hash)?.is_none() {
+            return Err(ApiError::not_found("Constraint hash not found"));
+        }
+        let (public_value, session) = BlindSession::new()?;
+        let fresh = SessionRecord { private_value: session.k.to_bytes(), public_value };
+        let record = state.sessions.get_or_insert(&constraint_hash, fresh)?;
+        Ok(PublicValueResponse { public_value: hex::encode(record.public_value), totp_step: totp_step() })
+    })
+    .await
+}
+
+/// Verifies the proof and blindly signs with the open session, which is
+/// consumed: a session never signs twice, even under concurrent requests.
+pub async fn blind_sign(State(state): State<AppState>, Json(req): Json<BlindSignRequest>) -> ApiResult<BlindSignResponse> {
+    blocking(move || {
+        let blinded_message = hex32(&req.blinded_message, "blinded message")?;
+        let constraint_hash = hex32(&req.constraint_hash, "constraint hash")?;
+
+        let session = state.sessions.get(&constraint_hash)?.ok_or_else(|| ApiError::not_found("Session not found"))?;
+        let key = state.keys.get(&constraint_hash)?.ok_or_else(|| ApiError::not_found("Constraint hash not found"))?;
+
+        let is_valid = state
+            .verifier
+            .verify(&req.proof, &blinded_message, &constraint_hash)
+            .map_err(ApiError::bad_request)?;
+        if !is_valid {
+            return Err(ApiError::bad_request("Verification failed"));
+        }
+
+        // another request may have signed with (or closed and replaced) the
+        // session while the proof was being verified
+        let session = state
+            .sessions
+            .take_if(&constraint_hash, &session.public_value)?
+            .ok_or_else(|| ApiError::new(StatusCode::CONFLICT, "Session was used or replaced while signing"))?;
+        let session = BlindSession { k: canonical_scalar(session.private_value, "private value")? };
+        let private_key = canonical_scalar(key.private_key, "private key")?;
+        let blinded_signature = session.sign_ep(&blinded_message, private_key)?;
+        Ok(BlindSignResponse { blinded_signature: hex::encode(blinded_signature) })
+    })
+    .await
+}
+
+fn batch_size(count: usize) -> Result<usize, ApiError> {
+    match count {
+        1..=MAX_BATCH => Ok(count),
+        _ => Err(ApiError::bad_request(format!("a batch has 1 to {} transactions", MAX_BATCH))),
+    }
+}
+
+/// Returns the public values of the constraint hash's first `count`
+/// sessions, opening any that aren't open. The first is the session
+/// /create-sign-session returns.
+pub async fn create_sign_sessions(State(state): State<AppState>, Json(req): Json<SessionsRequest>) -> ApiResult<PublicValuesResponse> {
+    blocking(move || {
+        let constraint_hash = hex32(&req.constraint_hash, "constraint hash")?;
+        let count = batch_size(req.count)?;
+        if state.keys.get(&constraint_hash)?.is_none() {
+            return Err(ApiError::not_found("Constraint hash not found"));
+        }
+        let public_values = (0..count)
+            .map(|index| {
+                let (public_value, session) = BlindSession::new()?;
+                let fresh = SessionRecord { private_value: session.k.to_bytes(), public_value };
+                let record = state.sessions.get_or_insert(&session_key(&constraint_hash, index), fresh)?;
+                Ok(hex::encode(record.public_value))
+            })
+            .collect::<Result<_, ApiError>>()?;
+        Ok(PublicValuesResponse { public_values, totp_step: totp_step() })
+    })
+    .await
+}
+
+/// Verifies the batch proof and blindly signs blinded message i with
+/// session i. Every session is taken before any signs, so a batch is signed
+/// whole or not at all; if another request got one of them first, the rest
+/// are consumed unsigned.
+pub async fn blind_sign_batch(State(state): State<AppState>, Json(req): Json<BlindSignBatchRequest>) -> ApiResult<BlindSignBatchResponse> {
+    blocking(move || {
+        let constraint_hash = hex32(&req.constraint_hash, "constraint hash")?;
+        let blinded_messages = req
+            .blinded_messages
+            .iter()
+            .map(|blinded_message| hex32(blinded_message, "blinded message"))
+            .collect::<Result<Vec<_>, _>>()?;
+        let slots: Vec<Bytes32> = (0..batch_size(blinded_messages.len())?).map(|index| session_key(&constraint_hash, index)).collect();
+
+        let sessions = slots
+            .iter()
+            .map(|slot| state.sessions.get(slot)?.ok_or_else(|| ApiError::not_found("Session not found")))
+            .collect::<Result<Vec<_>, _>>()?;
+        let key = state.keys.get(&constraint_hash)?.ok_or_else(|| ApiError::not_found("Constraint hash not found"))?;
+
+        let is_valid = state
+            .verifier
+            .verify_batch(&req.proof, &blinded_messages, &constraint_hash)
+            .map_err(ApiError::bad_request)?;
+        if !is_valid {
+            return Err(ApiError::bad_request("Verification failed"));
+        }
+
+        let taken = slots
+            .iter()
+            .zip(&sessions)
+            .map(|(slot, session)| state.sessions.take_if(slot, &session.public_value))
+            .collect::<Result<Vec<_>, _>>()?;
+        let sessions = taken
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| ApiError::new(StatusCode::CONFLICT, "Session was used or replaced while signing"))?;
+        let private_key = canonical_scalar(key.private_key, "private key")?;
+        let blinded_signatures = sessions
+            .iter()
+            .zip(&blinded_messages)
+            .map(|(session, blinded_message)| {
+                let session = BlindSession { k: canonical_scalar(session.private_value, "private value")? };
+                Ok(hex::encode(session.sign_ep(blinded_message, private_key)?))
+            })
+            .collect::<Result<_, ApiError>>()?;
+        Ok(BlindSignBatchResponse { blinded_signatures })
+    })
+    .await
+}
+
+/// Closes the constraint hash's session and any batch sessions.
+pub async fn close_sign_session(State(state): State<AppState>, Json(req): Json<ConstraintRequest>) -> ApiResult<SuccessResponse> {
+    blocking(move || {
+        let constraint_hash = hex32(&req.constraint_hash, "constraint hash")?;
+        for index in 0..MAX_BATCH {
+            state.sessions.delete(&session_key(&constraint_hash, index))?;
+        }
+        Ok(SuccessResponse { success: true })
+    })
+    .await
+}
+
+pub async fn delete_key(State(state): State<AppState>, Json(req): Json<ConstraintRequest>) -> ApiResult<SuccessResponse> {
+    blocking(move || {
+        let constraint_hash = hex32(&req.constraint_hash, "constraint hash")?;
+        if !state.keys.delete(&constraint_hash)? {
+            return Err(ApiError::not_found("Constraint hash not found"));
+        }
+        Ok(SuccessResponse { success: true })
+    })
+    .await
+}
@@ -0,0 +1,112 @@
+//! The custodian server: the same HTTP API as backend/app/app.py, signing with
+//! blindsign and verifying proofs with blindr_zk directly.
+//!
+//! All endpoints take and return JSON with hex encoded 32 byte values and a
+//! base64 proof:
+//!
+//! * POST /generate-keypair {constraint_hash} -> {public_key}
+//! * POST /create-sign-session {constraint_hash} -> {public_value, totp_step}
+//! * POST /blind-sign {blinded_message, constraint_hash, proof} -> {blinded_signature}
+//! * POST /create-sign-sessions {constraint_hash, count} -> {public_values, totp_step}
+//! * POST /blind-sign-batch {blinded_messages, constraint_hash, proof} -> {blinded_signatures}
+//! * POST /close-sign-session {constraint_hash} -> {success}
+//! * DELETE /delete-key {constraint_hash} -> {success}
+//!
+//! Errors are {error} with a 4xx or 5xx status.
+use std::sync::Arc;
+use axum::{extract::DefaultBodyLimit, routing::{delete, post}, Router};
+use base64::prelude::*;
+use blindr_zk_driver::ImageRegistry;
+use store::{Bytes32, KeyStore, MemoryStore, SessionStore};
+
+pub mod api;
+pub mod store;
+
+/// Checks the client's proof that the blinded message hides a transaction
+/// fitting the constraint with the given hash.
+pub trait ProofVerifier: Send + Sync {
+    /// Ok(false) for a proof that doesn't verify, Err for a malformed one.
+    fn verify(&self, proof: &str, blinded_message: &Bytes32, constraint_hash: &Bytes32) -> Result<bool, String>;
+
+    /// Like verify, for a batch proof committing the blinded messages in
+    /// order.
+    fn verify_batch(&self, proof: &str, blinded_messages: &[Bytes32], constraint_hash: &Bytes32) -> Result<bool, String>;
+}
+
+/// Verifies base64 proofs (as produced by libblindr, see
+/// blindr_zk::proof for the format) with
+/// blindr_zk, accepting the guest images in `images`: by default the released
+/// ones and the one the server was built with. Deprecate an image there to
+/// stop accepting its proofs once clients have upgraded.
+#[derive(Default)]
+pub struct ZkVerifier {
+    pub images: ImageRegistry,
+}
+
+impl ProofVerifier for ZkVerifier {
+    fn verify(&self, proof: &str, blinded_message: &Bytes32, constraint_hash: &Bytes32) -> Result<bool, String> {
+        let receipt = decode_proof(proof)?;
+        Ok(self.images.verify(&receipt, blinded_message, constraint_hash))
+    }
+
+    fn verify_batch(&self, proof: &str, blinded_messages: &[Bytes32], constraint_hash: &Bytes32) -> Result<bool, String> {
+        let receipt = decode_proof(proof)?;
+        Ok(self.images.verify_batch(&receipt, blinded_messages, constraint_hash))
+    }
+}
+
+fn decode_proof(proof: &str) -> Result<blindr_zk_driver::Receipt, String> {
+    let receipt_bin = BASE64_STANDARD
+        .decode(proof)
+        .map_err(|e| format!("proof is not valid base64: {}", e))?;
+    blindr_zk_driver::proof::decode(&receipt_bin).map_err(|e| e.to_string())
+}
+
+#[derive(Clone)]
+pub struct AppState {
+    pub keys: Arc<dyn KeyStore>,
+    pub sessions: Arc<dyn SessionStore>,
+    pub verifier: Arc<dyn ProofVerifier>,
+}
+
+impl AppState {
+    pub fn new(keys: Arc<dyn KeyStore>, sessions: Arc<dyn SessionStore>) -> Self {
+        AppState { keys, sessions, verifier: Arc::new(ZkVerifier::default()) }
+    }
+
+    /// Keys and sessions in the same store (ie: one SQLite database).
+    pub fn with_store<S: KeyStore + SessionStore + 'static>(store: S) -> Self {
+        let store = Arc::new(store);
+        AppState::new(store.clone(), store)
+    }
+
+    /// In-memory storage, lost on restart.
+    pub fn in_memory() -> Self {
+        AppState::new(Arc::new(MemoryStore::new()), Arc::new(MemoryStore::new()))
+    }
+}
+
+pub struct Config {
+    /// Larger request bodies are rejected with 413. Proofs make up nearly all
+    /// of a request, and a composite receipt can run to a few megabytes.
+    pub max_body_bytes: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { max_body_bytes: 8 * 1024 * 1024 }
+    }
+}
+
+pub fn router(state: AppState, config: &Config) -> Router {
+    Router::new()
+        .route("/generate-keypair", post(api::generate_keypair))
+        .route("/create-sign-session", post(api::create_sign_session))
+        .route("/blind-sign", post(api::blind_sign))
+        .route("/create-sign-sessions", post(api::create_sign_sessions))
+        .route("/blind-sign-batch", post(api::blind_sign_batch))
+        .route("/close-sign-session", post(api::close_sign_session))
+        .route("/delete-key", delete(api::delete_key))
+        .layer(DefaultBodyLimit::max(config.max_body_bytes))
+        .with_state(state)
+}
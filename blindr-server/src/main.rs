@@ -0,0 +1,51 @@
+use std::env;
+use blindr_server::{router, AppState, Config};
+#[cfg(any(feature = "sqlite", feature = "cess"))]
+use blindr_server::store::Sealer;
+
+// BLINDR_DATABASE picks the SQLite store and BLINDR_CESS_URL mirrors to CESS,
+// both sealing private material with BLINDR_STORE_KEY (32 bytes of hex).
+// Without either, keys and sessions are kept in memory.
+#[cfg(any(feature = "sqlite", feature = "cess"))]
+fn sealer() -> Sealer {
+    let key = env::var("BLINDR_STORE_KEY").expect("BLINDR_STORE_KEY is required for persistent storage");
+    Sealer::from_hex(&key).expect("invalid BLINDR_STORE_KEY")
+}
+
+fn state() -> AppState {
+    #[cfg(feature = "sqlite")]
+    if let Ok(path) = env::var("BLINDR_DATABASE") {
+        let store = blindr_server::store::SqliteStore::open(path, sealer()).expect("failed to open database");
+        #[cfg(feature = "cess")]
+        if let Ok(url) = env::var("BLINDR_CESS_URL") {
+            return AppState::with_store(blindr_server::store::CessMirror::new(store, url, sealer()));
+        }
+        return AppState::with_store(store);
+    }
+
+    #[cfg(feature = "cess")]
+    if let Ok(url) = env::var("BLINDR_CESS_URL") {
+        use blindr_server::store::{CessMirror, MemoryStore};
+        use std::sync::Arc;
+        let keys = CessMirror::new(MemoryStore::new(), url.clone(), sealer());
+        let sessions = CessMirror::new(MemoryStore::new(), url, sealer());
+        return AppState::new(Arc::new(keys), Arc::new(sessions));
+    }
+
+    AppState::in_memory()
+}
+
+#[tokio::main]
+async fn main() {
+    // same default port as the flask backend
+    let addr = env::var("BLINDR_SERVER_ADDR").unwrap_or_else(|_| "127.0.0.1:5000".to_string());
+    let mut config = Config::default();
+    if let Ok(max_body_bytes) = env::var("BLINDR_MAX_BODY_BYTES") {
+        config.max_body_bytes = max_body_bytes.parse().expect("BLINDR_MAX_BODY_BYTES must be a number of bytes");
+    }
+
+    let app = router(state(), &config);
+    let listener = tokio::net::TcpListener::bind(&addr).await.expect("failed to bind");
+    println!("blindr-server listening on {}", addr);
+    axum::serve(listener, app).await.expect("server failed");
+}
@@ -0,0 +1,143 @@
+//! Mirrors keys and sessions to CESS through the node-cess service, like the
+//! flask backend's store_in_cess, on top of another store that stays the
+//! source of truth. Unlike the flask backend, the private half is sealed
+//! before it is uploaded.
+//!
+//! Uploads must succeed for a record to be stored. Deletes from CESS are best
+//! effort: a failed delete leaves behind a sealed copy that is useless without
+//! the store key. File hashes are only kept in memory, so records from before
+//! a restart stay on CESS when deleted.
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use serde::Deserialize;
+use serde_json::json;
+use super::{poisoned, Bytes32, KeyRecord, KeyStore, Sealer, SessionRecord, SessionStore, StoreError, StoreResult};
+
+const KEYS: &str = "keys";
+const SESSIONS: &str = "sessions";
+
+#[derive(Deserialize)]
+struct UploadResponse {
+    file_hash: String,
+}
+
+pub struct CessMirror<S> {
+    inner: S,
+    url: String,
+    sealer: Sealer,
+    agent: ureq::Agent,
+    files: Mutex<HashMap<(&'static str, Bytes32), String>>,
+}
+
+impl<S> CessMirror<S> {
+    /// `url` is the node-cess service, ie: http://localhost:9000.
+    pub fn new(inner: S, url: impl Into<String>, sealer: Sealer) -> Self {
+        CessMirror {
+            inner,
+            url: url.into().trim_end_matches('/').to_string(),
+            sealer,
+            agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(60)).build(),
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn upload(&self, kind: &'static str, constraint_hash: &Bytes32, private: &Bytes32, public: &Bytes32) -> StoreResult<String> {
+        let sealed = self.sealer.seal(private, kind, constraint_hash)?;
+        let response: UploadResponse = self
+            .agent
+            .post(&format!("{}/upload-file", self.url))
+            .send_json(json!({
+                "constraints_hash": hex::encode(constraint_hash),
+                "private": hex::encode(sealed),
+                "public": hex::encode(public),
+            }))
+            .map_err(|e| StoreError(format!("cess upload failed: {}", e)))?
+            .into_json()
+            .map_err(|e| StoreError(format!("cess upload returned an invalid response: {}", e)))?;
+        Ok(response.file_hash)
+    }
+
+    fn remove(&self, file_hash: &str) {
+        let _ = self.agent.delete(&format!("{}/delete-file/{}", self.url, file_hash)).call();
+    }
+
+    fn forget(&self, kind: &'static str, constraint_hash: &Bytes32) -> StoreResult<()> {
+        let file_hash = self.files.lock().map_err(poisoned)?.remove(&(kind, *constraint_hash));
+        if let Some(file_hash) = file_hash {
+            self.remove(&file_hash);
+        }
+        Ok(())
+    }
+
+    /// Uploads, then lets `insert` decide what gets stored. If another record
+    /// won the race the upload is removed again.
+    fn mirror_insert<R: Copy + PartialEq>(
+        &self,
+        kind: &'static str,
+        constraint_hash: &Bytes32,
+        private: &Bytes32,
+        public: &Bytes32,
+        record: R,
+        insert: impl FnOnce(R) -> StoreResult<R>,
+    ) -> StoreResult<R> {
+        let file_hash = self.upload(kind, constraint_hash, private, public)?;
+        let stored = insert(record)?;
+        if stored == record {
+            self.files.lock().map_err(poisoned)?.insert((kind, *constraint_hash), file_hash);
+        } else {
+            self.remove(&file_hash);
+        }
+        Ok(stored)
+    }
+}
+
+impl<S: KeyStore> KeyStore for CessMirror<S> {
+    fn get(&self, constraint_hash: &Bytes32) -> StoreResult<Option<KeyRecord>> {
+        self.inner.get(constraint_hash)
+    }
+
+    fn get_or_insert(&self, constraint_hash: &Bytes32, record: KeyRecord) -> StoreResult<KeyRecord> {
+        if let Some(existing) = self.inner.get(constraint_hash)? {
+            return Ok(existing);
+        }
+        self.mirror_insert(KEYS, constraint_hash, &record.private_key, &record.public_key, record, |record| {
+            self.inner.get_or_insert(constraint_hash, record)
+        })
+    }
+
+    fn delete(&self, constraint_hash: &Bytes32) -> StoreResult<bool> {
+        let deleted = self.inner.delete(constraint_hash)?;
+        self.forget(KEYS, constraint_hash)?;
+        Ok(deleted)
+    }
+}
+
+impl<S: SessionStore> SessionStore for CessMirror<S> {
+    fn get(&self, constraint_hash: &Bytes32) -> StoreResult<Option<SessionRecord>> {
+        self.inner.get(constraint_hash)
+    }
+
+    fn get_or_insert(&self, constraint_hash: &Bytes32, record: SessionRecord) -> StoreResult<SessionRecord> {
+        if let Some(existing) = self.inner.get(constraint_hash)? {
+            return Ok(existing);
+        }
+        self.mirror_insert(SESSIONS, constraint_hash, &record.private_value, &record.public_value, record, |record| {
+            self.inner.get_or_insert(constraint_hash, record)
+        })
+    }
+
+    fn take_if(&self, constraint_hash: &Bytes32, public_value: &Bytes32) -> StoreResult<Option<SessionRecord>> {
+        let taken = self.inner.take_if(constraint_hash, public_value)?;
+        if taken.is_some() {
+            self.forget(SESSIONS, constraint_hash)?;
+        }
+        Ok(taken)
+    }
+
+    fn delete(&self, constraint_hash: &Bytes32) -> StoreResult<bool> {
+        let deleted = self.inner.delete(constraint_hash)?;
+        self.forget(SESSIONS, constraint_hash)?;
+        Ok(deleted)
+    }
+}
@@ -0,0 +1,157 @@
+//! Storage for the custodian's keypairs (one per constraint hash, kept until
+//! deleted) and signing sessions (at most one open per constraint hash,
+//! consumed by signing). A batch's sessions are stored under session_key.
+//!
+//! MemoryStore keeps everything in plaintext and is lost on restart. The
+//! persistent stores seal private keys and session values with a Sealer
+//! before they leave the process.
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+mod seal;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "cess")]
+mod cess;
+
+pub use seal::Sealer;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+#[cfg(feature = "cess")]
+pub use cess::CessMirror;
+
+pub type Bytes32 = [u8; 32];
+
+/// What the constraint hash's session `index` of a batch is stored under.
+/// Session 0 is the constraint hash itself, the one /create-sign-session
+/// opens; the others are domain separated hashes.
+pub fn session_key(constraint_hash: &Bytes32, index: usize) -> Bytes32 {
+    use sha2::{Digest, Sha256};
+    match index {
+        0 => *constraint_hash,
+        _ => Sha256::new()
+            .chain_update(b"blindr batch session")
+            .chain_update(constraint_hash)
+            .chain_update((index as u32).to_le_bytes())
+            .finalize()
+            .into(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyRecord {
+    pub private_key: Bytes32,
+    pub public_key: Bytes32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionRecord {
+    pub private_value: Bytes32,
+    pub public_value: Bytes32,
+}
+
+#[derive(Debug)]
+pub struct StoreError(pub String);
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "storage error: {}", self.0)
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+pub type StoreResult<T> = Result<T, StoreError>;
+
+pub trait KeyStore: Send + Sync {
+    fn get(&self, constraint_hash: &Bytes32) -> StoreResult<Option<KeyRecord>>;
+
+    /// Stores the record unless the constraint hash already has one, returning
+    /// whichever record ends up stored.
+    fn get_or_insert(&self, constraint_hash: &Bytes32, record: KeyRecord) -> StoreResult<KeyRecord>;
+
+    /// Returns whether there was a record to delete.
+    fn delete(&self, constraint_hash: &Bytes32) -> StoreResult<bool>;
+}
+
+pub trait SessionStore: Send + Sync {
+    fn get(&self, constraint_hash: &Bytes32) -> StoreResult<Option<SessionRecord>>;
+
+    /// Stores the record unless the constraint hash already has an open
+    /// session, returning whichever record ends up stored.
+    fn get_or_insert(&self, constraint_hash: &Bytes32, record: SessionRecord) -> StoreResult<SessionRecord>;
+
+    /// Compare-and-swap: removes and returns the open session only if it is
+    /// still the one with the given public value (ie: it wasn't consumed, or
+    /// closed and replaced, since it was read). Of any number of concurrent
+    /// calls, at most one gets the session, so it signs at most once.
+    fn take_if(&self, constraint_hash: &Bytes32, public_value: &Bytes32) -> StoreResult<Option<SessionRecord>>;
+
+    /// Returns whether there was a session to delete.
+    fn delete(&self, constraint_hash: &Bytes32) -> StoreResult<bool>;
+}
+
+fn poisoned<T>(_: T) -> StoreError {
+    StoreError("lock poisoned".to_string())
+}
+
+/// In-memory storage, lost on restart. Meant for tests and development.
+#[derive(Default)]
+pub struct MemoryStore<R> {
+    records: Mutex<HashMap<Bytes32, R>>,
+}
+
+impl<R: Copy> MemoryStore<R> {
+    pub fn new() -> Self {
+        MemoryStore { records: Mutex::new(HashMap::new()) }
+    }
+
+    fn get(&self, constraint_hash: &Bytes32) -> StoreResult<Option<R>> {
+        Ok(self.records.lock().map_err(poisoned)?.get(constraint_hash).copied())
+    }
+
+    fn get_or_insert(&self, constraint_hash: &Bytes32, record: R) -> StoreResult<R> {
+        Ok(*self.records.lock().map_err(poisoned)?.entry(*constraint_hash).or_insert(record))
+    }
+
+    fn take(&self, constraint_hash: &Bytes32) -> StoreResult<Option<R>> {
+        Ok(self.records.lock().map_err(poisoned)?.remove(constraint_hash))
+    }
+}
+
+impl KeyStore for MemoryStore<KeyRecord> {
+    fn get(&self, constraint_hash: &Bytes32) -> StoreResult<Option<KeyRecord>> {
+        MemoryStore::get(self, constraint_hash)
+    }
+
+    fn get_or_insert(&self, constraint_hash: &Bytes32, record: KeyRecord) -> StoreResult<KeyRecord> {
+        MemoryStore::get_or_insert(self, constraint_hash, record)
+    }
+
+    fn delete(&self, constraint_hash: &Bytes32) -> StoreResult<bool> {
+        Ok(self.take(constraint_hash)?.is_some())
+    }
+}
+
+impl SessionStore for MemoryStore<SessionRecord> {
+    fn get(&self, constraint_hash: &Bytes32) -> StoreResult<Option<SessionRecord>> {
+        MemoryStore::get(self, constraint_hash)
+    }
+
+    fn get_or_insert(&self, constraint_hash: &Bytes32, record: SessionRecord) -> StoreResult<SessionRecord> {
+        MemoryStore::get_or_insert(self, constraint_hash, record)
+    }
+
+    fn take_if(&self, constraint_hash: &Bytes32, public_value: &Bytes32) -> StoreResult<Option<SessionRecord>> {
+        let mut records = self.records.lock().map_err(poisoned)?;
+        match records.get(constraint_hash) {
+            Some(record) if record.public_value == *public_value => Ok(records.remove(constraint_hash)),
+            _ => Ok(None),
+        }
+    }
+
+    fn delete(&self, constraint_hash: &Bytes32) -> StoreResult<bool> {
+        Ok(MemoryStore::take(self, constraint_hash)?.is_some())
+    }
+}
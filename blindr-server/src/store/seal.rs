@@ -0,0 +1,69 @@
+//! Encryption at rest for private keys and session values.
+//!
+//! A sealed value is nonce (12 bytes) || ChaCha20-Poly1305 ciphertext and tag.
+//! The associated data names what the value is and whose it is (ie: "keys"
+//! and the constraint hash), so a sealed value copied to another row fails to
+//! open instead of signing for the wrong constraint.
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::RngCore;
+use super::{Bytes32, StoreError, StoreResult};
+
+const NONCE_LEN: usize = 12;
+
+pub struct Sealer {
+    cipher: ChaCha20Poly1305,
+}
+
+impl Sealer {
+    pub fn new(key: &Bytes32) -> Self {
+        Sealer { cipher: ChaCha20Poly1305::new(Key::from_slice(key)) }
+    }
+
+    /// Reads the 32 byte key from hex (ie: from an environment variable).
+    pub fn from_hex(key: &str) -> StoreResult<Self> {
+        let key = hex::decode(key.trim())
+            .ok()
+            .and_then(|key| Bytes32::try_from(key).ok())
+            .ok_or_else(|| StoreError("store key must be 32 bytes of hex".to_string()))?;
+        Ok(Sealer::new(&key))
+    }
+
+    pub fn seal(&self, secret: &Bytes32, kind: &str, constraint_hash: &Bytes32) -> StoreResult<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng()
+            .try_fill_bytes(&mut nonce)
+            .map_err(|e| StoreError(format!("rng failed: {}", e)))?;
+        let aad = associated_data(kind, constraint_hash);
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: secret, aad: &aad })
+            .map_err(|_| StoreError("encryption failed".to_string()))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    pub fn open(&self, sealed: &[u8], kind: &str, constraint_hash: &Bytes32) -> StoreResult<Bytes32> {
+        if sealed.len() < NONCE_LEN {
+            return Err(StoreError(format!("sealed {} value is truncated", kind)));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let aad = associated_data(kind, constraint_hash);
+        let secret = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad })
+            .map_err(|_| StoreError(format!("failed to open sealed {} value (wrong store key?)", kind)))?;
+        Bytes32::try_from(secret).map_err(|_| StoreError(format!("sealed {} value has the wrong length", kind)))
+    }
+}
+
+fn associated_data(kind: &str, constraint_hash: &Bytes32) -> Vec<u8> {
+    let mut aad = kind.as_bytes().to_vec();
+    aad.push(0);
+    aad.extend_from_slice(constraint_hash);
+    aad
+}
@@ -0,0 +1,139 @@
+//! SQLite storage. Keys and sessions share the database, each in its own
+//! table with the private half sealed. Consuming a session is a single
+//! DELETE .. RETURNING, so it stays atomic across processes sharing the file.
+use std::path::Path;
+use std::sync::Mutex;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use super::{poisoned, Bytes32, KeyRecord, KeyStore, Sealer, SessionRecord, SessionStore, StoreError, StoreResult};
+
+const KEYS: &str = "keys";
+const SESSIONS: &str = "sessions";
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        StoreError(format!("sqlite: {}", e))
+    }
+}
+
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+    sealer: Sealer,
+}
+
+impl SqliteStore {
+    pub fn open(path: impl AsRef<Path>, sealer: Sealer) -> StoreResult<Self> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        Self::init(conn, sealer)
+    }
+
+    pub fn open_in_memory(sealer: Sealer) -> StoreResult<Self> {
+        Self::init(Connection::open_in_memory()?, sealer)
+    }
+
+    fn init(conn: Connection, sealer: Sealer) -> StoreResult<Self> {
+        for table in [KEYS, SESSIONS] {
+            conn.execute(
+                &format!(
+                    "CREATE TABLE IF NOT EXISTS {} (
+                        constraint_hash BLOB PRIMARY KEY,
+                        private BLOB NOT NULL,
+                        public BLOB NOT NULL
+                    )",
+                    table
+                ),
+                [],
+            )?;
+        }
+        Ok(SqliteStore { conn: Mutex::new(conn), sealer })
+    }
+
+    fn row(&self, table: &str, constraint_hash: &Bytes32, private: Vec<u8>, public: Vec<u8>) -> StoreResult<(Bytes32, Bytes32)> {
+        let private = self.sealer.open(&private, table, constraint_hash)?;
+        let public = Bytes32::try_from(public).map_err(|_| StoreError(format!("corrupt public value in {}", table)))?;
+        Ok((private, public))
+    }
+
+    fn get(&self, table: &str, constraint_hash: &Bytes32) -> StoreResult<Option<(Bytes32, Bytes32)>> {
+        let conn = self.conn.lock().map_err(poisoned)?;
+        let row = conn
+            .query_row(
+                &format!("SELECT private, public FROM {} WHERE constraint_hash = ?1", table),
+                params![&constraint_hash[..]],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        row.map(|(private, public)| self.row(table, constraint_hash, private, public)).transpose()
+    }
+
+    fn get_or_insert(&self, table: &str, constraint_hash: &Bytes32, private: &Bytes32, public: &Bytes32) -> StoreResult<(Bytes32, Bytes32)> {
+        let sealed = self.sealer.seal(private, table, constraint_hash)?;
+        let mut conn = self.conn.lock().map_err(poisoned)?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute(
+            &format!("INSERT OR IGNORE INTO {} (constraint_hash, private, public) VALUES (?1, ?2, ?3)", table),
+            params![&constraint_hash[..], sealed, &public[..]],
+        )?;
+        let (private, public) = tx.query_row(
+            &format!("SELECT private, public FROM {} WHERE constraint_hash = ?1", table),
+            params![&constraint_hash[..]],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        tx.commit()?;
+        self.row(table, constraint_hash, private, public)
+    }
+
+    fn delete(&self, table: &str, constraint_hash: &Bytes32) -> StoreResult<bool> {
+        let conn = self.conn.lock().map_err(poisoned)?;
+        let deleted = conn.execute(&format!("DELETE FROM {} WHERE constraint_hash = ?1", table), params![&constraint_hash[..]])?;
+        Ok(deleted > 0)
+    }
+}
+
+impl KeyStore for SqliteStore {
+    fn get(&self, constraint_hash: &Bytes32) -> StoreResult<Option<KeyRecord>> {
+        let row = SqliteStore::get(self, KEYS, constraint_hash)?;
+        Ok(row.map(|(private_key, public_key)| KeyRecord { private_key, public_key }))
+    }
+
+    fn get_or_insert(&self, constraint_hash: &Bytes32, record: KeyRecord) -> StoreResult<KeyRecord> {
+        let (private_key, public_key) = SqliteStore::get_or_insert(self, KEYS, constraint_hash, &record.private_key, &record.public_key)?;
+        Ok(KeyRecord { private_key, public_key })
+    }
+
+    fn delete(&self, constraint_hash: &Bytes32) -> StoreResult<bool> {
+        SqliteStore::delete(self, KEYS, constraint_hash)
+    }
+}
+
+impl SessionStore for SqliteStore {
+    fn get(&self, constraint_hash: &Bytes32) -> StoreResult<Option<SessionRecord>> {
+        let row = SqliteStore::get(self, SESSIONS, constraint_hash)?;
+        Ok(row.map(|(private_value, public_value)| SessionRecord { private_value, public_value }))
+    }
+
+    fn get_or_insert(&self, constraint_hash: &Bytes32, record: SessionRecord) -> StoreResult<SessionRecord> {
+        let (private_value, public_value) =
+            SqliteStore::get_or_insert(self, SESSIONS, constraint_hash, &record.private_value, &record.public_value)?;
+        Ok(SessionRecord { private_value, public_value })
+    }
+
+    fn take_if(&self, constraint_hash: &Bytes32, public_value: &Bytes32) -> StoreResult<Option<SessionRecord>> {
+        let conn = self.conn.lock().map_err(poisoned)?;
+        let row = conn
+            .query_row(
+                "DELETE FROM sessions WHERE constraint_hash = ?1 AND public = ?2 RETURNING private, public",
+                params![&constraint_hash[..], &public_value[..]],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        drop(conn);
+        let row = row.map(|(private, public)| self.row(SESSIONS, constraint_hash, private, public)).transpose()?;
+        Ok(row.map(|(private_value, public_value)| SessionRecord { private_value, public_value }))
+    }
+
+    fn delete(&self, constraint_hash: &Bytes32) -> StoreResult<bool> {
+        SqliteStore::delete(self, SESSIONS, constraint_hash)
+    }
+}
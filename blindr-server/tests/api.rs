@@ -0,0 +1,181 @@
+use std::sync::Arc;
+use axum::{body::Body, http::{Method, Request, StatusCode}, Router};
+use blindr_server::{router, store::Bytes32, AppState, Config, ProofVerifier};
+use blindsign::{request::BlindRequest, signature::WiredUnblindedSigData};
+use curve25519_dalek::ristretto::CompressedRistretto;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+// Proving needs the zkvm, so these tests swap in a verifier that accepts the
+// proof "valid" and rejects anything else.
+struct FakeVerifier;
+
+impl ProofVerifier for FakeVerifier {
+    fn verify(&self, proof: &str, _: &Bytes32, _: &Bytes32) -> Result<bool, String> {
+        Ok(proof == "valid")
+    }
+
+    fn verify_batch(&self, proof: &str, _: &[Bytes32], _: &Bytes32) -> Result<bool, String> {
+        Ok(proof == "valid")
+    }
+}
+
+const CONSTRAINT_HASH: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+
+fn app(config: &Config) -> Router {
+    let mut state = AppState::in_memory();
+    state.verifier = Arc::new(FakeVerifier);
+    router(state, config)
+}
+
+async fn call(app: &Router, method: Method, uri: &str, body: Value) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+fn hex32(value: &Value) -> [u8; 32] {
+    hex::decode(value.as_str().unwrap()).unwrap().try_into().unwrap()
+}
+
+#[tokio::test]
+async fn test_blind_sign_flow() {
+    let app = app(&Config::default());
+    let body = json!({ "constraint_hash": CONSTRAINT_HASH });
+
+    let (status, keys) = call(&app, Method::POST, "/generate-keypair", body.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    // the keypair is stable per constraint hash
+    let (_, again) = call(&app, Method::POST, "/generate-keypair", body.clone()).await;
+    assert_eq!(keys["public_key"], again["public_key"]);
+
+    let (status, session) = call(&app, Method::POST, "/create-sign-session", body.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    let public_value = hex32(&session["public_value"]);
+    assert!(session["totp_step"].as_u64().unwrap() > 0);
+
+    let message = br#"{"sender":"alice","receiver":"bob","amount":50}"#;
+    let (blinded_message, request) = BlindRequest::new_specific_msg(&public_value, message).unwrap();
+
+    let (status, _) = call(&app, Method::POST, "/blind-sign", json!({
+        "blinded_message": hex::encode(blinded_message),
+        "constraint_hash": CONSTRAINT_HASH,
+        "proof": "invalid",
+    })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let sign = json!({
+        "blinded_message": hex::encode(blinded_message),
+        "constraint_hash": CONSTRAINT_HASH,
+        "proof": "valid",
+    });
+    let (status, signed) = call(&app, Method::POST, "/blind-sign", sign.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let sig = request.gen_signed_msg(&hex32(&signed["blinded_signature"])).unwrap();
+    let sig = WiredUnblindedSigData::from(sig).to_internal_format().unwrap();
+    let public_key = CompressedRistretto(hex32(&keys["public_key"])).decompress().unwrap();
+    assert!(sig.msg_authenticate::<(), _>(public_key, message));
+
+    // signing consumed the session
+    let (status, error) = call(&app, Method::POST, "/blind-sign", sign).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(error["error"], "Session not found");
+
+    let (status, _) = call(&app, Method::DELETE, "/delete-key", body.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&app, Method::DELETE, "/delete-key", body).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_blind_sign_batch() {
+    let app = app(&Config::default());
+    let body = json!({ "constraint_hash": CONSTRAINT_HASH });
+    let (_, keys) = call(&app, Method::POST, "/generate-keypair", body.clone()).await;
+    let public_key = CompressedRistretto(hex32(&keys["public_key"])).decompress().unwrap();
+
+    let (status, _) = call(&app, Method::POST, "/create-sign-sessions", json!({ "constraint_hash": CONSTRAINT_HASH, "count": 17 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (_, single) = call(&app, Method::POST, "/create-sign-session", body.clone()).await;
+    let (status, sessions) = call(&app, Method::POST, "/create-sign-sessions", json!({ "constraint_hash": CONSTRAINT_HASH, "count": 3 })).await;
+    assert_eq!(status, StatusCode::OK);
+    let public_values: Vec<[u8; 32]> = sessions["public_values"].as_array().unwrap().iter().map(hex32).collect();
+    // the first is the single session, the others are their own
+    assert_eq!(public_values[0], hex32(&single["public_value"]));
+    assert_ne!(public_values[1], public_values[2]);
+
+    let messages: Vec<String> = (0..3).map(|i| format!(r#"{{"sender":"alice","receiver":"bob{}","amount":10}}"#, i)).collect();
+    let requests: Vec<_> = public_values
+        .iter()
+        .zip(&messages)
+        .map(|(public_value, message)| BlindRequest::new_specific_msg(public_value, message.as_bytes()).unwrap())
+        .collect();
+    let sign = json!({
+        "blinded_messages": requests.iter().map(|(blinded_message, _)| hex::encode(blinded_message)).collect::<Vec<_>>(),
+        "constraint_hash": CONSTRAINT_HASH,
+        "proof": "valid",
+    });
+
+    let (status, _) = call(&app, Method::POST, "/blind-sign-batch", json!({
+        "blinded_messages": sign["blinded_messages"],
+        "constraint_hash": CONSTRAINT_HASH,
+        "proof": "invalid",
+    })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, signed) = call(&app, Method::POST, "/blind-sign-batch", sign.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    let blinded_signatures = signed["blinded_signatures"].as_array().unwrap();
+    assert_eq!(blinded_signatures.len(), 3);
+    for ((_, request), (blinded_signature, message)) in requests.iter().zip(blinded_signatures.iter().zip(&messages)) {
+        let sig = request.gen_signed_msg(&hex32(blinded_signature)).unwrap();
+        let sig = WiredUnblindedSigData::from(sig).to_internal_format().unwrap();
+        assert!(sig.msg_authenticate::<(), _>(public_key, message.as_bytes()));
+    }
+
+    // signing consumed every session of the batch
+    let (status, _) = call(&app, Method::POST, "/blind-sign-batch", sign).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, reopened) = call(&app, Method::POST, "/create-sign-sessions", json!({ "constraint_hash": CONSTRAINT_HASH, "count": 3 })).await;
+    assert_ne!(reopened["public_values"][1], sessions["public_values"][1]);
+
+    // closing the session closes the batch's too
+    let (status, _) = call(&app, Method::POST, "/close-sign-session", body).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&app, Method::POST, "/blind-sign-batch", json!({
+        "blinded_messages": [hex::encode([0u8; 32])],
+        "constraint_hash": CONSTRAINT_HASH,
+        "proof": "valid",
+    })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_session_needs_keypair() {
+    let app = app(&Config::default());
+    let (status, error) = call(&app, Method::POST, "/create-sign-session", json!({ "constraint_hash": CONSTRAINT_HASH })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(error["error"], "Constraint hash not found");
+
+    let (status, _) = call(&app, Method::POST, "/generate-keypair", json!({ "constraint_hash": "not hex" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_body_limit() {
+    let app = app(&Config { max_body_bytes: 1024 });
+    let (status, _) = call(&app, Method::POST, "/blind-sign", json!({
+        "blinded_message": "00",
+        "constraint_hash": CONSTRAINT_HASH,
+        "proof": "a".repeat(2048),
+    })).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+}
@@ -0,0 +1,77 @@
+use blindr_server::store::{KeyRecord, KeyStore, MemoryStore, SessionRecord, SessionStore};
+#[cfg(feature = "sqlite")]
+use blindr_server::store::{Sealer, SqliteStore};
+
+const HASH: [u8; 32] = [1u8; 32];
+#[cfg(feature = "sqlite")]
+const STORE_KEY: [u8; 32] = [7u8; 32];
+
+fn session(n: u8) -> SessionRecord {
+    SessionRecord { private_value: [n; 32], public_value: [n + 100; 32] }
+}
+
+fn check_sessions(store: &dyn SessionStore) {
+    assert_eq!(store.get_or_insert(&HASH, session(1)).unwrap(), session(1));
+    // an open session is never replaced by get_or_insert
+    assert_eq!(store.get_or_insert(&HASH, session(2)).unwrap(), session(1));
+
+    // compare-and-swap on the public value
+    assert_eq!(store.take_if(&HASH, &session(2).public_value).unwrap(), None);
+    assert_eq!(store.take_if(&HASH, &session(1).public_value).unwrap(), Some(session(1)));
+    assert_eq!(store.take_if(&HASH, &session(1).public_value).unwrap(), None);
+    assert_eq!(SessionStore::get(store, &HASH).unwrap(), None);
+
+    // a closed and reopened session can't be taken with the old public value
+    store.get_or_insert(&HASH, session(3)).unwrap();
+    assert!(SessionStore::delete(store, &HASH).unwrap());
+    store.get_or_insert(&HASH, session(4)).unwrap();
+    assert_eq!(store.take_if(&HASH, &session(3).public_value).unwrap(), None);
+    assert_eq!(store.take_if(&HASH, &session(4).public_value).unwrap(), Some(session(4)));
+}
+
+fn check_keys(store: &dyn KeyStore) {
+    let record = KeyRecord { private_key: [2u8; 32], public_key: [3u8; 32] };
+    assert_eq!(store.get_or_insert(&HASH, record).unwrap(), record);
+    let other = KeyRecord { private_key: [4u8; 32], public_key: [5u8; 32] };
+    assert_eq!(store.get_or_insert(&HASH, other).unwrap(), record);
+    assert_eq!(KeyStore::get(store, &HASH).unwrap(), Some(record));
+    assert!(KeyStore::delete(store, &HASH).unwrap());
+    assert!(!KeyStore::delete(store, &HASH).unwrap());
+}
+
+#[test]
+fn test_memory_store() {
+    check_sessions(&MemoryStore::new());
+    check_keys(&MemoryStore::new());
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_store() {
+    let store = SqliteStore::open_in_memory(Sealer::new(&STORE_KEY)).unwrap();
+    check_sessions(&store);
+    check_keys(&store);
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_store_is_sealed() {
+    let path = std::env::temp_dir().join(format!("blindr-server-test-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let record = KeyRecord { private_key: [2u8; 32], public_key: [3u8; 32] };
+
+    let store = SqliteStore::open(&path, Sealer::new(&STORE_KEY)).unwrap();
+    KeyStore::get_or_insert(&store, &HASH, record).unwrap();
+    drop(store);
+
+    let raw = std::fs::read(&path).unwrap();
+    assert!(!raw.windows(32).any(|window| window == record.private_key));
+
+    let reopened = SqliteStore::open(&path, Sealer::new(&STORE_KEY)).unwrap();
+    assert_eq!(KeyStore::get(&reopened, &HASH).unwrap(), Some(record));
+    let wrong_key = SqliteStore::open(&path, Sealer::new(&[8u8; 32])).unwrap();
+    assert!(KeyStore::get(&wrong_key, &HASH).is_err());
+
+    drop((reopened, wrong_key));
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,207 @@
+//! wasm-bindgen bindings for the client side of blindr: creating blind
+//! requests, unblinding and authenticating signatures and verifying proofs.
+//! Byte strings use the same encodings as libblindr (32 byte R', e', S' and
+//! public keys, 96 byte e || S || R signatures, base64 proofs in
+//! blindr_zk::proof's format).
+use wasm_bindgen::prelude::*;
+use blindsign::{request, signature::{UnblindedSigData, WiredUnblindedSigData}};
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use base64::prelude::*;
+
+fn array<const N: usize>(bytes: &[u8], what: &str) -> Result<[u8; N], JsError> {
+    bytes
+        .try_into()
+        .map_err(|_| JsError::new(&format!("{} must be {} bytes, got {}", what, N, bytes.len())))
+}
+
+fn public_key_point(public_key: &[u8]) -> Result<RistrettoPoint, JsError> {
+    CompressedRistretto(array(public_key, "public key")?)
+        .decompress()
+        .ok_or_else(|| JsError::new("public key is not a valid ristretto point"))
+}
+
+fn blindsign_error(e: blindsign::Error) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+#[wasm_bindgen(js_name = toHex)]
+pub fn to_hex(bytes: &[u8]) -> String {
+    hex::encode(bytes)
+}
+
+#[wasm_bindgen(js_name = fromHex)]
+pub fn from_hex(s: &str) -> Result<Vec<u8>, JsError> {
+    hex::decode(s).map_err(|e| JsError::new(&format!("invalid hex: {}", e)))
+}
+
+/// Whether the bytes are a valid signer public key.
+#[wasm_bindgen(js_name = isValidPublicKey)]
+pub fn is_valid_public_key(public_key: &[u8]) -> bool {
+    public_key_point(public_key).is_ok()
+}
+
+#[wasm_bindgen]
+pub struct Transaction {
+    inner: blindr_common::Transaction,
+}
+
+#[wasm_bindgen]
+impl Transaction {
+    /// `amount` is in whole units of the native asset, use `fromJson` for
+    /// other assets.
+    #[wasm_bindgen(constructor)]
+    pub fn new(sender: String, receiver: String, amount: u64) -> Transaction {
+        let amount = blindr_common::Amount::native(amount);
+        Transaction { inner: blindr_common::Transaction::new(sender, receiver, amount) }
+    }
+
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<Transaction, JsError> {
+        let inner = serde_json::from_str(json)?;
+        Ok(Transaction { inner })
+    }
+
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.inner).unwrap()
+    }
+
+    /// The exact bytes that get blinded and signed.
+    pub fn message(&self) -> Vec<u8> {
+        self.inner.message()
+    }
+}
+
+#[wasm_bindgen]
+pub struct Constraint {
+    inner: blindr_common::Constraint,
+}
+
+#[wasm_bindgen]
+impl Constraint {
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<Constraint, JsError> {
+        let inner = serde_json::from_str(json)?;
+        Ok(Constraint { inner })
+    }
+
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.inner).unwrap()
+    }
+
+    pub fn hash(&self) -> Vec<u8> {
+        self.inner.hash().to_vec()
+    }
+}
+
+/// The client's pending blind signing request, holding the blinding factors
+/// needed to unblind the signer's response.
+#[wasm_bindgen]
+pub struct BlindRequest {
+    request: request::BlindRequest,
+    blind_seed: [u8; 32],
+    blinded_message: [u8; 32],
+}
+
+#[wasm_bindgen]
+impl BlindRequest {
+    /// Blinds the transaction against the signer's session public value R',
+    /// with a fresh blinding seed from the JS crypto API.
+    #[wasm_bindgen(constructor)]
+    pub fn new(transaction: &Transaction, public_value: &[u8]) -> Result<BlindRequest, JsError> {
+        let public_value = array(public_value, "public value")?;
+        let mut blind_seed = [0u8; 32];
+        getrandom::getrandom(&mut blind_seed).map_err(|e| JsError::new(&e.to_string()))?;
+        // the proof recomputes the blinded message from this seed
+        let (blinded_message, request) =
+            request::BlindRequest::new_specific_msg_seeded(&public_value, transaction.inner.message(), &blind_seed)
+                .map_err(blindsign_error)?;
+
+        Ok(BlindRequest { request, blind_seed, blinded_message })
+    }
+
+    /// e', the value sent to the signer for blind signing.
+    #[wasm_bindgen(getter, js_name = blindedMessage)]
+    pub fn blinded_message(&self) -> Vec<u8> {
+        self.blinded_message.to_vec()
+    }
+
+    /// The 32 byte seed of the blinding factors, which the prover needs to
+    /// recompute the blinded message. As private as the request itself.
+    #[wasm_bindgen(getter, js_name = blindSeed)]
+    pub fn blind_seed(&self) -> Vec<u8> {
+        self.blind_seed.to_vec()
+    }
+
+    /// Unblinds the signer's blinded signature S'.
+    pub fn unblind(&self, blinded_signature: &[u8]) -> Result<Signature, JsError> {
+        let blinded_signature = array(blinded_signature, "blinded signature")?;
+        let sig = self.request.gen_signed_msg(&blinded_signature).map_err(blindsign_error)?;
+        Ok(Signature::from(sig))
+    }
+}
+
+/// An unblinded signature (e || S || R).
+#[wasm_bindgen]
+pub struct Signature {
+    wired: [u8; 96],
+    sig: UnblindedSigData,
+}
+
+impl From<UnblindedSigData> for Signature {
+    fn from(sig: UnblindedSigData) -> Self {
+        Signature { wired: WiredUnblindedSigData::from(sig).to_bytes(), sig }
+    }
+}
+
+#[wasm_bindgen]
+impl Signature {
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(signature: &[u8]) -> Result<Signature, JsError> {
+        let wired = array(signature, "signature")?;
+        let sig = WiredUnblindedSigData(wired).to_internal_format().map_err(blindsign_error)?;
+        Ok(Signature { wired, sig })
+    }
+
+    #[wasm_bindgen(js_name = fromHex)]
+    pub fn from_hex(signature: &str) -> Result<Signature, JsError> {
+        Signature::from_bytes(&from_hex(signature)?)
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.wired.to_vec()
+    }
+
+    #[wasm_bindgen(js_name = toHex)]
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.wired)
+    }
+
+    /// Checks the signature against the signer's public key.
+    pub fn verify(&self, public_key: &[u8]) -> Result<bool, JsError> {
+        Ok(self.sig.authenticate(public_key_point(public_key)?))
+    }
+
+    /// Checks the signature against the signer's public key and that it
+    /// signs the given transaction.
+    #[wasm_bindgen(js_name = verifyMessage)]
+    pub fn verify_message(&self, public_key: &[u8], transaction: &Transaction) -> Result<bool, JsError> {
+        let public_key = public_key_point(public_key)?;
+        Ok(self.sig.msg_authenticate::<(), _>(public_key, transaction.inner.message()))
+    }
+}
+
+/// Verifies a proof (base64, as produced by libblindr) that the blinded
+/// message hides a transaction fitting the constraint with the given hash,
+/// made at a time close to the JS clock.
+#[wasm_bindgen(js_name = verifyProof)]
+pub fn verify_proof(proof: &str, blinded_message: &[u8], constraint_hash: &[u8]) -> Result<bool, JsError> {
+    let blinded_message = array(blinded_message, "blinded message")?;
+    let constraint_hash = array(constraint_hash, "constraint hash")?;
+    let receipt_bin = BASE64_STANDARD.decode(proof)?;
+    let receipt = blindr_zk_driver::proof::decode(&receipt_bin)?;
+    let now = (js_sys::Date::now() / 1000.0) as u64;
+    Ok(blindr_zk_driver::verify_at(&receipt, &blinded_message, &constraint_hash, now, blindr_zk_driver::MAX_CLOCK_SKEW))
+}
@@ -0,0 +1,34 @@
+use blindr_wasm::{BlindRequest, Signature, Transaction};
+use blindsign::{keypair::BlindKeypair, request, session::BlindSession};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn test_blind_sign_round_trip() {
+    let keypair = BlindKeypair::generate().unwrap();
+    let (rp, bs) = BlindSession::new().unwrap();
+
+    let transaction = Transaction::from_json(
+        r#"{"sender": "1FWu4Z9NoBWnguurBCdXpmM2xuiog6kbdy", "receiver": "3C3nZhpVjjDGo7vGzBCTJkKfYzCGWGLWsq", "amount": 100}"#,
+    ).unwrap();
+    let request = BlindRequest::new(&transaction, &rp).unwrap();
+
+    let ep = request.blinded_message().try_into().unwrap();
+    let sp = bs.sign_ep(&ep, keypair.private()).unwrap();
+
+    let signature = request.unblind(&sp).unwrap();
+    let signature = Signature::from_hex(&signature.to_hex()).unwrap();
+    assert!(signature.verify(&keypair.public_wired()).unwrap());
+    assert!(signature.verify_message(&keypair.public_wired(), &transaction).unwrap());
+}
+
+#[wasm_bindgen_test]
+fn test_blinded_message_from_seed() {
+    let (rp, _) = BlindSession::new().unwrap();
+    let transaction = Transaction::new("alice".to_string(), "bob".to_string(), 100);
+    let request = BlindRequest::new(&transaction, &rp).unwrap();
+
+    // what the guest recomputes from the seed
+    let seed = request.blind_seed().try_into().unwrap();
+    let ep = request::BlindRequest::blind_specific_msg_seeded(&rp, transaction.message(), &seed).unwrap();
+    assert_eq!(request.blinded_message(), ep.to_vec());
+}
@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: Apache-2.0
+
+pragma solidity ^0.8.17;
+
+import {IRiscZeroVerifier} from "../../risc0/bonsai/ethereum/contracts/IRiscZeroVerifier.sol";
+
+/// @notice Checks Groth16 blindr proofs: that a blinded message is of a
+///     message satisfying the constraint with the given hash.
+/// @dev Deploy with a RiscZeroGroth16Verifier, the guest's image ID,
+///     blindr_zk::groth16::image_id(), and its journal version,
+///     blindr_common::GUEST_VERSION. Calldata comes from
+///     blindr_zk::groth16::OnchainProof::calldata().
+contract BlindrVerifier {
+    IRiscZeroVerifier public immutable verifier;
+    bytes32 public immutable imageId;
+    /// @notice the journal version the guest commits
+    uint32 public immutable guestVersion;
+    /// @notice how far the proof's time may be from the block's, in seconds
+    uint64 public immutable maxClockSkew;
+
+    constructor(IRiscZeroVerifier _verifier, bytes32 _imageId, uint32 _guestVersion, uint64 _maxClockSkew) {
+        verifier = _verifier;
+        imageId = _imageId;
+        guestVersion = _guestVersion;
+        maxClockSkew = _maxClockSkew;
+    }
+
+    /// @notice the guest's journal, (guestVersion, blindedMessage,
+    ///     constraintHash, provedAt, no TOTP step, no issuer) in the zkvm's
+    ///     serde format.
+    /// @dev the version is a little endian u32 word, every byte of the two
+    ///     byte arrays takes one too, the u64 takes two and the absent TOTP
+    ///     step and issuer a zero word each. Proofs of constraints requiring
+    ///     TOTP codes or identities can't be checked on-chain.
+    function journal(bytes32 blindedMessage, bytes32 constraintHash, uint64 provedAt)
+        public
+        view
+        returns (bytes memory encoded)
+    {
+        encoded = new bytes(276);
+        for (uint256 i = 0; i < 4; i++) {
+            encoded[i] = bytes1(uint8(guestVersion >> (8 * i)));
+        }
+        for (uint256 i = 0; i < 32; i++) {
+            encoded[4 + i * 4] = blindedMessage[i];
+            encoded[132 + i * 4] = constraintHash[i];
+        }
+        for (uint256 i = 0; i < 8; i++) {
+            encoded[260 + i] = bytes1(uint8(provedAt >> (8 * i)));
+        }
+    }
+
+    /// @return true if the seal proves the blinded message is of a message
+    ///     satisfying the constraint, at a time close to the block's.
+    function verify(
+        bytes calldata seal,
+        bytes32 postStateDigest,
+        bytes32 blindedMessage,
+        bytes32 constraintHash,
+        uint64 provedAt
+    ) external view returns (bool) {
+        uint256 skew = block.timestamp > provedAt ? block.timestamp - provedAt : provedAt - block.timestamp;
+        if (skew > maxClockSkew) {
+            return false;
+        }
+        bytes32 journalHash = sha256(journal(blindedMessage, constraintHash, provedAt));
+        return verifier.verify(seal, imageId, postStateDigest, journalHash);
+    }
+}
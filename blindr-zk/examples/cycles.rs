@@ -0,0 +1,25 @@
+//! Cycle counts of the guest's blinding, with dalek's software field
+//! arithmetic (before) and on the BIGINT accelerator (after). Only executes
+//! the guest, nothing is proved.
+//!
+//!     cargo run --release --example cycles
+use blindr_common::{Amount, Transaction};
+use blindsign::session::BlindSession;
+use hello_world_methods::CYCLES_ELF;
+use risc0_zkvm::{default_executor, ExecutorEnv};
+
+fn main() {
+    let (public_value, _) = BlindSession::new().unwrap();
+    let transaction = Transaction::new("alice".to_string(), "bob".to_string(), Amount::native(100));
+    let env = ExecutorEnv::builder()
+        .write(&(public_value, [7u8; 32], transaction.message()))
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = default_executor().execute(env, CYCLES_ELF).unwrap();
+    let (software, accelerated): (u64, u64) = session.journal.decode().unwrap();
+
+    println!("software:    {:>10} cycles", software);
+    println!("accelerated: {:>10} cycles", accelerated);
+    println!("speedup:     {:>10.1}x", software as f64 / accelerated as f64);
+}
@@ -0,0 +1,16 @@
+//! The RELEASED_IMAGES entries of the guests this crate was built with, to
+//! pin in src/registry.rs. Only entries of a docker build are reproducible:
+//!
+//!     BLINDR_DOCKER_BUILD=1 cargo run --example released_images -- v3
+use blindr_common::GUEST_VERSION;
+use hello_world_methods::{BATCH_ID, MULTIPLY_ID, PRESENT_ID};
+
+fn main() {
+    let label = std::env::args().nth(1).expect("usage: released_images <label>");
+    if std::env::var_os("BLINDR_DOCKER_BUILD").is_none() {
+        eprintln!("warning: not a docker build, these image IDs aren't reproducible");
+    }
+    for (kind, image_id) in [("Single", MULTIPLY_ID), ("Batch", BATCH_ID), ("Presentation", PRESENT_ID)] {
+        println!("    (\"{}\", GuestKind::{}, {}, {:?}),", label, kind, GUEST_VERSION, image_id);
+    }
+}
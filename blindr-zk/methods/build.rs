@@ -12,6 +12,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
+use risc0_build::{DockerOptions, GuestOptions};
+
+// BLINDR_DOCKER_BUILD=1 builds the guest in risc0's docker image, so the image
+// IDs are the same on any machine: how released IDs are made before they're
+// pinned in blindr_zk::registry::RELEASED_IMAGES. The docker context is the
+// repository root, which has the guest's path dependencies.
 fn main() {
-    risc0_build::embed_methods();
+    println!("cargo:rerun-if-env-changed=BLINDR_DOCKER_BUILD");
+    let use_docker = std::env::var_os("BLINDR_DOCKER_BUILD").map(|_| DockerOptions {
+        root_dir: Some(concat!(env!("CARGO_MANIFEST_DIR"), "/../..").into()),
+    });
+    risc0_build::embed_methods_with_options(HashMap::from([("multiply", GuestOptions { features: Vec::new(), use_docker })]));
 }
@@ -0,0 +1,60 @@
+use risc0_zkvm::guest::env;
+use blindr_common::{Approvals, Constraint, Transaction, GUEST_VERSION, MAX_BATCH, TRANSACTION_VERSION};
+use blindsign::request::BlindRequest;
+
+// main.rs for several transactions at once, each blinded against its own
+// session's R'. The constraint holds for each and for their total.
+fn main() {
+    let (transactions, constraint, public_values, blind_seeds, now, approvals): (
+        Vec<Transaction>,
+        Constraint,
+        Vec<[u8; 32]>,
+        Vec<[u8; 32]>,
+        u64,
+        Vec<Approvals>,
+    ) = env::read();
+
+    if transactions.is_empty() || transactions.len() > MAX_BATCH {
+        panic!("a batch has 1 to {} transactions", MAX_BATCH);
+    }
+    if public_values.len() != transactions.len() || blind_seeds.len() != transactions.len() {
+        panic!("need a public value and a blind seed per transaction");
+    }
+
+    let mut totp_step = None;
+    for (i, transaction) in transactions.iter().enumerate() {
+        if transaction.version != TRANSACTION_VERSION {
+            panic!("unsupported transaction version");
+        }
+        // now is public, the verifier checks it against its own clock
+        if transaction.is_expired(now) {
+            panic!("transaction expired");
+        }
+        // each transaction needs its own approval, missing ones are empty
+        let approvals = approvals.get(i).cloned().unwrap_or_default();
+        if let Err(e) = constraint.check_approvals(&transaction.message(), &approvals) {
+            panic!("transaction {} not approved: {}", i, e);
+        }
+        // one step is committed for the whole batch
+        let step = constraint.totp_step(&approvals);
+        if i > 0 && step != totp_step {
+            panic!("transaction {} has a TOTP code for another time step", i);
+        }
+        totp_step = step;
+    }
+    let amounts: Vec<_> = transactions.iter().map(|transaction| transaction.amount.clone()).collect();
+    if let Err(e) = constraint.check_batch(&amounts) {
+        panic!("bad withdrawal limit: {}", e);
+    }
+
+    let blinded_messages: Vec<[u8; 32]> = transactions
+        .iter()
+        .zip(public_values.iter().zip(&blind_seeds))
+        .map(|(transaction, (public_value, blind_seed))| BlindRequest::blind_specific_msg_seeded(public_value, &transaction.message(), blind_seed).unwrap())
+        .collect();
+
+    let issuer = constraint.identity.as_ref().map(|identity| identity.issuer.clone());
+
+    // commit the journal version, the blinded messages in order, hashed constraint, the time it was checked against, the TOTP step and the issuer
+    env::commit(&(GUEST_VERSION, blinded_messages, constraint.hash(), now, totp_step, issuer));
+}
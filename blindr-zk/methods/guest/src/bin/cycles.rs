@@ -0,0 +1,18 @@
+use risc0_zkvm::guest::env;
+use blindsign::request::BlindRequest;
+
+// the blinding main.rs does, once with dalek and once on the accelerator
+fn main() {
+    let (public_value, blind_seed, message): ([u8; 32], [u8; 32], Vec<u8>) = env::read();
+
+    let start = env::cycle_count();
+    let (software, _) = BlindRequest::new_specific_msg_seeded(&public_value, &message, &blind_seed).unwrap();
+    let software_cycles = env::cycle_count() - start;
+
+    let start = env::cycle_count();
+    let accelerated = BlindRequest::blind_specific_msg_seeded(&public_value, &message, &blind_seed).unwrap();
+    let accelerated_cycles = env::cycle_count() - start;
+
+    assert_eq!(software, accelerated);
+    env::commit(&(software_cycles as u64, accelerated_cycles as u64));
+}
@@ -0,0 +1,17 @@
+use risc0_zkvm::guest::env;
+use blindr_common::{Presentation, Transaction};
+use blindsign::signature::WiredUnblindedSigData;
+
+// shows that the prover holds the signer's signature on a transaction,
+// committing only the public key, the verifier's challenge and what the
+// prover chose to disclose
+fn main() {
+    let (transaction, signature, public_key, challenge, attributes, nullifier): (Transaction, Vec<u8>, [u8; 32], [u8; 32], Vec<String>, bool) =
+        env::read();
+
+    let signature = WiredUnblindedSigData(signature.try_into().expect("a signature is 96 bytes"));
+    let presentation = Presentation::present(&transaction, &signature, &public_key, &challenge, &attributes, nullifier)
+        .unwrap_or_else(|e| panic!("{}", e));
+
+    env::commit(&presentation);
+}
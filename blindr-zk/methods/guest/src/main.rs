@@ -2,28 +2,74 @@
 // #![no_std]
 
 use risc0_zkvm::guest::env;
-use blindr_common::{Transaction, Constraint};
+use blindr_common::{Approvals, Constraint, Message, Transaction, BitcoinTransaction, EthereumTransaction, GUEST_VERSION, TRANSACTION_VERSION};
 use blindsign::request::BlindRequest;
 
 // risc0_zkvm::guest::entry!(main);
 
-fn main() {
-    let (transaction, constraint, public_value, blind_seed): (Transaction, Constraint, _, _) = env::read();
+// checks the transaction, returning the message to blind
+fn check_transaction(transaction: &Transaction, constraint: &Constraint, now: u64) -> Vec<u8> {
+    if transaction.version != TRANSACTION_VERSION {
+        panic!("unsupported transaction version");
+    }
 
-    if transaction.amount > constraint.withdrawal_limit {
-        panic!("bad withdrawal limit");
+    // refuses other assets and overflows as well as amounts over the limit
+    if let Err(e) = constraint.check(&transaction.amount) {
+        panic!("bad withdrawal limit: {}", e);
     }
 
+    // now is public, the verifier checks it against its own clock
+    if transaction.is_expired(now) {
+        panic!("transaction expired");
+    }
+
+    transaction.message()
+}
+
+// checks the bitcoin transaction's outputs, returning its commitment to blind
+fn check_bitcoin(transaction: &BitcoinTransaction, constraint: &Constraint) -> Vec<u8> {
+    let parsed = transaction.parse().unwrap_or_else(|e| panic!("{}", e));
+    if let Err(e) = constraint.check_bitcoin(&parsed) {
+        panic!("bad bitcoin transaction: {}", e);
+    }
+    transaction.tx_commitment().unwrap().to_vec()
+}
+
+// checks what the ethereum transaction sends, returning its signing hash to blind
+fn check_ethereum(transaction: &EthereumTransaction, constraint: &Constraint) -> Vec<u8> {
+    let transfer = transaction.decode().unwrap_or_else(|e| panic!("{}", e));
+    if let Err(e) = constraint.check_ethereum(&transfer) {
+        panic!("bad ethereum transaction: {}", e);
+    }
+    transaction.signing_hash().unwrap().to_vec()
+}
+
+fn main() {
+    let (message, constraint, public_value, blind_seed, now, approvals): (Message, Constraint, _, _, u64, Approvals) = env::read();
+
     // compute the blinded_message
-    let message = transaction.message();
+    let message = match &message {
+        Message::Transaction(transaction) => check_transaction(transaction, &constraint, now),
+        Message::Bitcoin(transaction) => check_bitcoin(transaction, &constraint),
+        Message::Ethereum(transaction) => check_ethereum(transaction, &constraint),
+    };
+
+    // second factors (ie: the device's signature) approve what gets blinded
+    if let Err(e) = constraint.check_approvals(&message, &approvals) {
+        panic!("not approved: {}", e);
+    }
+    // the TOTP code's time step is public, the verifier checks it against its own clock
+    let totp_step = constraint.totp_step(&approvals);
 
-    blindsign::set_seed(Some(blind_seed));
-    let (blinded_message, _) = BlindRequest::new_specific_msg(&public_value, &message).unwrap();
-    blindsign::set_seed(None);
+    // on the BIGINT accelerator, the same e' as new_specific_msg_seeded
+    let blinded_message = BlindRequest::blind_specific_msg_seeded(&public_value, &message, &blind_seed).unwrap();
 
     // hash the constraint
     let hashed_constraint = constraint.hash();
 
-    // commit blinded message and hashed constraint
-    env::commit(&(blinded_message, hashed_constraint));
+    // the ID token's issuer is public, the subject stays hidden in the constraint
+    let issuer = constraint.identity.as_ref().map(|identity| identity.issuer.clone());
+
+    // commit the journal version, blinded message, hashed constraint, the time it was checked against, the TOTP step and the issuer
+    env::commit(&(GUEST_VERSION, blinded_message, hashed_constraint, now, totp_step, issuer));
 }
@@ -0,0 +1,110 @@
+//! On-chain verification. A blindr receipt is compressed into a Groth16
+//! receipt (with the `groth16` feature), which contracts/BlindrVerifier.sol
+//! checks through the fork's RiscZeroGroth16Verifier. OnchainProof turns a
+//! Groth16 receipt into the calldata for `BlindrVerifier.verify`.
+use risc0_zkvm::sha::Digestible;
+use sha3::{Digest as _, Keccak256};
+use hello_world_methods::MULTIPLY_ID;
+use blindr_common::GUEST_VERSION;
+use crate::{Bytes32, ProofJournal, Receipt};
+
+const VERIFY_SIGNATURE: &str = "verify(bytes,bytes32,bytes32,bytes32,uint64)";
+
+/// The guest's image ID as the bytes32 BlindrVerifier is deployed with.
+pub fn image_id() -> Bytes32 {
+    let mut image_id = [0u8; 32];
+    for (bytes, word) in image_id.chunks_exact_mut(4).zip(MULTIPLY_ID) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    image_id
+}
+
+/// The arguments of `BlindrVerifier.verify`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OnchainProof {
+    /// the Groth16 seal, abi encoded as the verifier's Seal struct
+    pub seal: Vec<u8>,
+    pub post_state_digest: Bytes32,
+    pub blinded_message: Bytes32,
+    pub constraint_hash: Bytes32,
+    pub proved_at: u64,
+}
+
+impl OnchainProof {
+    /// None unless the receipt is a Groth16 receipt with the current
+    /// guest's journal, the one BlindrVerifier is deployed for, without a
+    /// TOTP step or an issuer: BlindrVerifier doesn't check those.
+    pub fn from_receipt(receipt: &Receipt) -> Option<Self> {
+        let groth16 = receipt.inner.groth16().ok()?;
+        let journal = ProofJournal::decode(&receipt.journal, GUEST_VERSION)
+            .filter(|journal| journal.totp_step.is_none() && journal.issuer.is_none())?;
+        Some(OnchainProof {
+            seal: groth16.seal.clone(),
+            post_state_digest: groth16.claim.post.digest().into(),
+            blinded_message: journal.blinded_message,
+            constraint_hash: journal.constraint_hash,
+            proved_at: journal.proved_at,
+        })
+    }
+
+    /// ABI encoded call of `verify(seal, postStateDigest, blindedMessage,
+    /// constraintHash, provedAt)`.
+    pub fn calldata(&self) -> Vec<u8> {
+        let mut proved_at = [0u8; 32];
+        proved_at[24..].copy_from_slice(&self.proved_at.to_be_bytes());
+        // the seal is the only dynamic argument, its data follows the 5 head words
+        let mut seal_offset = [0u8; 32];
+        seal_offset[31] = 5 * 32;
+        let mut seal_len = [0u8; 32];
+        seal_len[24..].copy_from_slice(&(self.seal.len() as u64).to_be_bytes());
+
+        let mut calldata = Keccak256::digest(VERIFY_SIGNATURE.as_bytes())[..4].to_vec();
+        for word in [seal_offset, self.post_state_digest, self.blinded_message, self.constraint_hash, proved_at, seal_len] {
+            calldata.extend(word);
+        }
+        calldata.extend(&self.seal);
+        calldata.resize(calldata.len() + (32 - self.seal.len() % 32) % 32, 0);
+        calldata
+    }
+}
+
+/// Compresses a receipt from blindr_zk::prove into a Groth16 receipt.
+///
+/// Needs the fork's stark-to-snark prover, the `risc0-groth16-prover` docker
+/// image (see risc0/compact_proof), which reads and writes its files in
+/// `work_dir`.
+#[cfg(feature = "groth16")]
+pub fn to_groth16(receipt: &Receipt, work_dir: &std::path::Path) -> anyhow::Result<Receipt> {
+    use std::{fs::File, io::Cursor, process::Command};
+    use anyhow::{bail, Context};
+    use risc0_zkvm::{get_prover_server, Groth16Receipt, Groth16Seal, InnerReceipt, ProverOpts};
+
+    let prover = get_prover_server(&ProverOpts::default())?;
+    let succinct = prover.compress(receipt.inner.composite()?)?;
+    let p254 = prover.identity_p254(&succinct)?;
+
+    let input = File::create(work_dir.join("input.json"))?;
+    risc0_seal_to_json::to_json(Cursor::new(p254.get_seal_bytes()), input)?;
+
+    let status = Command::new("docker")
+        .args(["run", "--rm", "-v", &format!("{}:/mnt", work_dir.display()), "risc0-groth16-prover"])
+        .status()
+        .context("running risc0-groth16-prover")?;
+    if !status.success() {
+        bail!("risc0-groth16-prover exited with {}", status);
+    }
+
+    // the prover writes a, b, c and the public inputs without the brackets
+    let output = std::fs::read_to_string(work_dir.join("output.json"))?;
+    let (a, b, c, _): (Vec<String>, Vec<Vec<String>>, Vec<String>, Vec<String>) =
+        serde_json::from_str(&format!("[{}]", output))?;
+    let decode = |element: &String| hex::decode(element.trim_start_matches("0x"));
+    let seal = Groth16Seal {
+        a: a.iter().map(decode).collect::<Result<_, _>>()?,
+        b: b.iter().map(|pair| pair.iter().map(decode).collect()).collect::<Result<_, _>>()?,
+        c: c.iter().map(decode).collect::<Result<_, _>>()?,
+    };
+
+    let groth16 = Groth16Receipt { seal: seal.to_vec(), claim: p254.claim.clone() };
+    Ok(Receipt::new(InnerReceipt::Groth16(groth16), receipt.journal.bytes.clone()))
+}
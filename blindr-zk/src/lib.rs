@@ -1,12 +1,72 @@
 #![doc = include_str!("../README.md")]
 
-use hello_world_methods::{MULTIPLY_ID, MULTIPLY_ELF};
-use risc0_zkvm::{default_prover, ExecutorEnv, Receipt};
-use blindr_common::{Transaction, Constraint};
+pub use risc0_zkvm::Receipt;
+use risc0_zkvm::InnerReceipt;
+#[cfg(feature = "client")]
+use {
+    hello_world_methods::{BATCH_ELF, MULTIPLY_ELF, PRESENT_ELF},
+    risc0_zkvm::{default_prover, ExecutorEnv},
+    blindr_common::{Approvals, Transaction, Constraint, Message},
+};
+pub use blindr_common::Presentation;
+
+pub mod groth16;
+pub mod proof;
+pub mod registry;
+
+pub use registry::{BatchJournal, GuestImage, GuestKind, ImageRegistry, ProofJournal, ReleasedImage};
 
 type Bytes32 = [u8; 32];
 
+/// How far (in seconds) the time a proof was made at may be from the
+/// verifier's clock. Proving takes a while, so this is generous.
+pub const MAX_CLOCK_SKEW: u64 = 600;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_now() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Proves the transaction fits the constraint and hasn't expired by the
+/// host's clock. `blind_seed` is the seed the blinded message was made with,
+/// by blindsign's BlindRequest::new_specific_msg_seeded.
+#[cfg(all(feature = "client", not(target_arch = "wasm32")))]
 pub fn prove(message: &Transaction, constraint: &Constraint, public_value: &Bytes32, blind_seed: &Bytes32) -> Receipt {
+    prove_at(message, constraint, public_value, blind_seed, unix_now())
+}
+
+/// Like prove, with `now` (unix seconds) as the time the expiry is checked
+/// against. `now` is committed to the journal.
+#[cfg(feature = "client")]
+pub fn prove_at(message: &Transaction, constraint: &Constraint, public_value: &Bytes32, blind_seed: &Bytes32, now: u64) -> Receipt {
+    prove_message_at(&Message::Transaction(message.clone()), constraint, public_value, blind_seed, now)
+}
+
+/// Proves any kind of message fits the constraint, ie: a bitcoin
+/// transaction, which is blinded as its tx_commitment.
+#[cfg(all(feature = "client", not(target_arch = "wasm32")))]
+pub fn prove_message(message: &Message, constraint: &Constraint, public_value: &Bytes32, blind_seed: &Bytes32) -> Receipt {
+    prove_message_at(message, constraint, public_value, blind_seed, unix_now())
+}
+
+#[cfg(feature = "client")]
+pub fn prove_message_at(message: &Message, constraint: &Constraint, public_value: &Bytes32, blind_seed: &Bytes32, now: u64) -> Receipt {
+    prove_approved_at(message, constraint, &Approvals::default(), public_value, blind_seed, now)
+}
+
+/// Like prove_message_at, for a constraint requiring second factors: the
+/// approvals of the message's signed bytes, or a TOTP code for the time
+/// step the server gave (see Constraint::check_approvals).
+#[cfg(feature = "client")]
+pub fn prove_approved_at(
+    message: &Message,
+    constraint: &Constraint,
+    approvals: &Approvals,
+    public_value: &Bytes32,
+    blind_seed: &Bytes32,
+    now: u64,
+) -> Receipt {
     let env = ExecutorEnv::builder()
         .write(message)
         .unwrap()
@@ -16,15 +76,209 @@ pub fn prove(message: &Transaction, constraint: &Constraint, public_value: &Byte
         .unwrap()
         .write(blind_seed)
         .unwrap()
+        .write(&now)
+        .unwrap()
+        .write(approvals)
+        .unwrap()
         .build()
         .unwrap();
     default_prover().prove(env, MULTIPLY_ELF).unwrap()
 }
 
+/// Proves the transactions fit the constraint, each and in total, and
+/// haven't expired by the host's clock, blinding transaction i against
+/// `public_values[i]` with `blind_seeds[i]`. The journal commits the blinded
+/// messages in order, for the signer to sign each with its own session.
+/// `approvals[i]` are transaction i's second factors, if the constraint
+/// needs any.
+#[cfg(all(feature = "client", not(target_arch = "wasm32")))]
+pub fn prove_batch(
+    messages: &[Transaction],
+    constraint: &Constraint,
+    approvals: &[Approvals],
+    public_values: &[Bytes32],
+    blind_seeds: &[Bytes32],
+) -> Receipt {
+    prove_batch_at(messages, constraint, approvals, public_values, blind_seeds, unix_now())
+}
+
+#[cfg(feature = "client")]
+pub fn prove_batch_at(
+    messages: &[Transaction],
+    constraint: &Constraint,
+    approvals: &[Approvals],
+    public_values: &[Bytes32],
+    blind_seeds: &[Bytes32],
+    now: u64,
+) -> Receipt {
+    let env = ExecutorEnv::builder()
+        .write(&(messages, constraint, public_values, blind_seeds, now, approvals))
+        .unwrap()
+        .build()
+        .unwrap();
+    default_prover().prove(env, BATCH_ELF).unwrap()
+}
+
+/// Proves the prover holds `signature` (a wired blindsign signature) on the
+/// transaction under `public_key`, without showing the signature: the
+/// journal is a Presentation with the public key, the verifier's
+/// `challenge`, the named attributes of the transaction (see
+/// Transaction::attribute) and, if asked for, the signature's nullifier.
+/// The receipt can also be an assumption of another guest, see
+/// blindr_common::Presentation.
+#[cfg(feature = "client")]
+pub fn prove_presentation(
+    transaction: &Transaction,
+    signature: &[u8; 96],
+    public_key: &Bytes32,
+    challenge: &Bytes32,
+    attributes: &[&str],
+    nullifier: bool,
+) -> Receipt {
+    let attributes: Vec<String> = attributes.iter().map(|name| name.to_string()).collect();
+    let env = ExecutorEnv::builder()
+        .write(&(transaction, signature.to_vec(), public_key, challenge, attributes, nullifier))
+        .unwrap()
+        .build()
+        .unwrap();
+    default_prover().prove(env, PRESENT_ELF).unwrap()
+}
+
+/// The kinds of receipt a proof can come as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReceiptKind {
+    Composite,
+    Succinct,
+    Groth16,
+}
+
+impl std::str::FromStr for ReceiptKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "composite" => Ok(ReceiptKind::Composite),
+            "succinct" => Ok(ReceiptKind::Succinct),
+            "groth16" => Ok(ReceiptKind::Groth16),
+            _ => Err(format!("unknown receipt kind {:?}, expected composite, succinct or groth16", s)),
+        }
+    }
+}
+
+/// Which receipts verify. The default accepts any real proof and refuses
+/// fake (dev mode) receipts, even if the verifying process has
+/// RISC0_DEV_MODE set, and TOTP codes one time step off the verifier's.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifierPolicy {
+    /// accept fake receipts, which still needs RISC0_DEV_MODE as well
+    pub allow_fake: bool,
+    /// only accept receipts of this kind
+    pub require: Option<ReceiptKind>,
+    /// how many time steps (blindr_common::TOTP_PERIOD) the TOTP code a
+    /// proof committed to may be off the verifier's
+    pub totp_skew: u64,
+}
+
+impl Default for VerifierPolicy {
+    fn default() -> Self {
+        VerifierPolicy { allow_fake: false, require: None, totp_skew: 1 }
+    }
+}
+
+impl VerifierPolicy {
+    /// Accepts fake receipts when RISC0_DEV_MODE is set, for development.
+    pub fn dev_mode() -> Self {
+        VerifierPolicy { allow_fake: true, ..Default::default() }
+    }
+
+    /// Only accepts receipts of `kind`.
+    pub fn require(kind: ReceiptKind) -> Self {
+        VerifierPolicy { require: Some(kind), ..Default::default() }
+    }
+
+    /// Whether the receipt's kind is acceptable, before verifying it.
+    pub fn accepts(&self, receipt: &Receipt) -> bool {
+        let kind = match receipt.inner {
+            InnerReceipt::Composite(_) => ReceiptKind::Composite,
+            InnerReceipt::Succinct(_) => ReceiptKind::Succinct,
+            InnerReceipt::Groth16(_) => ReceiptKind::Groth16,
+            InnerReceipt::Fake { .. } => return self.allow_fake && self.require.is_none(),
+        };
+        self.require.is_none_or(|require| require == kind)
+    }
+}
+
+/// Verifies the receipt against the host's clock, allowing MAX_CLOCK_SKEW,
+/// with the default VerifierPolicy.
+#[cfg(not(target_arch = "wasm32"))]
 pub fn verify(receipt: &Receipt, blinded_message: &Bytes32, constraint_hash: &Bytes32) -> bool {
-    let (blinded_message_journal, constraint_hash_journal): (Bytes32, Bytes32) = receipt.journal.decode().unwrap();
+    verify_with(receipt, blinded_message, constraint_hash, &VerifierPolicy::default())
+}
+
+/// Like verify, with the given policy.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn verify_with(receipt: &Receipt, blinded_message: &Bytes32, constraint_hash: &Bytes32, policy: &VerifierPolicy) -> bool {
+    verify_at_with(receipt, blinded_message, constraint_hash, unix_now(), MAX_CLOCK_SKEW, policy)
+}
+
+/// Like verify_at_with, with the default VerifierPolicy.
+pub fn verify_at(receipt: &Receipt, blinded_message: &Bytes32, constraint_hash: &Bytes32, now: u64, max_skew: u64) -> bool {
+    verify_at_with(receipt, blinded_message, constraint_hash, now, max_skew, &VerifierPolicy::default())
+}
+
+/// Verifies the receipt if the policy accepts its kind, that it was proved
+/// with a guest image of the default ImageRegistry, that its journal commits
+/// to the blinded message and constraint hash, that the time the proof
+/// checked expiry against is within `max_skew` seconds of `now`, and that a
+/// TOTP code it checked is for a time step within the policy's totp_skew.
+pub fn verify_at_with(
+    receipt: &Receipt,
+    blinded_message: &Bytes32,
+    constraint_hash: &Bytes32,
+    now: u64,
+    max_skew: u64,
+    policy: &VerifierPolicy,
+) -> bool {
+    ImageRegistry::default().verify_at_with(receipt, blinded_message, constraint_hash, now, max_skew, policy)
+}
+
+/// Verifies a receipt from prove_batch against the host's clock, allowing
+/// MAX_CLOCK_SKEW, with the default VerifierPolicy.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn verify_batch(receipt: &Receipt, blinded_messages: &[Bytes32], constraint_hash: &Bytes32) -> bool {
+    ImageRegistry::default().verify_batch(receipt, blinded_messages, constraint_hash)
+}
+
+/// Like verify_at_with for a receipt from prove_batch: its journal commits
+/// exactly these blinded messages, in this order.
+pub fn verify_batch_at_with(
+    receipt: &Receipt,
+    blinded_messages: &[Bytes32],
+    constraint_hash: &Bytes32,
+    now: u64,
+    max_skew: u64,
+    policy: &VerifierPolicy,
+) -> bool {
+    ImageRegistry::default().verify_batch_at_with(receipt, blinded_messages, constraint_hash, now, max_skew, policy)
+}
+
+/// The presentation if the receipt is from prove_presentation, for a
+/// signature under `public_key`, answering `challenge`. With the default
+/// VerifierPolicy.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn verify_presentation(receipt: &Receipt, public_key: &Bytes32, challenge: &Bytes32) -> Option<Presentation> {
+    ImageRegistry::default().verify_presentation(receipt, public_key, challenge)
+}
 
-    receipt.verify(MULTIPLY_ID).is_ok()
-        && *blinded_message == blinded_message_journal
-        && *constraint_hash == constraint_hash_journal
+/// Like verify_presentation, refusing images deprecated at `now`, with the
+/// given policy. The presentation has no time of its own: the challenge is
+/// what makes it fresh.
+pub fn verify_presentation_at_with(
+    receipt: &Receipt,
+    public_key: &Bytes32,
+    challenge: &Bytes32,
+    now: u64,
+    policy: &VerifierPolicy,
+) -> Option<Presentation> {
+    ImageRegistry::default().verify_presentation_at_with(receipt, public_key, challenge, now, policy)
 }
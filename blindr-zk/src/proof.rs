@@ -0,0 +1,124 @@
+//! The proof format the client uploads: a header (magic, version and flags)
+//! and the bincode receipt, zstd compressed if the flags say so. Plain
+//! bincode receipts, what was uploaded before there was a header, still
+//! decode. Receipts of every kind (composite, succinct and Groth16) encode the
+//! same way; compress shrinks a composite receipt before encoding.
+use std::fmt;
+use crate::Receipt;
+
+pub const PROOF_MAGIC: &[u8; 4] = b"BLPF";
+pub const PROOF_VERSION: u8 = 1;
+/// The largest receipt a zstd compressed proof may decompress to, so a small
+/// upload can't expand without bound.
+pub const MAX_RECEIPT_LEN: usize = 64 * 1024 * 1024;
+
+const FLAG_ZSTD: u8 = 1;
+
+#[derive(Debug)]
+pub enum ProofError {
+    UnsupportedVersion(u8),
+    UnsupportedFlags(u8),
+    /// zstd support isn't built in (the `zstd` feature)
+    ZstdUnavailable,
+    /// the receipt decompresses to more than MAX_RECEIPT_LEN
+    TooLarge,
+    Malformed(String),
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProofError::UnsupportedVersion(version) => write!(f, "unsupported proof version {}", version),
+            ProofError::UnsupportedFlags(flags) => write!(f, "unsupported proof flags {:#04x}", flags),
+            ProofError::ZstdUnavailable => write!(f, "zstd compressed proofs need the zstd feature"),
+            ProofError::TooLarge => write!(f, "receipt is larger than {} bytes", MAX_RECEIPT_LEN),
+            ProofError::Malformed(e) => write!(f, "malformed proof: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProofError {}
+
+/// How encode writes the receipt.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Encoding {
+    pub zstd: bool,
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_compress(bytes: &[u8]) -> Result<Vec<u8>, ProofError> {
+    zstd::encode_all(bytes, zstd::DEFAULT_COMPRESSION_LEVEL).map_err(|e| ProofError::Malformed(e.to_string()))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_compress(_: &[u8]) -> Result<Vec<u8>, ProofError> {
+    Err(ProofError::ZstdUnavailable)
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_decompress(bytes: &[u8]) -> Result<Vec<u8>, ProofError> {
+    use std::io::Read;
+
+    let decoder = zstd::stream::Decoder::with_buffer(bytes).map_err(|e| ProofError::Malformed(e.to_string()))?;
+    // one byte past the cap tells a receipt of exactly MAX_RECEIPT_LEN from a
+    // larger one
+    let mut receipt = Vec::new();
+    decoder
+        .take(MAX_RECEIPT_LEN as u64 + 1)
+        .read_to_end(&mut receipt)
+        .map_err(|e| ProofError::Malformed(e.to_string()))?;
+    match receipt.len() > MAX_RECEIPT_LEN {
+        true => Err(ProofError::TooLarge),
+        false => Ok(receipt),
+    }
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_decompress(_: &[u8]) -> Result<Vec<u8>, ProofError> {
+    Err(ProofError::ZstdUnavailable)
+}
+
+pub fn encode(receipt: &Receipt, encoding: Encoding) -> Result<Vec<u8>, ProofError> {
+    let receipt = bincode::serialize(receipt).map_err(|e| ProofError::Malformed(e.to_string()))?;
+    let (flags, payload) = match encoding.zstd {
+        true => (FLAG_ZSTD, zstd_compress(&receipt)?),
+        false => (0, receipt),
+    };
+    let mut proof = PROOF_MAGIC.to_vec();
+    proof.extend([PROOF_VERSION, flags]);
+    proof.extend(payload);
+    Ok(proof)
+}
+
+pub fn decode(proof: &[u8]) -> Result<Receipt, ProofError> {
+    let malformed = |e: bincode::Error| ProofError::Malformed(e.to_string());
+    // a bincode receipt starts with its inner receipt's variant, never the magic
+    let Some(rest) = proof.strip_prefix(PROOF_MAGIC) else {
+        return bincode::deserialize(proof).map_err(malformed);
+    };
+    let [version, flags, payload @ ..] = rest else {
+        return Err(ProofError::Malformed("truncated header".to_string()));
+    };
+    if *version != PROOF_VERSION {
+        return Err(ProofError::UnsupportedVersion(*version));
+    }
+    match *flags {
+        0 => bincode::deserialize(payload).map_err(malformed),
+        FLAG_ZSTD => bincode::deserialize(&zstd_decompress(payload)?).map_err(malformed),
+        flags => Err(ProofError::UnsupportedFlags(flags)),
+    }
+}
+
+/// Compresses a composite receipt from prove into a succinct one, a few
+/// hundred KB at most whatever the number of segments. Other receipts are
+/// already succinct (see groth16::to_groth16 for the smallest).
+#[cfg(feature = "prove")]
+pub fn compress(receipt: &Receipt) -> anyhow::Result<Receipt> {
+    use risc0_zkvm::{get_prover_server, InnerReceipt, ProverOpts};
+
+    let InnerReceipt::Composite(composite) = &receipt.inner else {
+        return Ok(receipt.clone());
+    };
+    let succinct = get_prover_server(&ProverOpts::default())?.compress(composite)?;
+    Ok(Receipt::new(InnerReceipt::Succinct(succinct), receipt.journal.bytes.clone()))
+}
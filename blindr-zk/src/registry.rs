@@ -0,0 +1,316 @@
+//! The blindr guest images a verifier accepts. A receipt names the image it
+//! was proved with (its claim's pre state digest); the registry says which
+//! journal layout that image commits and until when it is accepted, so a
+//! server can take proofs from the old and the new guest while clients
+//! upgrade.
+//!
+//! Released images are built reproducibly (`BLINDR_DOCKER_BUILD=1`, see
+//! methods/build.rs) and pinned in RELEASED_IMAGES, so that verifiers built
+//! later, or on another machine, still recognise them. The released_images
+//! example prints the entries of a build to pin:
+//!
+//!     BLINDR_DOCKER_BUILD=1 cargo run --example released_images -- v3
+use risc0_zkvm::{serde::to_vec, sha::{Digest, Digestible}, Journal};
+use serde::{de::DeserializeOwned, Serialize};
+use hello_world_methods::{BATCH_ID, MULTIPLY_ID, PRESENT_ID};
+use blindr_common::{Presentation, GUEST_VERSION, TOTP_PERIOD};
+use crate::{Bytes32, Receipt, VerifierPolicy};
+
+/// A pinned guest image as (label, kind, journal version, image ID).
+pub type ReleasedImage = (&'static str, GuestKind, u32, [u32; 8]);
+
+/// Released guest images, oldest first. Empty until the first docker built
+/// release: IDs of local builds depend on the toolchain and aren't pinned.
+/// That release also pins the guest from before the journal had a version
+/// as version 0, built from the last commit without one.
+pub const RELEASED_IMAGES: &[ReleasedImage] = &[];
+
+/// Which guest program an image is, they commit different journals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GuestKind {
+    /// one message, committing a ProofJournal
+    Single,
+    /// several transactions, committing a BatchJournal
+    Batch,
+    /// a signed transaction shown, committing a blindr_common::Presentation
+    Presentation,
+}
+
+/// A guest image the verifier accepts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GuestImage {
+    pub label: String,
+    pub kind: GuestKind,
+    /// the journal version the guest commits (blindr_common::GUEST_VERSION
+    /// when it was built), 0 for guests from before the journal had one
+    pub version: u32,
+    pub image_id: [u32; 8],
+    /// unix seconds after which proofs from this image are refused
+    pub deprecated_after: Option<u64>,
+}
+
+impl GuestImage {
+    pub fn new(label: &str, version: u32, image_id: [u32; 8]) -> Self {
+        GuestImage { label: label.to_string(), kind: GuestKind::Single, version, image_id, deprecated_after: None }
+    }
+
+    pub fn batch(label: &str, version: u32, image_id: [u32; 8]) -> Self {
+        GuestImage { kind: GuestKind::Batch, ..GuestImage::new(label, version, image_id) }
+    }
+
+    pub fn presentation(label: &str, version: u32, image_id: [u32; 8]) -> Self {
+        GuestImage { kind: GuestKind::Presentation, ..GuestImage::new(label, version, image_id) }
+    }
+
+    /// The image this crate was built with.
+    pub fn current() -> Self {
+        GuestImage::new("current", GUEST_VERSION, MULTIPLY_ID)
+    }
+
+    /// The batch image this crate was built with.
+    pub fn current_batch() -> Self {
+        GuestImage::batch("current", GUEST_VERSION, BATCH_ID)
+    }
+
+    /// The presentation image this crate was built with.
+    pub fn current_presentation() -> Self {
+        GuestImage::presentation("current", GUEST_VERSION, PRESENT_ID)
+    }
+
+    pub fn is_deprecated(&self, now: u64) -> bool {
+        self.deprecated_after.is_some_and(|deprecated_after| now > deprecated_after)
+    }
+}
+
+// decode ignores trailing words, so a journal of another layout than the
+// image was registered with could otherwise pass
+fn decode_exact<T: DeserializeOwned + Serialize>(journal: &Journal) -> Option<T> {
+    let decoded: T = journal.decode().ok()?;
+    let words = to_vec(&decoded).ok()?;
+    words.iter().flat_map(|word| word.to_le_bytes()).eq(journal.bytes.iter().copied()).then_some(decoded)
+}
+
+/// What the guest commits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofJournal {
+    pub version: u32,
+    pub blinded_message: Bytes32,
+    pub constraint_hash: Bytes32,
+    /// the time the guest checked expiry against
+    pub proved_at: u64,
+    /// the time step of the TOTP code the guest checked, if the constraint
+    /// asks for one (always None before version 2)
+    pub totp_step: Option<u64>,
+    /// the issuer of the ID token the guest checked, if the constraint
+    /// requires an identity (always None before version 3)
+    pub issuer: Option<String>,
+}
+
+impl ProofJournal {
+    /// Decodes the journal of a guest committing `version`.
+    pub fn decode(journal: &Journal, version: u32) -> Option<Self> {
+        let decoded = match version {
+            0 => decode_exact::<(Bytes32, Bytes32, u64)>(journal).map(|(message, hash, at)| (0, message, hash, at, None, None)),
+            1 => decode_exact::<(u32, Bytes32, Bytes32, u64)>(journal).map(|(v, message, hash, at)| (v, message, hash, at, None, None)),
+            2 => decode_exact::<(u32, Bytes32, Bytes32, u64, Option<u64>)>(journal)
+                .map(|(v, message, hash, at, step)| (v, message, hash, at, step, None)),
+            _ => decode_exact(journal),
+        };
+        let (journal_version, blinded_message, constraint_hash, proved_at, totp_step, issuer) = decoded?;
+        (journal_version == version)
+            .then_some(ProofJournal { version, blinded_message, constraint_hash, proved_at, totp_step, issuer })
+    }
+}
+
+/// What the batch guest commits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchJournal {
+    pub version: u32,
+    /// in the order of the transactions
+    pub blinded_messages: Vec<Bytes32>,
+    pub constraint_hash: Bytes32,
+    pub proved_at: u64,
+    /// the time step of the TOTP codes, the same for every transaction
+    pub totp_step: Option<u64>,
+    pub issuer: Option<String>,
+}
+
+impl BatchJournal {
+    /// Decodes the journal of a batch guest committing `version`. There are
+    /// no batch guests from before the journal had one.
+    pub fn decode(journal: &Journal, version: u32) -> Option<Self> {
+        let decoded = match version {
+            1 => decode_exact::<(u32, Vec<Bytes32>, Bytes32, u64)>(journal)
+                .map(|(v, messages, hash, at)| (v, messages, hash, at, None, None)),
+            2 => decode_exact::<(u32, Vec<Bytes32>, Bytes32, u64, Option<u64>)>(journal)
+                .map(|(v, messages, hash, at, step)| (v, messages, hash, at, step, None)),
+            _ => decode_exact(journal),
+        };
+        let (journal_version, blinded_messages, constraint_hash, proved_at, totp_step, issuer) = decoded?;
+        (journal_version == version)
+            .then_some(BatchJournal { version, blinded_messages, constraint_hash, proved_at, totp_step, issuer })
+    }
+}
+
+// the committed TOTP step, if any, is within the policy's skew of the
+// verifier's
+fn totp_step_current(totp_step: Option<u64>, now: u64, policy: &VerifierPolicy) -> bool {
+    totp_step.is_none_or(|step| step.abs_diff(now / TOTP_PERIOD) <= policy.totp_skew)
+}
+
+/// The accepted guest images.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageRegistry {
+    images: Vec<GuestImage>,
+}
+
+impl Default for ImageRegistry {
+    /// The released images and the one this crate was built with.
+    fn default() -> Self {
+        let mut registry = ImageRegistry::released(RELEASED_IMAGES);
+        registry.register(GuestImage::current());
+        registry.register(GuestImage::current_batch());
+        registry.register(GuestImage::current_presentation());
+        registry
+    }
+}
+
+impl ImageRegistry {
+    /// A registry accepting no image.
+    pub fn new() -> Self {
+        ImageRegistry { images: Vec::new() }
+    }
+
+    /// A registry accepting the pinned images, ie: RELEASED_IMAGES.
+    pub fn released(released: &[ReleasedImage]) -> Self {
+        let mut registry = ImageRegistry::new();
+        for (label, kind, version, image_id) in released {
+            registry.register(GuestImage { kind: *kind, ..GuestImage::new(label, *version, *image_id) });
+        }
+        registry
+    }
+
+    /// Adds the image, replacing any registered with the same kind and
+    /// image ID.
+    pub fn register(&mut self, image: GuestImage) {
+        self.images.retain(|registered| (registered.kind, registered.image_id) != (image.kind, image.image_id));
+        self.images.push(image);
+    }
+
+    /// Refuses proofs of the image after `deprecated_after`. False if it
+    /// isn't registered.
+    pub fn deprecate(&mut self, image_id: &[u32; 8], deprecated_after: u64) -> bool {
+        let mut found = false;
+        for image in self.images.iter_mut().filter(|image| image.image_id == *image_id) {
+            image.deprecated_after = Some(deprecated_after);
+            found = true;
+        }
+        found
+    }
+
+    pub fn images(&self) -> &[GuestImage] {
+        &self.images
+    }
+
+    /// The registered image of that kind the receipt claims to be proved
+    /// with.
+    pub fn image(&self, receipt: &Receipt, kind: GuestKind) -> Option<&GuestImage> {
+        let image_id = receipt.get_claim().ok()?.pre.digest();
+        self.images.iter().find(|image| image.kind == kind && Digest::from(image.image_id) == image_id)
+    }
+
+    /// The receipt's journal, decoded as its image's version. Doesn't verify
+    /// the receipt.
+    pub fn journal(&self, receipt: &Receipt) -> Option<ProofJournal> {
+        ProofJournal::decode(&receipt.journal, self.image(receipt, GuestKind::Single)?.version)
+    }
+
+    // the image the receipt verifies against, if it isn't deprecated and
+    // the policy accepts the receipt
+    fn verified_image(&self, receipt: &Receipt, kind: GuestKind, now: u64, policy: &VerifierPolicy) -> Option<&GuestImage> {
+        let image = self.image(receipt, kind).filter(|image| !image.is_deprecated(now))?;
+        (policy.accepts(receipt) && receipt.verify(image.image_id).is_ok()).then_some(image)
+    }
+
+    /// crate::verify against the registered images.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn verify(&self, receipt: &Receipt, blinded_message: &Bytes32, constraint_hash: &Bytes32) -> bool {
+        let policy = VerifierPolicy::default();
+        self.verify_at_with(receipt, blinded_message, constraint_hash, crate::unix_now(), crate::MAX_CLOCK_SKEW, &policy)
+    }
+
+    /// crate::verify_at_with against the registered images: the receipt must
+    /// be proved with one that isn't deprecated at `now`.
+    pub fn verify_at_with(
+        &self,
+        receipt: &Receipt,
+        blinded_message: &Bytes32,
+        constraint_hash: &Bytes32,
+        now: u64,
+        max_skew: u64,
+        policy: &VerifierPolicy,
+    ) -> bool {
+        let journal = self
+            .verified_image(receipt, GuestKind::Single, now, policy)
+            .and_then(|image| ProofJournal::decode(&receipt.journal, image.version));
+        let Some(journal) = journal else {
+            return false;
+        };
+
+        *blinded_message == journal.blinded_message
+            && *constraint_hash == journal.constraint_hash
+            && journal.proved_at.abs_diff(now) <= max_skew
+            && totp_step_current(journal.totp_step, now, policy)
+    }
+
+    /// crate::verify_batch against the registered images.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn verify_batch(&self, receipt: &Receipt, blinded_messages: &[Bytes32], constraint_hash: &Bytes32) -> bool {
+        let policy = VerifierPolicy::default();
+        self.verify_batch_at_with(receipt, blinded_messages, constraint_hash, crate::unix_now(), crate::MAX_CLOCK_SKEW, &policy)
+    }
+
+    /// crate::verify_batch_at_with against the registered batch images.
+    pub fn verify_batch_at_with(
+        &self,
+        receipt: &Receipt,
+        blinded_messages: &[Bytes32],
+        constraint_hash: &Bytes32,
+        now: u64,
+        max_skew: u64,
+        policy: &VerifierPolicy,
+    ) -> bool {
+        let journal = self
+            .verified_image(receipt, GuestKind::Batch, now, policy)
+            .and_then(|image| BatchJournal::decode(&receipt.journal, image.version));
+        let Some(journal) = journal else {
+            return false;
+        };
+
+        blinded_messages == journal.blinded_messages
+            && *constraint_hash == journal.constraint_hash
+            && journal.proved_at.abs_diff(now) <= max_skew
+            && totp_step_current(journal.totp_step, now, policy)
+    }
+    /// crate::verify_presentation against the registered presentation images.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn verify_presentation(&self, receipt: &Receipt, public_key: &Bytes32, challenge: &Bytes32) -> Option<Presentation> {
+        self.verify_presentation_at_with(receipt, public_key, challenge, crate::unix_now(), &VerifierPolicy::default())
+    }
+
+    /// crate::verify_presentation_at_with against the registered presentation
+    /// images.
+    pub fn verify_presentation_at_with(
+        &self,
+        receipt: &Receipt,
+        public_key: &Bytes32,
+        challenge: &Bytes32,
+        now: u64,
+        policy: &VerifierPolicy,
+    ) -> Option<Presentation> {
+        let image = self.verified_image(receipt, GuestKind::Presentation, now, policy)?;
+        let presentation: Presentation = decode_exact(&receipt.journal)?;
+        (presentation.version == image.version && presentation.public_key == *public_key && presentation.challenge == *challenge)
+            .then_some(presentation)
+    }
+}
@@ -0,0 +1,38 @@
+use blindr_common::GUEST_VERSION;
+use blindr_zk_driver::{verify_batch_at_with, verify_at_with, BatchJournal, VerifierPolicy};
+use hello_world_methods::BATCH_ID;
+
+mod common;
+use common::{fake_receipt, fake_receipt_with, CONSTRAINT_HASH, NOW};
+
+const BLINDED_MESSAGES: [[u8; 32]; 3] = [[1; 32], [3; 32], [4; 32]];
+
+fn batch_receipt(blinded_messages: &[[u8; 32]]) -> blindr_zk_driver::Receipt {
+    let words = risc0_zkvm::serde::to_vec(&(GUEST_VERSION, blinded_messages, CONSTRAINT_HASH, NOW, None::<u64>, None::<String>)).unwrap();
+    fake_receipt_with(BATCH_ID, &words)
+}
+
+fn verifies(receipt: &blindr_zk_driver::Receipt, blinded_messages: &[[u8; 32]]) -> bool {
+    verify_batch_at_with(receipt, blinded_messages, &CONSTRAINT_HASH, NOW, 0, &VerifierPolicy::dev_mode())
+}
+
+#[test]
+fn test_batch() {
+    std::env::set_var("RISC0_DEV_MODE", "true");
+    let receipt = batch_receipt(&BLINDED_MESSAGES);
+    let journal = BatchJournal::decode(&receipt.journal, GUEST_VERSION).unwrap();
+    assert_eq!(journal.blinded_messages, BLINDED_MESSAGES);
+
+    assert!(verifies(&receipt, &BLINDED_MESSAGES));
+    // the signer signs message i with session i, so the order counts
+    assert!(!verifies(&receipt, &[BLINDED_MESSAGES[1], BLINDED_MESSAGES[0], BLINDED_MESSAGES[2]]));
+    assert!(!verifies(&receipt, &BLINDED_MESSAGES[..2]));
+    assert!(!verify_batch_at_with(&receipt, &BLINDED_MESSAGES, &[5; 32], NOW, 0, &VerifierPolicy::dev_mode()));
+    assert!(!verify_batch_at_with(&receipt, &BLINDED_MESSAGES, &CONSTRAINT_HASH, NOW + 1, 0, &VerifierPolicy::dev_mode()));
+
+    // single and batch proofs don't pass for one another
+    assert!(!verifies(&fake_receipt(), &[[1; 32]]));
+    let single = batch_receipt(&BLINDED_MESSAGES[..1]);
+    assert!(!verify_at_with(&single, &BLINDED_MESSAGES[0], &CONSTRAINT_HASH, NOW, 0, &VerifierPolicy::dev_mode()));
+    std::env::remove_var("RISC0_DEV_MODE");
+}
@@ -0,0 +1,37 @@
+use blindr_common::GUEST_VERSION;
+use hello_world_methods::MULTIPLY_ID;
+use risc0_zkvm::{
+    sha::{Digest, Digestible},
+    Assumptions, ExitCode, InnerReceipt, Journal, MaybePruned, Output, Receipt, ReceiptClaim,
+};
+
+pub const BLINDED_MESSAGE: [u8; 32] = [1; 32];
+pub const CONSTRAINT_HASH: [u8; 32] = [2; 32];
+pub const NOW: u64 = 1_700_000_000;
+
+// what dev mode proving returns for the guest
+pub fn fake_receipt() -> Receipt {
+    fake_receipt_totp(None)
+}
+
+// what it returns for a constraint requiring a TOTP code of the step
+pub fn fake_receipt_totp(totp_step: Option<u64>) -> Receipt {
+    let words = risc0_zkvm::serde::to_vec(&(GUEST_VERSION, BLINDED_MESSAGE, CONSTRAINT_HASH, NOW, totp_step, None::<String>)).unwrap();
+    fake_receipt_with(MULTIPLY_ID, &words)
+}
+
+// a fake receipt of another image, committing the given journal words
+pub fn fake_receipt_with(image_id: [u32; 8], words: &[u32]) -> Receipt {
+    let journal: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+    let claim = ReceiptClaim {
+        pre: MaybePruned::Pruned(Digest::from(image_id)),
+        post: MaybePruned::Pruned(Digest::ZERO),
+        exit_code: ExitCode::Halted(0),
+        input: Digest::ZERO,
+        output: MaybePruned::Value(Some(Output {
+            journal: MaybePruned::Pruned(Journal::new(journal.clone()).digest()),
+            assumptions: Assumptions(vec![]).into(),
+        })),
+    };
+    Receipt::new(InnerReceipt::Fake { claim }, journal)
+}
@@ -0,0 +1,27 @@
+use blindr_zk_driver::{verify_at_with, ReceiptKind, VerifierPolicy};
+
+mod common;
+use common::{fake_receipt, BLINDED_MESSAGE, CONSTRAINT_HASH, NOW};
+
+fn verifies(policy: &VerifierPolicy) -> bool {
+    verify_at_with(&fake_receipt(), &BLINDED_MESSAGE, &CONSTRAINT_HASH, NOW, 0, policy)
+}
+
+#[test]
+fn test_fake_receipts() {
+    // the only test of this binary touching RISC0_DEV_MODE, its tests run
+    // in parallel (the other tests/*.rs that set it are separate processes)
+    std::env::set_var("RISC0_DEV_MODE", "true");
+    assert!(!verifies(&VerifierPolicy::default()));
+    assert!(verifies(&VerifierPolicy::dev_mode()));
+    let required = VerifierPolicy { allow_fake: true, require: Some(ReceiptKind::Succinct), ..VerifierPolicy::default() };
+    assert!(!verifies(&required));
+    assert!(!verifies(&VerifierPolicy::require(ReceiptKind::Groth16)));
+
+    // allowing fake receipts isn't enough without dev mode
+    std::env::remove_var("RISC0_DEV_MODE");
+    assert!(!verifies(&VerifierPolicy::dev_mode()));
+
+    assert_eq!("groth16".parse(), Ok(ReceiptKind::Groth16));
+    assert!("fake".parse::<ReceiptKind>().is_err());
+}
@@ -0,0 +1,42 @@
+use blindr_common::GUEST_VERSION;
+use blindr_zk_driver::{verify_presentation_at_with, Presentation, VerifierPolicy};
+use hello_world_methods::PRESENT_ID;
+
+mod common;
+use common::{fake_receipt, fake_receipt_with, NOW};
+
+const PUBLIC_KEY: [u8; 32] = [5; 32];
+const CHALLENGE: [u8; 32] = [6; 32];
+
+fn presentation() -> Presentation {
+    Presentation {
+        version: GUEST_VERSION,
+        public_key: PUBLIC_KEY,
+        challenge: CHALLENGE,
+        attributes: vec![("receiver".to_string(), r#""bob""#.to_string())],
+        nullifier: Some([8; 32]),
+    }
+}
+
+fn presented(presentation: &Presentation, public_key: &[u8; 32], challenge: &[u8; 32]) -> Option<Presentation> {
+    let receipt = fake_receipt_with(PRESENT_ID, &risc0_zkvm::serde::to_vec(presentation).unwrap());
+    verify_presentation_at_with(&receipt, public_key, challenge, NOW, &VerifierPolicy::dev_mode())
+}
+
+#[test]
+fn test_presentation() {
+    std::env::set_var("RISC0_DEV_MODE", "true");
+    let shown = presented(&presentation(), &PUBLIC_KEY, &CHALLENGE).unwrap();
+    assert_eq!(shown, presentation());
+    assert_eq!(shown.attribute("receiver"), Some(r#""bob""#));
+
+    // another signer's, or an answer to another challenge (a replay)
+    assert_eq!(presented(&presentation(), &[0; 32], &CHALLENGE), None);
+    assert_eq!(presented(&presentation(), &PUBLIC_KEY, &[0; 32]), None);
+    assert_eq!(presented(&Presentation { version: GUEST_VERSION + 1, ..presentation() }, &PUBLIC_KEY, &CHALLENGE), None);
+
+    // a blinding proof isn't a presentation
+    let receipt = fake_receipt();
+    assert_eq!(verify_presentation_at_with(&receipt, &PUBLIC_KEY, &CHALLENGE, NOW, &VerifierPolicy::dev_mode()), None);
+    std::env::remove_var("RISC0_DEV_MODE");
+}
@@ -0,0 +1,56 @@
+use blindr_zk_driver::proof::{decode, encode, Encoding, ProofError, PROOF_MAGIC, PROOF_VERSION};
+
+mod common;
+use common::fake_receipt;
+
+#[test]
+fn test_encoding() {
+    let receipt = bincode::serialize(&fake_receipt()).unwrap();
+
+    let proof = encode(&fake_receipt(), Encoding::default()).unwrap();
+    assert_eq!(&proof[..6], b"BLPF\x01\x00");
+    assert_eq!(bincode::serialize(&decode(&proof).unwrap()).unwrap(), receipt);
+
+    // what was uploaded before the header
+    assert_eq!(bincode::serialize(&decode(&receipt).unwrap()).unwrap(), receipt);
+
+    let mut future = proof.clone();
+    future[4] = PROOF_VERSION + 1;
+    assert!(matches!(decode(&future), Err(ProofError::UnsupportedVersion(_))));
+    let mut flagged = proof.clone();
+    flagged[5] = 0x80;
+    assert!(matches!(decode(&flagged), Err(ProofError::UnsupportedFlags(0x80))));
+    assert!(matches!(decode(PROOF_MAGIC), Err(ProofError::Malformed(_))));
+    assert!(matches!(decode(&proof[..proof.len() - 1]), Err(ProofError::Malformed(_))));
+}
+
+#[test]
+fn test_zstd() {
+    let zstd = Encoding { zstd: true };
+    if cfg!(feature = "zstd") {
+        let proof = encode(&fake_receipt(), zstd).unwrap();
+        assert_eq!(proof[5], 1);
+        let receipt = bincode::serialize(&fake_receipt()).unwrap();
+        assert_eq!(bincode::serialize(&decode(&proof).unwrap()).unwrap(), receipt);
+    } else {
+        assert!(matches!(encode(&fake_receipt(), zstd), Err(ProofError::ZstdUnavailable)));
+        let mut proof = encode(&fake_receipt(), Encoding::default()).unwrap();
+        proof[5] = 1;
+        assert!(matches!(decode(&proof), Err(ProofError::ZstdUnavailable)));
+    }
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_zstd_bound() {
+    use std::io::Read;
+    use blindr_zk_driver::proof::MAX_RECEIPT_LEN;
+
+    // a few KB that decompress to one byte more than any receipt may be
+    let zeros = std::io::repeat(0).take(MAX_RECEIPT_LEN as u64 + 1);
+    let mut proof = PROOF_MAGIC.to_vec();
+    proof.extend([PROOF_VERSION, 1]);
+    proof.extend(zstd::encode_all(zeros, 0).unwrap());
+    assert!(proof.len() < 64 * 1024);
+    assert!(matches!(decode(&proof), Err(ProofError::TooLarge)));
+}
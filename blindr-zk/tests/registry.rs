@@ -0,0 +1,108 @@
+use blindr_common::GUEST_VERSION;
+use blindr_zk_driver::{registry::RELEASED_IMAGES, GuestImage, GuestKind, ImageRegistry, ReleasedImage, VerifierPolicy};
+use hello_world_methods::MULTIPLY_ID;
+
+mod common;
+use common::{fake_receipt, fake_receipt_with, BLINDED_MESSAGE, CONSTRAINT_HASH, NOW};
+
+const OLD_ID: [u32; 8] = [9; 8];
+
+fn verifies(registry: &ImageRegistry, receipt: &blindr_zk_driver::Receipt, now: u64) -> bool {
+    let policy = VerifierPolicy::dev_mode();
+    registry.verify_at_with(receipt, &BLINDED_MESSAGE, &CONSTRAINT_HASH, now, u64::MAX, &policy)
+}
+
+#[test]
+fn test_registry() {
+    // fake receipts verify whatever their image with RISC0_DEV_MODE, the
+    // registry decides which images count
+    std::env::set_var("RISC0_DEV_MODE", "true");
+    let legacy_words = risc0_zkvm::serde::to_vec(&(BLINDED_MESSAGE, CONSTRAINT_HASH, NOW)).unwrap();
+    let legacy = fake_receipt_with(OLD_ID, &legacy_words);
+
+    let mut registry = ImageRegistry::default();
+    assert_eq!(registry.image(&fake_receipt(), GuestKind::Single), Some(&GuestImage::current()));
+    assert!(verifies(&registry, &fake_receipt(), NOW));
+    assert!(!verifies(&registry, &legacy, NOW));
+
+    // the old guest, from before the journal had a version
+    registry.register(GuestImage::new("v0", 0, OLD_ID));
+    assert_eq!(registry.journal(&legacy).unwrap().proved_at, NOW);
+    assert!(verifies(&registry, &legacy, NOW));
+    assert!(registry.deprecate(&OLD_ID, NOW + 10));
+    assert!(verifies(&registry, &legacy, NOW + 10));
+    assert!(!verifies(&registry, &legacy, NOW + 11));
+    assert!(verifies(&registry, &fake_receipt(), NOW + 11));
+    assert!(!registry.deprecate(&[7; 8], NOW));
+
+    // the guest from before TOTP steps were committed
+    let v1_words = risc0_zkvm::serde::to_vec(&(1u32, BLINDED_MESSAGE, CONSTRAINT_HASH, NOW)).unwrap();
+    let v1 = fake_receipt_with([8; 8], &v1_words);
+    registry.register(GuestImage::new("v1", 1, [8; 8]));
+    assert_eq!(registry.journal(&v1).unwrap().totp_step, None);
+    assert!(verifies(&registry, &v1, NOW));
+    // and before issuers were
+    let v2_words = risc0_zkvm::serde::to_vec(&(2u32, BLINDED_MESSAGE, CONSTRAINT_HASH, NOW, None::<u64>)).unwrap();
+    let v2 = fake_receipt_with([6; 8], &v2_words);
+    registry.register(GuestImage::new("v2", 2, [6; 8]));
+    assert_eq!(registry.journal(&v2).unwrap().issuer, None);
+    assert!(verifies(&registry, &v2, NOW));
+
+    // an identity constraint's proof names the issuer
+    let issuer = Some("https://accounts.example.com".to_string());
+    let words = risc0_zkvm::serde::to_vec(&(GUEST_VERSION, BLINDED_MESSAGE, CONSTRAINT_HASH, NOW, None::<u64>, &issuer)).unwrap();
+    let identified = fake_receipt_with(MULTIPLY_ID, &words);
+    assert_eq!(registry.journal(&identified).unwrap().issuer, issuer);
+    assert!(verifies(&registry, &identified, NOW));
+
+    // a journal of another version than the image is registered with
+    registry.register(GuestImage::new("v0", 0, MULTIPLY_ID));
+    assert!(registry.journal(&fake_receipt()).is_none());
+    assert!(!verifies(&registry, &fake_receipt(), NOW));
+    registry.register(GuestImage::new("next", GUEST_VERSION + 1, MULTIPLY_ID));
+    assert!(!verifies(&registry, &fake_receipt(), NOW));
+
+    assert!(!verifies(&ImageRegistry::new(), &fake_receipt(), NOW));
+    std::env::remove_var("RISC0_DEV_MODE");
+}
+
+#[test]
+fn test_released_images() {
+    // a journal decodes as the version its pinned entry names
+    const PINNED: &[ReleasedImage] = &[("v2", GuestKind::Single, 2, [5; 8])];
+    let registry = ImageRegistry::released(PINNED);
+    let words = risc0_zkvm::serde::to_vec(&(2u32, BLINDED_MESSAGE, CONSTRAINT_HASH, NOW, Some(7u64))).unwrap();
+    let journal = registry.journal(&fake_receipt_with([5; 8], &words)).unwrap();
+    assert_eq!((journal.version, journal.proved_at, journal.totp_step), (2, NOW, Some(7)));
+    assert!(registry.journal(&fake_receipt()).is_none());
+
+    // and every released image is accepted by default
+    let images = ImageRegistry::default();
+    for (label, kind, version, image_id) in RELEASED_IMAGES {
+        assert!(images.images().contains(&GuestImage { kind: *kind, ..GuestImage::new(label, *version, *image_id) }));
+    }
+}
+
+#[test]
+fn test_default_accepts_released_images() {
+    // a receipt of a pinned image, rebuilt since, still resolves to its
+    // entry and decodes as its version
+    let images = ImageRegistry::default();
+    let current = [GuestImage::current(), GuestImage::current_batch(), GuestImage::current_presentation()];
+    for (label, kind, version, image_id) in RELEASED_IMAGES {
+        if current.iter().any(|image| image.image_id == *image_id) {
+            continue;
+        }
+        let words = match version {
+            0 => risc0_zkvm::serde::to_vec(&(BLINDED_MESSAGE, CONSTRAINT_HASH, NOW)),
+            1 => risc0_zkvm::serde::to_vec(&(1u32, BLINDED_MESSAGE, CONSTRAINT_HASH, NOW)),
+            2 => risc0_zkvm::serde::to_vec(&(2u32, BLINDED_MESSAGE, CONSTRAINT_HASH, NOW, None::<u64>)),
+            _ => risc0_zkvm::serde::to_vec(&(*version, BLINDED_MESSAGE, CONSTRAINT_HASH, NOW, None::<u64>, None::<String>)),
+        };
+        let receipt = fake_receipt_with(*image_id, &words.unwrap());
+        assert_eq!(images.image(&receipt, *kind).map(|image| image.label.as_str()), Some(*label));
+        if *kind == GuestKind::Single {
+            assert_eq!(images.journal(&receipt).unwrap().version, *version);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use blindr_common::TOTP_PERIOD;
+use blindr_zk_driver::{verify_at_with, ImageRegistry, VerifierPolicy};
+
+mod common;
+use common::{fake_receipt, fake_receipt_totp, BLINDED_MESSAGE, CONSTRAINT_HASH, NOW};
+
+fn verifies(totp_step: Option<u64>, policy: &VerifierPolicy) -> bool {
+    verify_at_with(&fake_receipt_totp(totp_step), &BLINDED_MESSAGE, &CONSTRAINT_HASH, NOW, 0, policy)
+}
+
+#[test]
+fn test_totp_skew() {
+    std::env::set_var("RISC0_DEV_MODE", "true");
+    let policy = VerifierPolicy::dev_mode();
+    let step = NOW / TOTP_PERIOD;
+    assert_eq!(ImageRegistry::default().journal(&fake_receipt_totp(Some(step))).unwrap().totp_step, Some(step));
+
+    // constraints without TOTP codes commit no step
+    assert!(verify_at_with(&fake_receipt(), &BLINDED_MESSAGE, &CONSTRAINT_HASH, NOW, 0, &policy));
+    assert!(verifies(Some(step), &policy));
+    assert!(verifies(Some(step - 1), &policy));
+    assert!(verifies(Some(step + 1), &policy));
+    assert!(!verifies(Some(step - 2), &policy));
+    assert!(!verifies(Some(step + 2), &policy));
+
+    let strict = VerifierPolicy { totp_skew: 0, ..policy };
+    assert!(verifies(Some(step), &strict));
+    assert!(!verifies(Some(step + 1), &strict));
+    std::env::remove_var("RISC0_DEV_MODE");
+}
@@ -0,0 +1,333 @@
+//! Ristretto arithmetic for the zkvm guest
+//!
+//! The software dalek backend spends most of the guest's cycles on field
+//! multiplications. Here every multiplication mod 2^255 - 19 (and mod l, for
+//! inverting u) is a single call to the zkvm's BIGINT accelerator, which
+//! multiplies 256 bit numbers modulo a third. Off the zkvm the same
+//! multiplication is done in (slow) software, so the results can be checked
+//! against dalek.
+//!
+//! Only what computing e' needs is implemented: ristretto decoding and
+//! encoding (RFC 9496), point addition and doubling in extended coordinates,
+//! a double scalar multiplication and scalar inversion.
+use std::ops::{Add, Mul, Neg, Sub};
+
+type Limbs = [u32; 8];
+
+// little endian 32 bit words
+const P: Limbs = [0xffffffed, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0x7fffffff];
+const P_MINUS_5_DIV_8: Limbs = [0xfffffffd, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0x0fffffff];
+const L: Limbs = [0x5cf5d3ed, 0x5812631a, 0xa2f79cd6, 0x14def9de, 0, 0, 0, 0x10000000];
+const L_MINUS_2: Limbs = [0x5cf5d3eb, 0x5812631a, 0xa2f79cd6, 0x14def9de, 0, 0, 0, 0x10000000];
+
+const D: Fe = Fe([0x135978a3, 0x75eb4dca, 0x4141d8ab, 0x00700a4d, 0x7779e898, 0x8cc74079, 0x2b6ffe73, 0x52036cee]);
+const D2: Fe = Fe([0x26b2f159, 0xebd69b94, 0x8283b156, 0x00e0149a, 0xeef3d130, 0x198e80f2, 0x56dffce7, 0x2406d9dc]);
+const SQRT_M1: Fe = Fe([0x4a0ea0b0, 0xc4ee1b27, 0xad2fe478, 0x2f431806, 0x3dfbd7a7, 0x2b4d0099, 0x4fc1df0b, 0x2b832480]);
+const INVSQRT_A_MINUS_D: Fe = Fe([0x805d40ea, 0x99c8fdaa, 0x5a4172be, 0x9d2f1617, 0xfe01d840, 0x16c27b91, 0xcfaffca2, 0x786c8905]);
+const ZERO: Fe = Fe([0; 8]);
+const ONE: Fe = Fe([1, 0, 0, 0, 0, 0, 0, 0]);
+
+fn less_than(a: &Limbs, b: &Limbs) -> bool {
+    for i in (0..8).rev() {
+        if a[i] != b[i] {
+            return a[i] < b[i];
+        }
+    }
+    false
+}
+
+// a + b mod 2^256 and the carry
+fn add_limbs(a: &Limbs, b: &Limbs) -> (Limbs, bool) {
+    let mut sum = [0u32; 8];
+    let mut carry = 0u64;
+    for i in 0..8 {
+        carry += a[i] as u64 + b[i] as u64;
+        sum[i] = carry as u32;
+        carry >>= 32;
+    }
+    (sum, carry != 0)
+}
+
+// a - b mod 2^256 and the borrow
+fn sub_limbs(a: &Limbs, b: &Limbs) -> (Limbs, bool) {
+    let mut difference = [0u32; 8];
+    let mut borrow = false;
+    for i in 0..8 {
+        let (d, b1) = a[i].overflowing_sub(b[i]);
+        let (d, b2) = d.overflowing_sub(borrow as u32);
+        difference[i] = d;
+        borrow = b1 || b2;
+    }
+    (difference, borrow)
+}
+
+fn bit(limbs: &Limbs, i: usize) -> u32 {
+    (limbs[i / 32] >> (i % 32)) & 1
+}
+
+/// x * y mod n, for x and y below n.
+#[cfg(target_os = "zkvm")]
+fn mul_mod(x: &Limbs, y: &Limbs, n: &Limbs) -> Limbs {
+    use risc0_zkvm_platform::syscall::{bigint, sys_bigint};
+    let mut product = [0u32; 8];
+    unsafe { sys_bigint(&mut product, bigint::OP_MULTIPLY, x, y, n) };
+    // the circuit only proves the result is congruent, not that it is reduced
+    assert!(less_than(&product, n), "bigint result not reduced");
+    product
+}
+
+/// x * y mod n, for x and y below n.
+#[cfg(not(target_os = "zkvm"))]
+fn mul_mod(x: &Limbs, y: &Limbs, n: &Limbs) -> Limbs {
+    // double and add over the bits of y, keeping the accumulator below n
+    let mut product = [0u32; 8];
+    for i in (0..256).rev() {
+        let (doubled, carry) = add_limbs(&product, &product);
+        product = match carry || !less_than(&doubled, n) {
+            true => sub_limbs(&doubled, n).0,
+            false => doubled,
+        };
+        if bit(y, i) == 1 {
+            let (sum, carry) = add_limbs(&product, x);
+            product = match carry || !less_than(&sum, n) {
+                true => sub_limbs(&sum, n).0,
+                false => sum,
+            };
+        }
+    }
+    product
+}
+
+/// x^e mod n, for x below n.
+fn pow_mod(x: &Limbs, e: &Limbs, n: &Limbs) -> Limbs {
+    let mut result = [1, 0, 0, 0, 0, 0, 0, 0];
+    for i in (0..256).rev() {
+        result = mul_mod(&result, &result, n);
+        if bit(e, i) == 1 {
+            result = mul_mod(&result, x, n);
+        }
+    }
+    result
+}
+
+fn from_bytes(bytes: &[u8; 32]) -> Limbs {
+    let mut limbs = [0u32; 8];
+    for (limb, word) in limbs.iter_mut().zip(bytes.chunks(4)) {
+        *limb = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    limbs
+}
+
+fn to_bytes(limbs: &Limbs) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (word, limb) in bytes.chunks_mut(4).zip(limbs.iter()) {
+        word.copy_from_slice(&limb.to_le_bytes());
+    }
+    bytes
+}
+
+/// An element of the field mod 2^255 - 19, always fully reduced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Fe(Limbs);
+
+impl Add for Fe {
+    type Output = Fe;
+    fn add(self, other: Fe) -> Fe {
+        // both are below P < 2^255, so the sum can't carry
+        let (sum, _) = add_limbs(&self.0, &other.0);
+        match less_than(&sum, &P) {
+            true => Fe(sum),
+            false => Fe(sub_limbs(&sum, &P).0),
+        }
+    }
+}
+
+impl Sub for Fe {
+    type Output = Fe;
+    fn sub(self, other: Fe) -> Fe {
+        match sub_limbs(&self.0, &other.0) {
+            (difference, false) => Fe(difference),
+            (difference, true) => Fe(add_limbs(&difference, &P).0),
+        }
+    }
+}
+
+impl Neg for Fe {
+    type Output = Fe;
+    fn neg(self) -> Fe {
+        ZERO - self
+    }
+}
+
+impl Mul for Fe {
+    type Output = Fe;
+    fn mul(self, other: Fe) -> Fe {
+        Fe(mul_mod(&self.0, &other.0, &P))
+    }
+}
+
+impl Fe {
+    /// None unless the bytes are the canonical encoding.
+    fn from_bytes(bytes: &[u8; 32]) -> Option<Fe> {
+        let limbs = from_bytes(bytes);
+        match less_than(&limbs, &P) {
+            true => Some(Fe(limbs)),
+            false => None,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; 32] {
+        to_bytes(&self.0)
+    }
+
+    fn square(self) -> Fe {
+        self * self
+    }
+
+    fn is_negative(&self) -> bool {
+        self.0[0] & 1 == 1
+    }
+
+    fn abs(self) -> Fe {
+        match self.is_negative() {
+            true => -self,
+            false => self,
+        }
+    }
+
+    /// (whether u/v is square, sqrt(u/v) or sqrt(i*u/v)), the
+    /// nonnegative root.
+    fn sqrt_ratio_m1(u: Fe, v: Fe) -> (bool, Fe) {
+        let v3 = v.square() * v;
+        let v7 = v3.square() * v;
+        let r = u * v3 * Fe(pow_mod(&(u * v7).0, &P_MINUS_5_DIV_8, &P));
+        let check = v * r.square();
+        let correct_sign = check == u;
+        let flipped_sign = check == -u;
+        let flipped_sign_i = check == -u * SQRT_M1;
+        let r = match flipped_sign || flipped_sign_i {
+            true => SQRT_M1 * r,
+            false => r,
+        };
+        (correct_sign || flipped_sign, r.abs())
+    }
+}
+
+/// A point in extended twisted Edwards coordinates, x = X/Z, y = Y/Z and
+/// xy = T/Z.
+#[derive(Clone, Copy, Debug)]
+struct Point {
+    x: Fe,
+    y: Fe,
+    z: Fe,
+    t: Fe,
+}
+
+const IDENTITY: Point = Point { x: ZERO, y: ONE, z: ONE, t: ZERO };
+
+impl Point {
+    /// Ristretto decoding, None for invalid encodings.
+    fn decompress(bytes: &[u8; 32]) -> Option<Point> {
+        let s = Fe::from_bytes(bytes)?;
+        if s.is_negative() {
+            return None;
+        }
+        let ss = s.square();
+        let u1 = ONE - ss;
+        let u2 = ONE + ss;
+        let u2_sqr = u2.square();
+        let v = -(D * u1.square()) - u2_sqr;
+        let (was_square, invsqrt) = Fe::sqrt_ratio_m1(ONE, v * u2_sqr);
+        let den_x = invsqrt * u2;
+        let den_y = invsqrt * den_x * v;
+        let x = (s + s) * den_x;
+        let x = x.abs();
+        let y = u1 * den_y;
+        let t = x * y;
+        if !was_square || t.is_negative() || y == ZERO {
+            return None;
+        }
+        Some(Point { x, y, z: ONE, t })
+    }
+
+    /// Ristretto encoding.
+    fn compress(&self) -> [u8; 32] {
+        let u1 = (self.z + self.y) * (self.z - self.y);
+        let u2 = self.x * self.y;
+        let (_, invsqrt) = Fe::sqrt_ratio_m1(ONE, u1 * u2.square());
+        let den1 = invsqrt * u1;
+        let den2 = invsqrt * u2;
+        let z_inv = den1 * den2 * self.t;
+        let rotate = (self.t * z_inv).is_negative();
+        let (x, y, den_inv) = match rotate {
+            true => (self.y * SQRT_M1, self.x * SQRT_M1, den1 * INVSQRT_A_MINUS_D),
+            false => (self.x, self.y, den2),
+        };
+        let y = match (x * z_inv).is_negative() {
+            true => -y,
+            false => y,
+        };
+        (den_inv * (self.z - y)).abs().to_bytes()
+    }
+
+    // add-2008-hwcd-3
+    fn add(&self, other: &Point) -> Point {
+        let a = (self.y - self.x) * (other.y - other.x);
+        let b = (self.y + self.x) * (other.y + other.x);
+        let c = self.t * D2 * other.t;
+        let d = self.z * (other.z + other.z);
+        let (e, f, g, h) = (b - a, d - c, d + c, b + a);
+        Point { x: e * f, y: g * h, z: f * g, t: e * h }
+    }
+
+    // dbl-2008-hwcd, with a = -1
+    fn double(&self) -> Point {
+        let a = self.x.square();
+        let b = self.y.square();
+        let c = self.z.square();
+        let c = c + c;
+        let e = (self.x + self.y).square() - a - b;
+        let g = b - a;
+        let f = g - c;
+        let h = -a - b;
+        Point { x: e * f, y: g * h, z: f * g, t: e * h }
+    }
+
+    // 0P, 1P, ..., 15P
+    fn multiples(&self) -> [Point; 16] {
+        let mut multiples = [IDENTITY; 16];
+        for i in 1..16 {
+            multiples[i] = multiples[i - 1].add(self);
+        }
+        multiples
+    }
+}
+
+// the 4 bit window of a little endian scalar
+fn nibble(scalar: &[u8; 32], i: usize) -> usize {
+    ((scalar[i / 2] >> (4 * (i % 2))) & 0xf) as usize
+}
+
+/// a*A + b*B, sharing the doublings (Straus) over 4 bit windows.
+fn double_scalar_mul(a: &[u8; 32], point_a: &Point, b: &[u8; 32], point_b: &Point) -> Point {
+    let (multiples_a, multiples_b) = (point_a.multiples(), point_b.multiples());
+    let mut result = IDENTITY;
+    for i in (0..64).rev() {
+        result = result.double().double().double().double();
+        result = result.add(&multiples_a[nibble(a, i)]).add(&multiples_b[nibble(b, i)]);
+    }
+    result
+}
+
+/// R = u*R' + v*P, as compressed R, None if R' isn't a valid encoding.
+pub(crate) fn generate_r(u: &[u8; 32], v: &[u8; 32], rp: &[u8; 32], p: &[u8; 32]) -> Option<[u8; 32]> {
+    let rp = Point::decompress(rp)?;
+    let p = Point::decompress(p)?;
+    Some(double_scalar_mul(u, &rp, v, &p).compress())
+}
+
+/// e / u mod l, for canonical scalars.
+pub(crate) fn generate_ep(u: &[u8; 32], e: &[u8; 32]) -> [u8; 32] {
+    let u_inv = pow_mod(&from_bytes(u), &L_MINUS_2, &L);
+    to_bytes(&mul_mod(&u_inv, &from_bytes(e), &L))
+}
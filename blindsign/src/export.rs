@@ -0,0 +1,133 @@
+//! Versioned envelopes for persisting requester side state between processes,
+//! optionally encrypted with a passphrase.
+//!
+//! A pending BlindRequest is only useful until the signer responds, but losing
+//! it (ie: the requester process restarts) means the blind signature can never
+//! be unblinded. Sealing it lets the requester keep it on disk in the
+//! meantime.
+//!
+//! # Format
+//!
+//! * magic = "BSEX" (4 bytes)
+//! * version = 1 (1 byte)
+//! * kind = 0 for plaintext, 1 for encrypted (1 byte)
+//! * the payload when kind is 0, otherwise
+//! salt (16 bytes) || nonce (12 bytes) || ChaCha20-Poly1305 ciphertext and tag
+//!
+//! The encryption key is Argon2id(passphrase, salt) with the argon2 crate's
+//! default parameters, and the 6 byte header is authenticated as associated
+//! data.
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::RngCore;
+use Error::{DecryptionFailed, ExportMalformed, ExportVersionUnsupported, PassphraseRequired};
+
+const MAGIC: &[u8; 4] = b"BSEX";
+const VERSION: u8 = 1;
+const KIND_PLAINTEXT: u8 = 0;
+const KIND_ENCRYPTED: u8 = 1;
+const HEADER_LEN: usize = 6;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Wraps the payload in a versioned envelope, encrypting it when a passphrase
+/// is provided.
+///
+/// # Returns
+///
+/// * Ok(Vec<u8>) on success, the sealed envelope.
+///
+/// * Err(::Error) on failure, which can only be the failure to derive the
+/// encryption key.
+pub fn seal(payload: &[u8], passphrase: Option<&[u8]>) -> ::Result<Vec<u8>> {
+    let mut sealed = MAGIC.to_vec();
+    sealed.push(VERSION);
+
+    let passphrase = match passphrase {
+        Some(passphrase) => passphrase,
+        None => {
+            sealed.push(KIND_PLAINTEXT);
+            sealed.extend_from_slice(payload);
+            return Ok(sealed);
+        }
+    };
+    sealed.push(KIND_ENCRYPTED);
+
+    // The salt and nonce must never come from the (possibly seeded) scalar
+    // RNG used by the protocol.
+    let mut rng = rand::thread_rng();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.try_fill_bytes(&mut salt)?;
+    rng.try_fill_bytes(&mut nonce)?;
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+    let aad = sealed.clone();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: payload, aad: &aad })
+        .map_err(|_| ExportMalformed)?;
+
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Unwraps an envelope created by seal, decrypting it if needed.
+///
+/// # Returns
+///
+/// * Ok(Vec<u8>) on success, the original payload.
+///
+/// * Err(::Error) on failure, which could be due to the envelope being
+/// malformed or of an unknown version, a passphrase being required but not
+/// provided, or decryption failing (ie: the passphrase is wrong).
+pub fn open(sealed: &[u8], passphrase: Option<&[u8]>) -> ::Result<Vec<u8>> {
+    let encrypted = is_encrypted(sealed)?;
+    let body = &sealed[HEADER_LEN..];
+    match encrypted {
+        false => Ok(body.to_vec()),
+        true => {
+            let passphrase = passphrase.ok_or(PassphraseRequired)?;
+            if body.len() < SALT_LEN + NONCE_LEN {
+                return Err(ExportMalformed);
+            }
+            let (salt, rest) = body.split_at(SALT_LEN);
+            let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+            let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, salt)?);
+            cipher
+                .decrypt(
+                    Nonce::from_slice(nonce),
+                    Payload { msg: ciphertext, aad: &sealed[..HEADER_LEN] },
+                )
+                .map_err(|_| DecryptionFailed)
+        }
+    }
+}
+
+/// Returns whether the envelope is encrypted (and therefore needs a
+/// passphrase to open), or an error if it isn't a valid envelope.
+pub fn is_encrypted(sealed: &[u8]) -> ::Result<bool> {
+    if sealed.len() < HEADER_LEN || &sealed[..4] != MAGIC {
+        return Err(ExportMalformed);
+    }
+    if sealed[4] != VERSION {
+        return Err(ExportVersionUnsupported(sealed[4]));
+    }
+    match sealed[5] {
+        KIND_PLAINTEXT => Ok(false),
+        KIND_ENCRYPTED => Ok(true),
+        _ => Err(ExportMalformed),
+    }
+}
+
+fn derive_key(passphrase: &[u8], salt: &[u8]) -> ::Result<Key> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase, salt, &mut key)
+        .map_err(|_| ExportMalformed)?;
+    Ok(key)
+}
@@ -17,6 +17,12 @@ extern crate failure;
 extern crate rand;
 extern crate typenum;
 extern crate subtle;
+#[cfg(feature = "export")]
+extern crate argon2;
+#[cfg(feature = "export")]
+extern crate chacha20poly1305;
+#[cfg(target_os = "zkvm")]
+extern crate risc0_zkvm_platform;
 
 // Imported crates with used macros
 #[macro_use]
@@ -24,9 +30,15 @@ extern crate failure_derive;
 
 // The public interface
 pub mod keypair;
+pub mod nullifier;
 pub mod request;
 pub mod session;
 pub mod signature;
+#[cfg(feature = "export")]
+pub mod export;
+
+// Internal modules
+mod accel;
 
 /// The Result type used
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -40,6 +52,18 @@ pub enum Error {
     WiredScalarMalformed,
     #[fail(display = "failed to convert wired ristretto point to ristretto point")]
     WiredRistrettoPointMalformed,
+    #[fail(display = "exported data is malformed")]
+    ExportMalformed,
+    #[fail(display = "unsupported export version {}", _0)]
+    ExportVersionUnsupported(u8),
+    #[fail(display = "exported data is encrypted and requires a passphrase")]
+    PassphraseRequired,
+    #[fail(display = "failed to decrypt exported data, wrong passphrase or corrupted data")]
+    DecryptionFailed,
+    #[fail(display = "the signature was already spent")]
+    DoubleSpend,
+    #[fail(display = "nullifier store failed: {}", _0)]
+    NullifierStoreFailed(String),
 }
 
 impl From<rand::Error> for Error {
@@ -66,8 +90,21 @@ pub(crate) fn random_scalar() -> Scalar {
             Some(seed) => Box::new(StdRng::from_seed(seed)),
             None => Box::new(rand::thread_rng())
         };
-        let mut value = [0u8; 64];
-        rng.fill_bytes(&mut value);
-        Scalar::from_bytes_mod_order_wide(&value)
+        scalar_from(&mut *rng)
     }
 }
+
+fn scalar_from<R: RngCore + ?Sized>(rng: &mut R) -> Scalar {
+    let mut value = [0u8; 64];
+    rng.fill_bytes(&mut value);
+    Scalar::from_bytes_mod_order_wide(&value)
+}
+
+/// The blinding factors u and v of a request made from `seed`, drawn one
+/// after the other from the same StdRng so they are independent.
+pub(crate) fn seeded_scalars(seed: &[u8; 32]) -> (Scalar, Scalar) {
+    let mut rng = StdRng::from_seed(*seed);
+    let u = scalar_from(&mut rng);
+    let v = scalar_from(&mut rng);
+    (u, v)
+}
@@ -0,0 +1,194 @@
+//! Replay protection for unblinded signatures.
+//!
+//! The signer never sees the unblinded signature, so it can't stop the
+//! requester from presenting the same signature twice. Whoever accepts
+//! signatures (ie: a payment processor) keeps a registry of the nullifiers of
+//! the signatures it has accepted and refuses repeats.
+//!
+//! # Nullifiers
+//!
+//! The nullifier of a signature is SHA-256("blindsign-nullifier" || R || e).
+//! Given the signer's key, (R, e) fixes S (SP == e*Qs + R), and both are
+//! canonically encoded in the wired format, so a signature has exactly one
+//! nullifier. When a proof commits its own nullifier instead (ie: one derived
+//! in the zkvm guest), use Nullifier::from_committed.
+//!
+//! # Stores
+//!
+//! * MemoryNullifierStore remembers every nullifier exactly.
+//!
+//! * BloomNullifierStore uses a fixed amount of memory for large sets. It never
+//! accepts a repeat, but with the configured false positive rate it refuses a
+//! signature that was never presented.
+use std::collections::HashSet;
+use std::sync::Mutex;
+use digest::Digest;
+use signature::UnblindedSigData;
+use Error::{DoubleSpend, NullifierStoreFailed};
+
+const DOMAIN: &[u8] = b"blindsign-nullifier";
+
+/// Identifies a signature for replay protection, see the module docs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Nullifier(pub [u8; 32]);
+
+impl Nullifier {
+    /// Derives the nullifier from (R, e).
+    pub fn from_sig(sig: &UnblindedSigData) -> Self {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(DOMAIN);
+        hasher.update(sig.r().compress().as_bytes());
+        hasher.update(sig.e().as_bytes());
+        let mut nullifier = [0u8; 32];
+        nullifier.copy_from_slice(hasher.finalize().as_slice());
+        Nullifier(nullifier)
+    }
+
+    /// Uses a nullifier committed elsewhere (ie: in a proof's journal) as is.
+    pub fn from_committed(nullifier: [u8; 32]) -> Self {
+        Nullifier(nullifier)
+    }
+
+    /// Returns a reference to the internal [u8; 32]
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+/// Remembers spent nullifiers.
+pub trait NullifierStore: Send + Sync {
+    /// Atomically records the nullifier, returning false if it was already
+    /// recorded. Of any number of concurrent calls with the same nullifier,
+    /// at most one returns true.
+    fn insert(&self, nullifier: &Nullifier) -> ::Result<bool>;
+
+    fn contains(&self, nullifier: &Nullifier) -> ::Result<bool>;
+}
+
+fn poisoned<T>(_: T) -> ::Error {
+    NullifierStoreFailed("lock poisoned".to_string())
+}
+
+/// Exact in-memory store.
+#[derive(Default)]
+pub struct MemoryNullifierStore {
+    spent: Mutex<HashSet<Nullifier>>,
+}
+
+impl MemoryNullifierStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NullifierStore for MemoryNullifierStore {
+    fn insert(&self, nullifier: &Nullifier) -> ::Result<bool> {
+        Ok(self.spent.lock().map_err(poisoned)?.insert(*nullifier))
+    }
+
+    fn contains(&self, nullifier: &Nullifier) -> ::Result<bool> {
+        Ok(self.spent.lock().map_err(poisoned)?.contains(nullifier))
+    }
+}
+
+/// Bloom filter store, see the module docs for its false positives.
+pub struct BloomNullifierStore {
+    bits: Mutex<Vec<u64>>,
+    num_bits: u64,
+    num_hashes: u64,
+}
+
+impl BloomNullifierStore {
+    /// Sizes the filter to keep the false positive rate at or below
+    /// `false_positive_rate` until `expected_items` nullifiers are stored.
+    ///
+    /// # Panics
+    ///
+    /// If expected_items is 0 or false_positive_rate isn't in (0, 1).
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        assert!(expected_items > 0, "expected_items must be positive");
+        assert!(
+            false_positive_rate > 0.0 && false_positive_rate < 1.0,
+            "false_positive_rate must be in (0, 1)"
+        );
+        let ln2 = ::std::f64::consts::LN_2;
+        let n = expected_items as f64;
+        let num_bits = (-n * false_positive_rate.ln() / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().max(1.0) as u64;
+        BloomNullifierStore {
+            bits: Mutex::new(vec![0; num_bits.div_ceil(64) as usize]),
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    // Nullifiers are already uniform hashes, so two 64 bit halves of one are
+    // enough for double hashing.
+    fn positions<'a>(&'a self, nullifier: &Nullifier) -> impl Iterator<Item = u64> + 'a {
+        let mut h1 = [0u8; 8];
+        let mut h2 = [0u8; 8];
+        h1.copy_from_slice(&nullifier.0[0..8]);
+        h2.copy_from_slice(&nullifier.0[8..16]);
+        let h1 = u64::from_le_bytes(h1);
+        let h2 = u64::from_le_bytes(h2) | 1;
+        (0..self.num_hashes).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+}
+
+impl NullifierStore for BloomNullifierStore {
+    fn insert(&self, nullifier: &Nullifier) -> ::Result<bool> {
+        let mut bits = self.bits.lock().map_err(poisoned)?;
+        let mut fresh = false;
+        for position in self.positions(nullifier) {
+            let (word, bit) = ((position / 64) as usize, 1u64 << (position % 64));
+            fresh |= bits[word] & bit == 0;
+            bits[word] |= bit;
+        }
+        Ok(fresh)
+    }
+
+    fn contains(&self, nullifier: &Nullifier) -> ::Result<bool> {
+        let bits = self.bits.lock().map_err(poisoned)?;
+        Ok(self
+            .positions(nullifier)
+            .all(|position| bits[(position / 64) as usize] & (1u64 << (position % 64)) != 0))
+    }
+}
+
+/// Refuses signatures (or committed nullifiers) that were already spent.
+pub struct NullifierRegistry<S> {
+    store: S,
+}
+
+impl<S: NullifierStore> NullifierRegistry<S> {
+    pub fn new(store: S) -> Self {
+        NullifierRegistry { store }
+    }
+
+    /// Marks the signature as spent. Authenticate it first: spending an
+    /// unauthenticated signature lets anyone burn nullifiers.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Nullifier) on success, the nullifier that was recorded.
+    ///
+    /// * Err(::Error) on failure, which is DoubleSpend if the signature was
+    /// already spent, or an error from the store.
+    pub fn spend(&self, sig: &UnblindedSigData) -> ::Result<Nullifier> {
+        let nullifier = Nullifier::from_sig(sig);
+        self.spend_nullifier(&nullifier)?;
+        Ok(nullifier)
+    }
+
+    /// The same as spend, for a nullifier derived elsewhere.
+    pub fn spend_nullifier(&self, nullifier: &Nullifier) -> ::Result<()> {
+        match self.store.insert(nullifier)? {
+            true => Ok(()),
+            false => Err(DoubleSpend),
+        }
+    }
+
+    pub fn is_spent(&self, sig: &UnblindedSigData) -> ::Result<bool> {
+        self.store.contains(&Nullifier::from_sig(sig))
+    }
+}
@@ -5,8 +5,9 @@
 //! This **does not** include **any** networking code, such as any code to
 //! actually request protocol initiation. Also, the request for protocol
 //! initiation is neither defined nor implemented by this crate.
+use accel;
 use curve25519_dalek::{
-    constants::RISTRETTO_BASEPOINT_POINT,
+    constants::{RISTRETTO_BASEPOINT_COMPRESSED, RISTRETTO_BASEPOINT_POINT},
     ristretto::{CompressedRistretto, RistrettoPoint},
     scalar::Scalar,
 };
@@ -65,7 +66,8 @@ impl BlindRequest {
     // where
     //     H: Digest<OutputSize = U64> + Default,
     {
-        initiate::<&[u8; 32]>(rp, crate::random_scalar().as_bytes())
+        let m = crate::random_scalar();
+        initiate::<&[u8; 32]>(rp, m.as_bytes(), crate::random_scalar(), crate::random_scalar())
     }
 
     /// The same as new, but allows for passing in a specific message value 'm'
@@ -75,7 +77,44 @@ impl BlindRequest {
         // H: Digest<OutputSize = U64> + Default,
         M: AsRef<[u8]>,
     {
-        initiate::<M>(rp, m)
+        initiate::<M>(rp, m, crate::random_scalar(), crate::random_scalar())
+    }
+
+    /// The same as new_specific_msg, but the blinding factors u and v are
+    /// derived from 'seed' instead of the thread's RNG, so that e' can be
+    /// recomputed from the seed (ie: by the zk guest, see
+    /// blind_specific_msg_seeded).
+    ///
+    /// # Note
+    ///
+    /// The seed determines u and v: it must be random, used for one request
+    /// only and kept as private as the request itself.
+    pub fn new_specific_msg_seeded<M>(rp: &[u8; 32], m: M, seed: &[u8; 32]) -> ::Result<([u8; 32], Self)>
+    where
+        M: AsRef<[u8]>,
+    {
+        let (u, v) = crate::seeded_scalars(seed);
+        initiate::<M>(rp, m, u, v)
+    }
+
+    /// Only the e' of new_specific_msg_seeded for the same seed, computed on
+    /// the zkvm's BIGINT accelerator (see the accel module) rather than
+    /// dalek's software field arithmetic. For the guest, which recomputes e'
+    /// from the same seed but never needs the request itself.
+    ///
+    /// # Note
+    ///
+    /// Off the zkvm the accelerator is emulated in software and this is much
+    /// slower than new_specific_msg_seeded.
+    pub fn blind_specific_msg_seeded<M>(rp: &[u8; 32], m: M, seed: &[u8; 32]) -> ::Result<[u8; 32]>
+    where
+        M: AsRef<[u8]>,
+    {
+        let (u, v) = crate::seeded_scalars(seed);
+        let r = accel::generate_r(u.as_bytes(), v.as_bytes(), rp, RISTRETTO_BASEPOINT_COMPRESSED.as_bytes())
+            .ok_or(WiredRistrettoPointMalformed)?;
+        let e = hash_e(&r, m.as_ref());
+        Ok(accel::generate_ep(u.as_bytes(), e.as_bytes()))
     }
 
     /// Input the blinded signature S' from the signer, consumes self and
@@ -110,13 +149,106 @@ impl BlindRequest {
         let sp = Scalar::from_canonical_bytes(*sp).ok_or(WiredScalarMalformed)?;
         Ok(UnblindedSigData::new(self.e, sp * self.u + self.v, self.r))
     }
+
+    /// Exports the request so it can be restored with import, for instance by
+    /// another process once the signer responds. The export is encrypted
+    /// when a passphrase is provided. See the export module for the format.
+    ///
+    /// # Note
+    ///
+    /// The export contains the blinding factors u and v. Anyone holding them
+    /// can link the unblinded signature to this request.
+    #[cfg(feature = "export")]
+    pub fn export(&self, passphrase: Option<&[u8]>) -> ::Result<Vec<u8>> {
+        ::export::seal(WiredBlindRequest::from(self).as_bytes(), passphrase)
+    }
+
+    /// Restores a request previously exported with export.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BlindRequest) on success.
+    ///
+    /// * Err(::Error) on failure, which could be due to the export being
+    /// malformed, encrypted without a passphrase being provided, or failing to
+    /// decrypt with the provided passphrase.
+    #[cfg(feature = "export")]
+    pub fn import(exported: &[u8], passphrase: Option<&[u8]>) -> ::Result<Self> {
+        let payload = ::export::open(exported, passphrase)?;
+        if payload.len() != 128 {
+            return Err(::Error::ExportMalformed);
+        }
+        let mut arr = [0; 128];
+        arr.copy_from_slice(&payload);
+        WiredBlindRequest(arr).to_internal_format()
+    }
+}
+
+/// The BlindRequest in wired form, for persisting a pending request until the
+/// signer responds. The wired form consists of u || v || R || e, with each
+/// component consisting of 32 bytes.
+///
+/// # Note
+///
+/// u and v are the blinding factors, so the wired form must be kept as
+/// private as the BlindRequest itself.
+pub struct WiredBlindRequest(pub [u8; 128]);
+
+impl<'a> From<&'a BlindRequest> for WiredBlindRequest {
+    fn from(br: &'a BlindRequest) -> Self {
+        let mut arr = [0; 128];
+        arr[0..32].copy_from_slice(br.u.as_bytes());
+        arr[32..64].copy_from_slice(br.v.as_bytes());
+        arr[64..96].copy_from_slice(br.r.compress().as_bytes());
+        arr[96..128].copy_from_slice(br.e.as_bytes());
+        WiredBlindRequest(arr)
+    }
+}
+
+impl WiredBlindRequest {
+    /// Converts WiredBlindRequest into BlindRequest.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BlindRequest) on success
+    ///
+    /// * Err(::Error) on failure, which could be due to any component of the
+    /// internal [u8; 128] being malformed.
+    pub fn to_internal_format(&self) -> ::Result<BlindRequest> {
+        let mut u_arr = [0; 32];
+        let mut v_arr = [0; 32];
+        let mut r_arr = [0; 32];
+        let mut e_arr = [0; 32];
+        u_arr.copy_from_slice(&self.0[0..32]);
+        v_arr.copy_from_slice(&self.0[32..64]);
+        r_arr.copy_from_slice(&self.0[64..96]);
+        e_arr.copy_from_slice(&self.0[96..128]);
+        Ok(BlindRequest {
+            u: Scalar::from_canonical_bytes(u_arr).ok_or(WiredScalarMalformed)?,
+            v: Scalar::from_canonical_bytes(v_arr).ok_or(WiredScalarMalformed)?,
+            r: CompressedRistretto(r_arr)
+                .decompress()
+                .ok_or(WiredRistrettoPointMalformed)?,
+            e: Scalar::from_canonical_bytes(e_arr).ok_or(WiredScalarMalformed)?,
+        })
+    }
+
+    /// Returns a reference to the internal [u8; 128]
+    pub fn as_bytes(&self) -> &[u8; 128] {
+        &self.0
+    }
+
+    /// Returns a copy of the internal [u8; 128]
+    pub fn to_bytes(&self) -> [u8; 128] {
+        self.0
+    }
 }
 
 // Implementation internal functions, not exposed to crate users -->
 
 /// Internal code for all new variants (ie: with random or specific msg)
 // fn initiate<H, M>(rp: &[u8; 32], m: M) -> ::Result<([u8; 32], BlindRequest)>
-fn initiate<M>(rp: &[u8; 32], m: M) -> ::Result<([u8; 32], BlindRequest)>
+fn initiate<M>(rp: &[u8; 32], m: M, u: Scalar, v: Scalar) -> ::Result<([u8; 32], BlindRequest)>
 where
     // H: Digest<OutputSize = U64> + Default,
     M: AsRef<[u8]>,
@@ -127,9 +259,7 @@ where
         .decompress()
         .ok_or(WiredRistrettoPointMalformed)?;
 
-    // The random scalars u and v must be generated
-    let u = crate::random_scalar();
-    let v = crate::random_scalar();
+    // u and v are the random scalars of the caller
 
     // R = u*R' + v*P
     let r = generate_r(u, v, rp);
@@ -162,9 +292,14 @@ pub(crate) fn generate_e(r: RistrettoPoint, m: &[u8]) -> Scalar
 // where
 //     H: Digest<OutputSize = U64> + Default,
 {
+    hash_e(r.compress().as_bytes(), m)
+}
+
+// e = H(R||m) with R already compressed
+fn hash_e(r: &[u8; 32], m: &[u8]) -> Scalar {
     use std::convert::TryInto;
     let mut hasher = sha2::Sha256::new();
-    hasher.update(r.compress().as_bytes());
+    hasher.update(r);
     hasher.update(m);
     let result = hasher.finalize();
     let arr = result.as_slice().try_into().unwrap();
@@ -7,6 +7,7 @@ use curve25519_dalek::{
 use Error::{WiredRistrettoPointMalformed, WiredScalarMalformed};
 use subtle::ConstantTimeEq;
 use request;
+use nullifier::Nullifier;
 
 /// The data required for authenticating the unblinded signature,
 ///
@@ -56,6 +57,22 @@ impl UnblindedSigData {
         Self { e, s, r }
     }
 
+    /// The e value, H(R || msg).
+    pub fn e(&self) -> Scalar {
+        self.e
+    }
+
+    /// The unblinded R value.
+    pub fn r(&self) -> RistrettoPoint {
+        self.r
+    }
+
+    /// The nullifier identifying this signature for replay protection, see
+    /// the nullifier module.
+    pub fn nullifier(&self) -> Nullifier {
+        Nullifier::from_sig(self)
+    }
+
     /// Authenticates that the signature value S on e is valid with R and the
     /// provided public key (ie: that S' was created on e' with the private key
     /// associated with the provided public key, in the session that was
@@ -6,6 +6,7 @@ extern crate sha3;
 
 #[cfg(test)]
 mod integration_test {
+    use curve25519_dalek::ristretto::CompressedRistretto;
     use sha3::Sha3_512;
 
     use blindsign::{
@@ -63,7 +64,7 @@ mod integration_test {
 
         // Initiates a new blind request on the requester side, which is input R' and
         // generates e' (ep). In this case, using a specific message.
-        let (ep, br) = BlindRequest::new_specific_msg::<Sha3_512, &str>(&rp, "specific").unwrap();
+        let (ep, br) = BlindRequest::new_specific_msg::<&str>(&rp, "specific").unwrap();
 
         // Signs the e' value, which is essentially the blinded message hash. Produces
         // S' (sp), which is the blind signature.
@@ -83,4 +84,105 @@ mod integration_test {
         // A demonstration of authenticating the blind signature
         assert!(sig.authenticate(keypair.public()));
     }
+
+    #[cfg(feature = "export")]
+    #[test]
+    fn session_with_exported_request() {
+        let keypair = BlindKeypair::generate().unwrap();
+        let (rp, bs) = BlindSession::new().unwrap();
+        let (ep, br) = BlindRequest::new_specific_msg(&rp, "specific").unwrap();
+
+        // The requester persists the pending request, encrypted with a
+        // passphrase, and restores it once the signer has responded.
+        let exported = br.export(Some(b"passphrase")).unwrap();
+        drop(br);
+
+        let sp = bs.sign_ep(&ep, keypair.private()).unwrap();
+
+        match BlindRequest::import(&exported, None) {
+            Err(Error::PassphraseRequired) => {}
+            _ => panic!("encrypted export opened without a passphrase"),
+        }
+        match BlindRequest::import(&exported, Some(b"wrong")) {
+            Err(Error::DecryptionFailed) => {}
+            _ => panic!("encrypted export opened with the wrong passphrase"),
+        }
+
+        let br = BlindRequest::import(&exported, Some(b"passphrase")).unwrap();
+        let sig = br.gen_signed_msg(&sp).unwrap();
+        assert!(sig.msg_authenticate::<Sha3_512, _>(keypair.public(), "specific"));
+    }
+
+    #[cfg(feature = "export")]
+    #[test]
+    fn import_truncated_export() {
+        let (rp, _bs) = BlindSession::new().unwrap();
+        let (_ep, br) = BlindRequest::new_specific_msg(&rp, "specific").unwrap();
+        let exported = br.export(None).unwrap();
+
+        // Every prefix shorter than the header, including the empty one, is
+        // rejected rather than read past.
+        for len in 0..6 {
+            match BlindRequest::import(&exported[..len], None) {
+                Err(Error::ExportMalformed) => {}
+                _ => panic!("truncated export of {} bytes wasn't rejected", len),
+            }
+        }
+    }
+
+    #[test]
+    fn session_with_spent_signature() {
+        use blindsign::nullifier::{BloomNullifierStore, MemoryNullifierStore, NullifierRegistry};
+
+        let keypair = BlindKeypair::generate().unwrap();
+        let (rp, bs) = BlindSession::new().unwrap();
+        let (ep, br) = BlindRequest::new_specific_msg(&rp, "specific").unwrap();
+        let sp = bs.sign_ep(&ep, keypair.private()).unwrap();
+        let sig = br.gen_signed_msg(&sp).unwrap();
+
+        // The wired round trip keeps the nullifier.
+        let wired = WiredUnblindedSigData::from(sig).to_internal_format().unwrap();
+        assert_eq!(sig.nullifier(), wired.nullifier());
+
+        // The verifier authenticates the signature, then spends it once.
+        assert!(sig.msg_authenticate::<Sha3_512, _>(keypair.public(), "specific"));
+        let registry = NullifierRegistry::new(MemoryNullifierStore::new());
+        assert!(!registry.is_spent(&sig).unwrap());
+        registry.spend(&sig).unwrap();
+        match registry.spend(&wired) {
+            Err(Error::DoubleSpend) => {}
+            _ => panic!("spent the same signature twice"),
+        }
+
+        let registry = NullifierRegistry::new(BloomNullifierStore::new(1000, 0.001));
+        registry.spend(&sig).unwrap();
+        assert!(registry.is_spent(&wired).unwrap());
+        match registry.spend(&wired) {
+            Err(Error::DoubleSpend) => {}
+            _ => panic!("spent the same signature twice"),
+        }
+    }
+
+    #[test]
+    fn accelerated_blinded_msg() {
+        // The guest's e' has to match the requester's for the same seed.
+        let (rp, _) = BlindSession::new().unwrap();
+        for seed in [[1u8; 32], [7u8; 32]].iter() {
+            let (ep, _) = BlindRequest::new_specific_msg_seeded(&rp, "specific", seed).unwrap();
+            let accelerated = BlindRequest::blind_specific_msg_seeded(&rp, "specific", seed).unwrap();
+            assert_eq!(ep, accelerated);
+        }
+
+        // R' is refused exactly when dalek refuses it: non-canonical,
+        // negative or not on the curve.
+        for byte in [0u8, 1, 2, 4, 8, 0x7f, 0xff].iter() {
+            let rp = [*byte; 32];
+            let decompressed = CompressedRistretto(rp).decompress();
+            match BlindRequest::blind_specific_msg_seeded(&rp, "specific", &[1u8; 32]) {
+                Ok(_) => assert!(decompressed.is_some()),
+                Err(Error::WiredRistrettoPointMalformed) => assert!(decompressed.is_none()),
+                Err(e) => panic!("{}", e),
+            }
+        }
+    }
 }
@@ -1,4 +1,6 @@
 cargo build -r
 cp target/release/liblibblindr.dylib libblindr.so
 cp libblindr.so ../sdk/
-cp libblindr.so ../backend/app/
\ No newline at end of file
+cp libblindr.so ../backend/app/
+cp libblindr.pyi ../sdk/
+cp libblindr.pyi ../backend/app/
\ No newline at end of file
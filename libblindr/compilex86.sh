@@ -1,4 +1,6 @@
 cross build -r --target x86_64-apple-darwin
 cp target/x86_64-apple-darwin/release/liblibblindr.dylib libblindr.so
 cp libblindr.so ../sdk/
-cp libblindr.so ../backend/app/
\ No newline at end of file
+cp libblindr.so ../backend/app/
+cp libblindr.pyi ../sdk/
+cp libblindr.pyi ../backend/app/
\ No newline at end of file
@@ -39,7 +39,7 @@ proof = bl.prove_message_fits_constraint(request, constraint, transaction, serve
 
 # server verifies the proof of transaction validity of constraint hash and then blindly signs the message
 
-is_valid_proof = bl.verify_message_fits_constraint(proof, blinded_transaction, contraint_hash)
+is_valid_proof = bl.verify_message_fits_constraint(proof, blinded_transaction, contraint_hash, allow_fake=True)
 assert(is_valid_proof)
 
 blinded_signature = bl.server_sign(server_sk, server_sv, blinded_transaction)
@@ -0,0 +1,181 @@
+# Type stubs for the libblindr extension module.
+from typing import Awaitable, Generator, List, Optional, Sequence, Tuple
+
+class Keypair:
+    """The signer's long lived keypair."""
+    @staticmethod
+    def generate() -> Keypair: ...
+    @staticmethod
+    def from_bytes(private_key: bytes) -> Keypair: ...
+    @staticmethod
+    def from_hex(private_key: str) -> Keypair: ...
+    @property
+    def public_key(self) -> bytes: ...
+    def private_key(self) -> bytes: ...
+
+class SignerSession:
+    """A single use signing session (k, R' = kP)."""
+    @staticmethod
+    def generate() -> SignerSession: ...
+    @staticmethod
+    def from_bytes(private_value: bytes) -> SignerSession: ...
+    @staticmethod
+    def from_hex(private_value: str) -> SignerSession: ...
+    @property
+    def public_value(self) -> bytes: ...
+    @property
+    def used(self) -> bool: ...
+    def private_value(self) -> bytes: ...
+    def sign(self, keypair: Keypair, blinded_message: bytes) -> bytes: ...
+
+class Transaction:
+    def __init__(
+        self,
+        sender: str,
+        receiver: str,
+        amount: int,
+        asset: str = "",
+        decimals: int = 0,
+        nonce: int = 0,
+        valid_until: Optional[int] = None,
+        memo: str = "",
+        domain: str = "",
+    ) -> None: ...
+    @staticmethod
+    def from_json(s: str) -> Transaction: ...
+    def to_json(self) -> str: ...
+    def message(self) -> bytes: ...
+    @property
+    def sender(self) -> str: ...
+    @property
+    def receiver(self) -> str: ...
+    @property
+    def amount(self) -> int: ...
+    @property
+    def decimals(self) -> int: ...
+    @property
+    def version(self) -> int: ...
+    @property
+    def asset(self) -> str: ...
+    @property
+    def nonce(self) -> int: ...
+    @property
+    def valid_until(self) -> Optional[int]: ...
+    @property
+    def memo(self) -> str: ...
+    @property
+    def domain(self) -> str: ...
+
+class Constraint:
+    def __init__(
+        self,
+        auth: Sequence[Tuple[str, str]],
+        withdrawal_limit: int,
+        limits: Sequence[Tuple[str, int, int]] = ...,
+    ) -> None: ...
+    @staticmethod
+    def from_json(s: str) -> Constraint: ...
+    def to_json(self) -> str: ...
+    def hash(self) -> bytes: ...
+    @property
+    def withdrawal_limit(self) -> int: ...
+    @property
+    def limits(self) -> List[Tuple[str, int, int]]: ...
+
+class BlindRequest:
+    """The client's pending blind signing request. Not picklable, use export(passphrase)."""
+    def __init__(self, transaction: Transaction, public_value: bytes) -> None: ...
+    @property
+    def blinded_message(self) -> bytes: ...
+    @property
+    def public_value(self) -> bytes: ...
+    @property
+    def transaction(self) -> Transaction: ...
+    def unblind(self, blinded_signature: bytes) -> Signature: ...
+    def export(self, passphrase: Optional[str] = None) -> bytes: ...
+    @staticmethod
+    def restore(exported: bytes, passphrase: Optional[str] = None) -> BlindRequest: ...
+
+PyBlindRequest = BlindRequest
+
+class Signature:
+    """An unblinded signature (e || S || R)."""
+    @staticmethod
+    def from_bytes(signature: bytes) -> Signature: ...
+    @staticmethod
+    def from_hex(signature: str) -> Signature: ...
+    def to_bytes(self) -> bytes: ...
+    def hex(self) -> str: ...
+    def verify(self, public_key: bytes) -> bool: ...
+    def verify_message(self, public_key: bytes, transaction: Transaction) -> bool: ...
+
+class ProveJob(Awaitable[str]):
+    """A proof being generated on a background thread."""
+    def status(self) -> str: ...
+    def elapsed(self) -> Optional[float]: ...
+    def done(self) -> bool: ...
+    def cancel(self) -> bool: ...
+    def result(self, timeout: Optional[float] = None) -> str: ...
+    def __await__(self) -> Generator[None, None, str]: ...
+
+class Proof:
+    # succinct and groth16 need libblindr's features of the same name and
+    # exclude each other; zstd needs a verifier that decodes it
+    @staticmethod
+    def prove(request: BlindRequest, constraint: Constraint, succinct: bool = False, groth16: bool = False) -> Proof: ...
+    @staticmethod
+    def prove_async(
+        request: BlindRequest,
+        constraint: Constraint,
+        succinct: bool = False,
+        zstd: bool = False,
+        groth16: bool = False,
+    ) -> ProveJob: ...
+    @staticmethod
+    def from_bytes(proof: bytes) -> Proof: ...
+    @staticmethod
+    def from_base64(proof: str) -> Proof: ...
+    def to_bytes(self, zstd: bool = False) -> bytes: ...
+    def to_base64(self, zstd: bool = False) -> str: ...
+    def verify(
+        self,
+        blinded_message: bytes,
+        constraint_hash: bytes,
+        allow_fake: bool = False,
+        require: Optional[str] = None,
+    ) -> bool: ...
+
+# hex and json string interface
+
+def server_generate_keypair() -> Tuple[str, str]: ...
+def server_generate_session() -> Tuple[str, str]: ...
+def client_new_blind_request(transaction: str, public_value_hex: str) -> Tuple[str, BlindRequest]: ...
+def server_sign(private_key_hex: str, private_value_hex: str, blinded_message_hex: str) -> str: ...
+def client_unblind_signature(blind_request: BlindRequest, blinded_signature_hex: str) -> str: ...
+def client_verify_signature(public_key_hex: str, signature_hex: str) -> bool: ...
+def hash_spend_constraint(constraint: str) -> str: ...
+def prove_message_fits_constraint(
+    request: BlindRequest,
+    constraint: str,
+    transaction: str,
+    public_value_hex: str,
+    succinct: bool = False,
+    zstd: bool = False,
+    groth16: bool = False,
+) -> str: ...
+def prove_message_fits_constraint_async(
+    request: BlindRequest,
+    constraint: str,
+    transaction: str,
+    public_value_hex: str,
+    succinct: bool = False,
+    zstd: bool = False,
+    groth16: bool = False,
+) -> ProveJob: ...
+def verify_message_fits_constraint(
+    receipt_base64: str,
+    blinded_message_hex: str,
+    constraint_hash_hex: str,
+    allow_fake: bool = False,
+    require: Optional[str] = None,
+) -> bool: ...
@@ -0,0 +1,214 @@
+//! Client (requester) side of the protocol.
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyType};
+use blindsign::{export, request::{BlindRequest, WiredBlindRequest}, signature::{UnblindedSigData, WiredUnblindedSigData}};
+use blindr_common::Transaction;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use rand::RngCore;
+use crate::convert::{array_from_bytes, array_from_hex, blindsign_error, json_error, short_hex};
+use crate::zk::PyTransaction;
+
+/// The client's pending blind signing request. Holds the blinding factors, so
+/// it is needed again to unblind the server's signature and is never shown in
+/// `repr`, and it can't be pickled. Use `export(passphrase)` to keep it
+/// across process restarts.
+#[pyclass(name = "BlindRequest", module = "libblindr")]
+pub struct PyBlindRequest {
+    pub(crate) request: BlindRequest,
+    pub(crate) seed: [u8; 32],
+    pub(crate) transaction: Transaction,
+    pub(crate) public_value: [u8; 32],
+    pub(crate) blinded_message: [u8; 32],
+}
+
+impl PyBlindRequest {
+    pub(crate) fn create(transaction: Transaction, public_value: [u8; 32]) -> PyResult<Self> {
+        let message = transaction.message();
+
+        let mut rng = rand::thread_rng();
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        // the proof recomputes the blinded message from this seed
+        let (blinded_message, request) =
+            BlindRequest::new_specific_msg_seeded(&public_value, &message, &seed).map_err(blindsign_error)?;
+
+        Ok(PyBlindRequest { request, seed, transaction, public_value, blinded_message })
+    }
+
+    pub(crate) fn unblind_bytes(&self, blinded_signature: &[u8]) -> PyResult<PySignature> {
+        let blinded_signature = array_from_bytes(blinded_signature, "blinded signature")?;
+        let sig = self.request.gen_signed_msg(&blinded_signature).map_err(blindsign_error)?;
+        Ok(PySignature::from(sig))
+    }
+
+    // Export payload, version 1:
+    // version (1) || seed (32) || R' (32) || e' (32) || wired request (128) || transaction json
+    fn to_payload(&self) -> Vec<u8> {
+        let mut payload = vec![EXPORT_VERSION];
+        payload.extend_from_slice(&self.seed);
+        payload.extend_from_slice(&self.public_value);
+        payload.extend_from_slice(&self.blinded_message);
+        payload.extend_from_slice(WiredBlindRequest::from(&self.request).as_bytes());
+        payload.extend_from_slice(&self.transaction.message());
+        payload
+    }
+
+    fn from_payload(payload: &[u8]) -> PyResult<Self> {
+        if payload.len() < 225 {
+            return Err(PyValueError::new_err("exported blind request is truncated"));
+        }
+        if payload[0] != EXPORT_VERSION {
+            return Err(PyValueError::new_err(format!("unsupported blind request export version {}", payload[0])));
+        }
+        let wired = WiredBlindRequest(array_from_bytes(&payload[97..225], "wired request")?);
+        Ok(PyBlindRequest {
+            request: wired.to_internal_format().map_err(blindsign_error)?,
+            seed: array_from_bytes(&payload[1..33], "seed")?,
+            public_value: array_from_bytes(&payload[33..65], "public value")?,
+            blinded_message: array_from_bytes(&payload[65..97], "blinded message")?,
+            transaction: serde_json::from_slice(&payload[225..]).map_err(json_error)?,
+        })
+    }
+}
+
+const EXPORT_VERSION: u8 = 1;
+
+#[pymethods]
+impl PyBlindRequest {
+    /// Blinds the transaction against the server's session public value R'.
+    #[new]
+    fn new(transaction: PyRef<PyTransaction>, public_value: &[u8]) -> PyResult<Self> {
+        let public_value = array_from_bytes(public_value, "public value")?;
+        Self::create(transaction.inner.clone(), public_value)
+    }
+
+    /// e', the value sent to the server for blind signing.
+    #[getter]
+    fn blinded_message<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.blinded_message)
+    }
+
+    #[getter]
+    fn public_value<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.public_value)
+    }
+
+    #[getter]
+    fn transaction(&self) -> PyTransaction {
+        PyTransaction { inner: self.transaction.clone() }
+    }
+
+    /// Unblinds the server's blinded signature S'.
+    fn unblind(&self, blinded_signature: &[u8]) -> PyResult<PySignature> {
+        self.unblind_bytes(blinded_signature)
+    }
+
+    /// Exports the request so it can be restored with `BlindRequest.restore`,
+    /// encrypted when a passphrase is given. The GIL is released while
+    /// deriving the encryption key.
+    #[pyo3(signature = (passphrase=None))]
+    fn export<'py>(&self, py: Python<'py>, passphrase: Option<&str>) -> PyResult<&'py PyBytes> {
+        let payload = self.to_payload();
+        let sealed = py
+            .allow_threads(|| export::seal(&payload, passphrase.map(str::as_bytes)))
+            .map_err(blindsign_error)?;
+        Ok(PyBytes::new(py, &sealed))
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (exported, passphrase=None))]
+    fn restore(py: Python, exported: &[u8], passphrase: Option<&str>) -> PyResult<Self> {
+        let payload = py
+            .allow_threads(|| export::open(exported, passphrase.map(str::as_bytes)))
+            .map_err(blindsign_error)?;
+        Self::from_payload(&payload)
+    }
+
+    // pickling would write the blinding factors in the clear
+    fn __reduce__(&self) -> PyResult<PyObject> {
+        Err(PyTypeError::new_err("BlindRequest can't be pickled, use export(passphrase)"))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("BlindRequest(blinded_message={})", short_hex(&self.blinded_message))
+    }
+}
+
+/// An unblinded signature (e || S || R) that anyone holding the signer's
+/// public key can authenticate.
+#[pyclass(name = "Signature", module = "libblindr")]
+pub struct PySignature {
+    wired: [u8; 96],
+    sig: UnblindedSigData,
+}
+
+impl From<UnblindedSigData> for PySignature {
+    fn from(sig: UnblindedSigData) -> Self {
+        PySignature { wired: WiredUnblindedSigData::from(sig).to_bytes(), sig }
+    }
+}
+
+impl PySignature {
+    fn from_wired(wired: [u8; 96]) -> PyResult<Self> {
+        let sig = WiredUnblindedSigData(wired).to_internal_format().map_err(blindsign_error)?;
+        Ok(PySignature { wired, sig })
+    }
+
+    pub(crate) fn verify_bytes(&self, public_key: &[u8]) -> PyResult<bool> {
+        Ok(self.sig.authenticate(public_key_point(public_key)?))
+    }
+}
+
+fn public_key_point(public_key: &[u8]) -> PyResult<RistrettoPoint> {
+    let public_key = array_from_bytes(public_key, "public key")?;
+    CompressedRistretto(public_key)
+        .decompress()
+        .ok_or_else(|| PyValueError::new_err("public key is not a valid ristretto point"))
+}
+
+#[pymethods]
+impl PySignature {
+    #[staticmethod]
+    fn from_bytes(signature: &[u8]) -> PyResult<Self> {
+        Self::from_wired(array_from_bytes(signature, "signature")?)
+    }
+
+    #[staticmethod]
+    pub(crate) fn from_hex(signature: &str) -> PyResult<Self> {
+        Self::from_wired(array_from_hex(signature, "signature")?)
+    }
+
+    fn to_bytes<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.wired)
+    }
+
+    pub(crate) fn hex(&self) -> String {
+        hex::encode(self.wired)
+    }
+
+    /// Checks the signature against the signer's public key.
+    fn verify(&self, public_key: &[u8]) -> PyResult<bool> {
+        self.verify_bytes(public_key)
+    }
+
+    /// Checks the signature against the signer's public key and that it
+    /// signs the given transaction.
+    fn verify_message(&self, public_key: &[u8], transaction: PyRef<PyTransaction>) -> PyResult<bool> {
+        let public_key = public_key_point(public_key)?;
+        Ok(self.sig.msg_authenticate::<(), _>(public_key, transaction.inner.message()))
+    }
+
+    fn __reduce__(&self, py: Python) -> PyResult<(PyObject, (PyObject,))> {
+        let from_bytes = PyType::new::<Self>(py).getattr("from_bytes")?;
+        Ok((from_bytes.into(), (PyBytes::new(py, &self.wired).into(),)))
+    }
+
+    fn __eq__(&self, other: PyRef<PySignature>) -> bool {
+        self.wired == other.wired
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Signature({})", short_hex(&self.wired))
+    }
+}
@@ -0,0 +1,33 @@
+//! Conversions from python arguments into the fixed size byte strings used by
+//! the protocol, raising ValueError instead of panicking on bad input.
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+
+pub fn array_from_bytes<const N: usize>(bytes: &[u8], what: &str) -> PyResult<[u8; N]> {
+    bytes
+        .try_into()
+        .map_err(|_| PyValueError::new_err(format!("{} must be {} bytes, got {}", what, N, bytes.len())))
+}
+
+pub fn array_from_hex<const N: usize>(s: &str, what: &str) -> PyResult<[u8; N]> {
+    let bytes = hex::decode(s).map_err(|e| PyValueError::new_err(format!("{} is not valid hex: {}", what, e)))?;
+    array_from_bytes(&bytes, what)
+}
+
+pub fn blindsign_error(e: blindsign::Error) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+pub fn json_error(e: serde_json::Error) -> PyErr {
+    PyValueError::new_err(format!("invalid json: {}", e))
+}
+
+/// Shortens a hex string for use in `__repr__`.
+pub fn short_hex(bytes: &[u8]) -> String {
+    let full = hex::encode(bytes);
+    if full.len() > 16 {
+        format!("{}..", &full[..16])
+    } else {
+        full
+    }
+}
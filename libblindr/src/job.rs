@@ -0,0 +1,266 @@
+//! Background proving jobs. Proving takes minutes outside of dev mode, so the
+//! work runs on its own thread without the GIL and python can poll, block on,
+//! cancel or `await` the returned handle.
+use pyo3::prelude::*;
+use pyo3::exceptions::{PyRuntimeError, PyTimeoutError};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Phase {
+    Queued,
+    Proving,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl Phase {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Phase::Queued => "queued",
+            Phase::Proving => "proving",
+            Phase::Done => "done",
+            Phase::Failed => "failed",
+            Phase::Cancelled => "cancelled",
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self, Phase::Done | Phase::Failed | Phase::Cancelled)
+    }
+}
+
+struct JobState {
+    phase: Phase,
+    started: Option<Instant>,
+    finished: Option<Instant>,
+    // Ok(proof) once done, Err(message) once failed
+    outcome: Option<Result<String, String>>,
+    // asyncio (loop, future) pairs to settle once the job finishes
+    waiters: Vec<(PyObject, PyObject)>,
+}
+
+struct Shared {
+    state: Mutex<JobState>,
+    finished: Condvar,
+}
+
+/// Handle to a proof being generated on a background thread.
+///
+/// `status()` reports progress as one of "queued", "proving", "done",
+/// "failed" or "cancelled". `result()` blocks (without the GIL) until the
+/// proof is ready, and the job can be awaited directly from asyncio code.
+///
+/// Cancelling a job that has not started prevents it from running. The prover
+/// itself cannot be interrupted, so cancelling a running job discards its
+/// result once the prover returns.
+#[pyclass]
+pub struct ProveJob {
+    shared: Arc<Shared>,
+}
+
+impl ProveJob {
+    pub fn spawn<F>(work: F) -> Self
+    where
+        F: FnOnce() -> Result<String, String> + Send + 'static,
+    {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(JobState {
+                phase: Phase::Queued,
+                started: None,
+                finished: None,
+                outcome: None,
+                waiters: Vec::new(),
+            }),
+            finished: Condvar::new(),
+        });
+
+        let worker = shared.clone();
+        thread::spawn(move || {
+            {
+                let mut state = worker.state.lock().unwrap();
+                if state.phase == Phase::Cancelled {
+                    return;
+                }
+                state.phase = Phase::Proving;
+                state.started = Some(Instant::now());
+            }
+
+            let outcome = panic::catch_unwind(AssertUnwindSafe(work)).map_err(panic_message).and_then(|proof| proof);
+
+            let (phase, outcome, waiters) = {
+                let mut state = worker.state.lock().unwrap();
+                if state.phase != Phase::Cancelled {
+                    state.phase = if outcome.is_ok() { Phase::Done } else { Phase::Failed };
+                    state.finished = Some(Instant::now());
+                    state.outcome = Some(outcome);
+                }
+                worker.finished.notify_all();
+                (state.phase, state.outcome.clone(), std::mem::take(&mut state.waiters))
+            };
+
+            if !waiters.is_empty() {
+                Python::with_gil(|py| {
+                    for (event_loop, future) in waiters {
+                        // nothing to report to if the event loop has gone away
+                        let _ = settle_threadsafe(py, &event_loop, &future, phase, &outcome);
+                    }
+                });
+            }
+        });
+
+        ProveJob { shared }
+    }
+}
+
+#[pymethods]
+impl ProveJob {
+    /// The current phase of the job.
+    fn status(&self) -> &'static str {
+        self.shared.state.lock().unwrap().phase.as_str()
+    }
+
+    /// Seconds spent proving so far, or None if proving has not started.
+    fn elapsed(&self) -> Option<f64> {
+        let state = self.shared.state.lock().unwrap();
+        let started = state.started?;
+        let until = state.finished.unwrap_or_else(Instant::now);
+        Some(until.duration_since(started).as_secs_f64())
+    }
+
+    fn done(&self) -> bool {
+        self.shared.state.lock().unwrap().phase.is_finished()
+    }
+
+    /// Cancels the job, returning False if it had already finished.
+    fn cancel(&self, py: Python) -> PyResult<bool> {
+        let waiters = {
+            let mut state = self.shared.state.lock().unwrap();
+            if state.phase.is_finished() {
+                return Ok(false);
+            }
+            state.phase = Phase::Cancelled;
+            state.finished = Some(Instant::now());
+            self.shared.finished.notify_all();
+            std::mem::take(&mut state.waiters)
+        };
+        for (event_loop, future) in waiters {
+            settle_threadsafe(py, &event_loop, &future, Phase::Cancelled, &None)?;
+        }
+        Ok(true)
+    }
+
+    /// Blocks until the proof is ready and returns it (base64 encoded). The GIL
+    /// is released while waiting.
+    #[pyo3(signature = (timeout=None))]
+    fn result(&self, py: Python, timeout: Option<f64>) -> PyResult<String> {
+        let (phase, outcome) = py.allow_threads(|| {
+            let deadline = timeout.map(|t| Instant::now() + Duration::from_secs_f64(t.max(0.0)));
+            let mut state = self.shared.state.lock().unwrap();
+            while !state.phase.is_finished() {
+                state = match deadline {
+                    Some(deadline) => {
+                        let now = Instant::now();
+                        if now >= deadline {
+                            break;
+                        }
+                        self.shared.finished.wait_timeout(state, deadline - now).unwrap().0
+                    }
+                    None => self.shared.finished.wait(state).unwrap(),
+                };
+            }
+            (state.phase, state.outcome.clone())
+        });
+
+        match (phase, outcome) {
+            (Phase::Done, Some(Ok(proof))) => Ok(proof),
+            (Phase::Failed, Some(Err(message))) => Err(PyRuntimeError::new_err(message)),
+            (Phase::Cancelled, _) => Err(cancelled_error(py)?),
+            _ => Err(PyTimeoutError::new_err("proof is not ready yet")),
+        }
+    }
+
+    fn __await__(&self, py: Python) -> PyResult<PyObject> {
+        let event_loop = py.import("asyncio")?.call_method0("get_running_loop")?;
+        let future = event_loop.call_method0("create_future")?;
+        let finished = {
+            let mut state = self.shared.state.lock().unwrap();
+            if state.phase.is_finished() {
+                Some((state.phase, state.outcome.clone()))
+            } else {
+                state.waiters.push((event_loop.into(), future.into()));
+                None
+            }
+        };
+        if let Some((phase, outcome)) = finished {
+            settle(py, future, phase, &outcome)?;
+        }
+        Ok(future.call_method0("__await__")?.into())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ProveJob(status={:?})", self.status())
+    }
+}
+
+/// Settles an asyncio future from outside its event loop, which has to go
+/// through the loop that owns the future.
+fn settle_threadsafe(
+    py: Python,
+    event_loop: &PyObject,
+    future: &PyObject,
+    phase: Phase,
+    outcome: &Option<Result<String, String>>,
+) -> PyResult<()> {
+    let settle_fn = wrap_pyfunction!(settle_future, py)?;
+    let (kind, value): (&str, PyObject) = match (phase, outcome) {
+        (Phase::Done, Some(Ok(proof))) => ("result", proof.into_py(py)),
+        (Phase::Failed, Some(Err(message))) => ("exception", PyRuntimeError::new_err(message.clone()).into_py(py)),
+        _ => ("cancel", py.None()),
+    };
+    event_loop.call_method1(py, "call_soon_threadsafe", (settle_fn, future, kind, value))?;
+    Ok(())
+}
+
+/// Settles an asyncio future from the thread running its event loop.
+fn settle(py: Python, future: &PyAny, phase: Phase, outcome: &Option<Result<String, String>>) -> PyResult<()> {
+    match (phase, outcome) {
+        (Phase::Done, Some(Ok(proof))) => settle_future(future, "result", proof.into_py(py)),
+        (Phase::Failed, Some(Err(message))) => {
+            settle_future(future, "exception", PyRuntimeError::new_err(message.clone()).into_py(py))
+        }
+        _ => settle_future(future, "cancel", py.None()),
+    }
+}
+
+#[pyfunction]
+fn settle_future(future: &PyAny, kind: &str, value: PyObject) -> PyResult<()> {
+    // the awaiting task may have been cancelled in the meantime
+    if future.call_method0("done")?.is_true()? {
+        return Ok(());
+    }
+    match kind {
+        "result" => future.call_method1("set_result", (value,))?,
+        "exception" => future.call_method1("set_exception", (value,))?,
+        _ => future.call_method0("cancel")?,
+    };
+    Ok(())
+}
+
+fn cancelled_error(py: Python) -> PyResult<PyErr> {
+    let cls = py.import("concurrent.futures")?.getattr("CancelledError")?;
+    Ok(PyErr::from_value(cls.call1(("proof job was cancelled",))?))
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "proving failed".to_string()
+    }
+}
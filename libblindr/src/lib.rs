@@ -1,126 +1,157 @@
 use pyo3::prelude::*;
-use blindsign::{session::BlindSession, keypair::BlindKeypair, request::BlindRequest, signature::WiredUnblindedSigData};
 use blindr_common::{Transaction, Constraint};
-use std::convert::TryInto;
-use curve25519_dalek::{scalar::Scalar, ristretto::CompressedRistretto};
 use base64::prelude::*;
-use rand::RngCore;
+use pyo3::exceptions::PyValueError;
+
+mod client;
+mod convert;
+mod job;
+mod signer;
+mod zk;
+
+use client::{PyBlindRequest, PySignature};
+use convert::{array_from_hex, json_error};
+use job::ProveJob;
+use signer::{PyKeypair, PySignerSession};
+use zk::{verifier_policy, ProveOptions, PyConstraint, PyProof, PyTransaction};
+
+// The functions below are the original hex/json string interface, kept as thin
+// wrappers around the classes above.
 
 #[pyfunction]
 fn server_generate_keypair() -> PyResult<(String, String)> {
-    let keypair = BlindKeypair::generate().unwrap();
-    let private_key = keypair.private().to_bytes();
-    let public_key = keypair.public().compress().to_bytes();
-    let private_key_hex = hex::encode(private_key);
-    let public_key_hex = hex::encode(public_key);
+    let keypair = PyKeypair::generate()?.keypair;
+    let private_key_hex = hex::encode(keypair.private_wired());
+    let public_key_hex = hex::encode(keypair.public_wired());
     Ok((private_key_hex, public_key_hex))
 }
 
 #[pyfunction]
 fn server_generate_session() -> PyResult<(String, String)> {
-    let (public_value_bytes, private_value) = BlindSession::new().unwrap();
-    let private_value_bytes = private_value.k.to_bytes();
-    let private_value_hex = hex::encode(private_value_bytes);
-    let public_value_bytes = hex::encode(public_value_bytes);
-    Ok((private_value_hex, public_value_bytes))
-}
-
-#[pyclass]
-pub struct PyBlindRequest{
-    request: BlindRequest,
-    seed: [u8; 32],
+    let session = PySignerSession::generate()?;
+    let private_value_hex = hex::encode(session.session.k.to_bytes());
+    let public_value_hex = hex::encode(session.public_value);
+    Ok((private_value_hex, public_value_hex))
 }
 
 #[pyfunction]
 fn client_new_blind_request(transaction: String, public_value_hex: String) -> PyResult<(String, PyBlindRequest)> {
-    let message = Transaction::from_str(&transaction).message();
-    let public_value = hex::decode(public_value_hex).unwrap().try_into().unwrap();
-    
-    // return blinded_message and blind_request
-    let mut rng = rand::thread_rng();
-    let mut seed = [0u8; 32];
-    rng.fill_bytes(&mut seed);
-    blindsign::set_seed(Some(seed));
-    let (blinded_message, blind_request) = BlindRequest::new_specific_msg(&public_value, &message).unwrap();
-    blindsign::set_seed(None);
-
-    let blinded_message_hex = hex::encode(blinded_message);
-    let blind_request_py = PyBlindRequest { request: blind_request, seed };
-    Ok((blinded_message_hex, blind_request_py))
+    let transaction = serde_json::from_str(&transaction).map_err(json_error)?;
+    let public_value = array_from_hex(&public_value_hex, "public value")?;
+    let blind_request = PyBlindRequest::create(transaction, public_value)?;
+    let blinded_message_hex = hex::encode(blind_request.blinded_message);
+    Ok((blinded_message_hex, blind_request))
 }
 
 #[pyfunction]
 fn server_sign(private_key_hex: String, private_value_hex: String, blinded_message_hex: String) -> PyResult<String> {
-    let private_key_bytes = hex::decode(private_key_hex).unwrap().try_into().unwrap();
-    let private_key_scalar = Scalar::from_bits(private_key_bytes);
-
-    let private_value_bytes = hex::decode(private_value_hex).unwrap().try_into().unwrap();
-    let private_value_scalar = Scalar::from_bits(private_value_bytes);
-    let private_value = BlindSession { k: private_value_scalar };
-
-    let blinded_message_bytes: [u8; 32] = hex::decode(blinded_message_hex).unwrap().try_into().unwrap();
-
-    // return blinded signature
-    let blinded_signature = private_value.sign_ep(&blinded_message_bytes, private_key_scalar).unwrap();
-    let blinded_signature_hex = hex::encode(blinded_signature);
-    Ok(blinded_signature_hex)
+    let keypair = PyKeypair::from_private(array_from_hex(&private_key_hex, "private key")?)?;
+    let mut session = PySignerSession::from_private(array_from_hex(&private_value_hex, "private value")?)?;
+    let blinded_message: [u8; 32] = array_from_hex(&blinded_message_hex, "blinded message")?;
+    let blinded_signature = session.sign_bytes(&keypair, &blinded_message)?;
+    Ok(hex::encode(blinded_signature))
 }
 
 #[pyfunction]
 fn client_unblind_signature(blind_request: PyRef<PyBlindRequest>, blinded_signature_hex: String) -> PyResult<String> {
-    let blinded_signature_bytes = hex::decode(blinded_signature_hex).unwrap().try_into().unwrap();
-
-    let unblinded_sig_msg = blind_request.request.gen_signed_msg(&blinded_signature_bytes).unwrap();
-    let unblinded_sig_bytes = WiredUnblindedSigData::from(unblinded_sig_msg).to_bytes();
-    let unblinded_sig_hex = hex::encode(unblinded_sig_bytes);
-
-    Ok(unblinded_sig_hex)
+    let blinded_signature: [u8; 32] = array_from_hex(&blinded_signature_hex, "blinded signature")?;
+    let signature = blind_request.unblind_bytes(&blinded_signature)?;
+    Ok(signature.hex())
 }
 
 #[pyfunction]
 fn client_verify_signature(public_key_hex: String, signature_hex: String) -> PyResult<bool> {
-    let public_key_bytes = hex::decode(public_key_hex).unwrap().try_into().unwrap();
-    let public_key = CompressedRistretto(public_key_bytes).decompress().unwrap();
-
-    let signature_bytes = hex::decode(signature_hex).unwrap().try_into().unwrap();
-    let sig = WiredUnblindedSigData(signature_bytes).to_internal_format().unwrap();
-
-    let isok = sig.authenticate(public_key);
-    Ok(isok)
+    let public_key: [u8; 32] = array_from_hex(&public_key_hex, "public key")?;
+    let signature = PySignature::from_hex(&signature_hex)?;
+    signature.verify_bytes(&public_key)
 }
 
-
 #[pyfunction]
 fn hash_spend_constraint(constraint: String) -> PyResult<String> {
-    let digest = Constraint::from_str(&constraint).hash();
-    let digest_hex = hex::encode(digest);
-    Ok(digest_hex)
+    let constraint: Constraint = serde_json::from_str(&constraint).map_err(json_error)?;
+    Ok(hex::encode(constraint.hash()))
 }
 
+/// The proof, in base64, that the request blinds the transaction and that it
+/// fits the constraint. `succinct` and `groth16` compress the receipt (see
+/// Proof.prove) and `zstd` the upload.
 #[pyfunction]
-fn prove_message_fits_constraint(request: PyRef<PyBlindRequest>, constraint: String, transaction: String, public_value_hex: String) -> PyResult<String> {
-    let constraint = Constraint::from_str(&constraint);
-    let transaction = Transaction::from_str(&transaction);
-    let public_value = hex::decode(public_value_hex).unwrap().try_into().unwrap();
-    let receipt = blindr_zk_driver::prove(&transaction, &constraint, &public_value, &request.seed);
-    let receipt_bin = bincode::serialize(&receipt).unwrap();
-    let receipt_base64 = BASE64_STANDARD.encode(receipt_bin);
-    Ok(receipt_base64)
+#[pyo3(signature = (request, constraint, transaction, public_value_hex, succinct=false, zstd=false, groth16=false))]
+#[allow(clippy::too_many_arguments)]
+fn prove_message_fits_constraint(
+    py: Python,
+    request: PyRef<PyBlindRequest>,
+    constraint: String,
+    transaction: String,
+    public_value_hex: String,
+    succinct: bool,
+    zstd: bool,
+    groth16: bool,
+) -> PyResult<String> {
+    let options = ProveOptions::new(succinct, zstd, groth16)?;
+    let (constraint, transaction, public_value) = prove_inputs(&constraint, &transaction, &public_value_hex)?;
+    let seed = request.seed;
+    py.allow_threads(move || zk::prove_base64(&transaction, &constraint, &public_value, &seed, options))
+        .map_err(PyValueError::new_err)
+}
+
+/// Same as prove_message_fits_constraint, but proves on a background thread
+/// and returns a ProveJob that can be polled, cancelled or awaited.
+#[pyfunction]
+#[pyo3(signature = (request, constraint, transaction, public_value_hex, succinct=false, zstd=false, groth16=false))]
+fn prove_message_fits_constraint_async(
+    request: PyRef<PyBlindRequest>,
+    constraint: String,
+    transaction: String,
+    public_value_hex: String,
+    succinct: bool,
+    zstd: bool,
+    groth16: bool,
+) -> PyResult<ProveJob> {
+    let options = ProveOptions::new(succinct, zstd, groth16)?;
+    let (constraint, transaction, public_value) = prove_inputs(&constraint, &transaction, &public_value_hex)?;
+    let seed = request.seed;
+    Ok(ProveJob::spawn(move || zk::prove_base64(&transaction, &constraint, &public_value, &seed, options)))
+}
+
+fn prove_inputs(constraint: &str, transaction: &str, public_value_hex: &str) -> PyResult<(Constraint, Transaction, [u8; 32])> {
+    let constraint = serde_json::from_str(constraint).map_err(json_error)?;
+    let transaction = serde_json::from_str(transaction).map_err(json_error)?;
+    let public_value = array_from_hex(public_value_hex, "public value")?;
+    Ok((constraint, transaction, public_value))
 }
 
 #[pyfunction]
-fn verify_message_fits_constraint(receipt_base64: String, blinded_message_hex: String, constraint_hash_hex: String) -> PyResult<bool> {
-    let blinded_message = hex::decode(blinded_message_hex).unwrap().try_into().unwrap();
-    let constraint_hash = hex::decode(constraint_hash_hex).unwrap().try_into().unwrap();
-    let receipt_bin = BASE64_STANDARD.decode(&receipt_base64).unwrap();
-    let receipt = bincode::deserialize(&receipt_bin).unwrap();
-    let isok = blindr_zk_driver::verify(&receipt, &blinded_message, &constraint_hash);
-    Ok(isok)
+#[pyo3(signature = (receipt_base64, blinded_message_hex, constraint_hash_hex, allow_fake=false, require=None))]
+fn verify_message_fits_constraint(
+    py: Python,
+    receipt_base64: String,
+    blinded_message_hex: String,
+    constraint_hash_hex: String,
+    allow_fake: bool,
+    require: Option<&str>,
+) -> PyResult<bool> {
+    let blinded_message: [u8; 32] = array_from_hex(&blinded_message_hex, "blinded message")?;
+    let constraint_hash: [u8; 32] = array_from_hex(&constraint_hash_hex, "constraint hash")?;
+    let receipt_bin = BASE64_STANDARD
+        .decode(&receipt_base64)
+        .map_err(|e| PyValueError::new_err(format!("proof is not valid base64: {}", e)))?;
+    let proof = PyProof::from_bytes(&receipt_bin)?;
+    proof.verify_bytes(py, &blinded_message, &constraint_hash, &verifier_policy(allow_fake, require)?)
 }
 
 #[pymodule]
-fn libblindr(_py: Python, m: &PyModule) -> PyResult<()> {
+fn libblindr(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyKeypair>()?;
+    m.add_class::<PySignerSession>()?;
     m.add_class::<PyBlindRequest>()?;
+    m.add_class::<PySignature>()?;
+    m.add_class::<PyTransaction>()?;
+    m.add_class::<PyConstraint>()?;
+    m.add_class::<PyProof>()?;
+    m.add_class::<ProveJob>()?;
+    // the name BlindRequest used to be exported under
+    m.add("PyBlindRequest", py.get_type::<PyBlindRequest>())?;
 
     m.add_function(wrap_pyfunction!(server_generate_keypair, m)?)?;
     m.add_function(wrap_pyfunction!(server_generate_session, m)?)?;
@@ -130,6 +161,7 @@ fn libblindr(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(client_verify_signature, m)?)?;
     m.add_function(wrap_pyfunction!(hash_spend_constraint, m)?)?;
     m.add_function(wrap_pyfunction!(prove_message_fits_constraint, m)?)?;
+    m.add_function(wrap_pyfunction!(prove_message_fits_constraint_async, m)?)?;
     m.add_function(wrap_pyfunction!(verify_message_fits_constraint, m)?)?;
 
     Ok(())
@@ -0,0 +1,132 @@
+//! Signer (custodian) side of the protocol.
+use pyo3::prelude::*;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::types::PyBytes;
+use blindsign::{keypair::BlindKeypair, session::BlindSession};
+use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, scalar::Scalar};
+use crate::convert::{array_from_bytes, array_from_hex, blindsign_error, short_hex};
+
+/// The signer's long lived keypair. Only the public key shows up in `repr`,
+/// and keypairs can't be pickled; use `private_key()` to store it explicitly.
+#[pyclass(name = "Keypair", module = "libblindr")]
+pub struct PyKeypair {
+    pub(crate) keypair: BlindKeypair,
+}
+
+impl PyKeypair {
+    pub(crate) fn from_private(private: [u8; 32]) -> PyResult<Self> {
+        let scalar = Scalar::from_canonical_bytes(private)
+            .ok_or_else(|| PyValueError::new_err("private key is not a canonical scalar"))?;
+        let public = (scalar * RISTRETTO_BASEPOINT_POINT).compress().to_bytes();
+        let keypair = BlindKeypair::from_wired(private, public).map_err(blindsign_error)?;
+        Ok(PyKeypair { keypair })
+    }
+}
+
+#[pymethods]
+impl PyKeypair {
+    #[staticmethod]
+    pub(crate) fn generate() -> PyResult<Self> {
+        let keypair = BlindKeypair::generate().map_err(blindsign_error)?;
+        Ok(PyKeypair { keypair })
+    }
+
+    #[staticmethod]
+    fn from_bytes(private_key: &[u8]) -> PyResult<Self> {
+        Self::from_private(array_from_bytes(private_key, "private key")?)
+    }
+
+    #[staticmethod]
+    fn from_hex(private_key: &str) -> PyResult<Self> {
+        Self::from_private(array_from_hex(private_key, "private key")?)
+    }
+
+    #[getter]
+    fn public_key<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.keypair.public_wired())
+    }
+
+    fn private_key<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.keypair.private_wired())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Keypair(public_key={})", short_hex(&self.keypair.public_wired()))
+    }
+}
+
+/// A single signing session (the nonce k and its public value R' = kP).
+/// A session signs at most once: signing two messages with the same k
+/// reveals the private key.
+#[pyclass(name = "SignerSession", module = "libblindr")]
+pub struct PySignerSession {
+    pub(crate) session: BlindSession,
+    pub(crate) public_value: [u8; 32],
+    used: bool,
+}
+
+impl PySignerSession {
+    pub(crate) fn from_private(private: [u8; 32]) -> PyResult<Self> {
+        let k = Scalar::from_canonical_bytes(private)
+            .ok_or_else(|| PyValueError::new_err("private value is not a canonical scalar"))?;
+        let public_value = (k * RISTRETTO_BASEPOINT_POINT).compress().to_bytes();
+        Ok(PySignerSession { session: BlindSession { k }, public_value, used: false })
+    }
+
+    pub(crate) fn sign_bytes(&mut self, keypair: &PyKeypair, blinded_message: &[u8]) -> PyResult<[u8; 32]> {
+        if self.used {
+            return Err(PyRuntimeError::new_err("signer session was already used to sign"));
+        }
+        let blinded_message = array_from_bytes(blinded_message, "blinded message")?;
+        let blinded_signature = self.session
+            .sign_ep(&blinded_message, keypair.keypair.private())
+            .map_err(blindsign_error)?;
+        self.used = true;
+        Ok(blinded_signature)
+    }
+}
+
+#[pymethods]
+impl PySignerSession {
+    #[staticmethod]
+    pub(crate) fn generate() -> PyResult<Self> {
+        let (public_value, session) = BlindSession::new().map_err(blindsign_error)?;
+        Ok(PySignerSession { session, public_value, used: false })
+    }
+
+    #[staticmethod]
+    fn from_bytes(private_value: &[u8]) -> PyResult<Self> {
+        Self::from_private(array_from_bytes(private_value, "private value")?)
+    }
+
+    #[staticmethod]
+    fn from_hex(private_value: &str) -> PyResult<Self> {
+        Self::from_private(array_from_hex(private_value, "private value")?)
+    }
+
+    /// R', sent to the client to create its blind request.
+    #[getter]
+    fn public_value<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.public_value)
+    }
+
+    fn private_value<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.session.k.to_bytes())
+    }
+
+    #[getter]
+    fn used(&self) -> bool {
+        self.used
+    }
+
+    /// Blindly signs the client's blinded message, returning the blinded
+    /// signature S'.
+    fn sign<'py>(&mut self, py: Python<'py>, keypair: PyRef<PyKeypair>, blinded_message: &[u8]) -> PyResult<&'py PyBytes> {
+        let blinded_signature = self.sign_bytes(&keypair, blinded_message)?;
+        Ok(PyBytes::new(py, &blinded_signature))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("SignerSession(public_value={}, used={})", short_hex(&self.public_value), if self.used { "True" } else { "False" })
+    }
+}
@@ -0,0 +1,381 @@
+//! Transactions, spend constraints and the zero knowledge proof that a
+//! blinded transaction fits a constraint.
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyBytes, PyType};
+use blindr_common::{Amount, Auth, Constraint, Transaction};
+use blindr_zk_driver::{proof::Encoding, ImageRegistry, ReceiptKind, Receipt, VerifierPolicy};
+use base64::prelude::*;
+use crate::client::PyBlindRequest;
+use crate::convert::{array_from_bytes, json_error, short_hex};
+use crate::job::ProveJob;
+
+#[pyclass(name = "Transaction", module = "libblindr")]
+pub struct PyTransaction {
+    pub(crate) inner: Transaction,
+}
+
+#[pymethods]
+impl PyTransaction {
+    #[new]
+    #[pyo3(signature = (sender, receiver, amount, asset=String::new(), decimals=0, nonce=0, valid_until=None, memo=String::new(), domain=String::new()))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        sender: String,
+        receiver: String,
+        amount: u128,
+        asset: String,
+        decimals: u8,
+        nonce: u64,
+        valid_until: Option<u64>,
+        memo: String,
+        domain: String,
+    ) -> Self {
+        let amount = Amount { asset, value: amount, decimals };
+        let inner = Transaction { nonce, valid_until, memo, domain, ..Transaction::new(sender, receiver, amount) };
+        PyTransaction { inner }
+    }
+
+    #[staticmethod]
+    fn from_json(s: &str) -> PyResult<Self> {
+        let inner = serde_json::from_str(s).map_err(json_error)?;
+        Ok(PyTransaction { inner })
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(&self.inner).unwrap()
+    }
+
+    /// The exact bytes that get blinded and signed.
+    fn message<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.inner.message())
+    }
+
+    #[getter]
+    fn sender(&self) -> String {
+        self.inner.sender.clone()
+    }
+
+    #[getter]
+    fn receiver(&self) -> String {
+        self.inner.receiver.clone()
+    }
+
+    /// In units of 10^-decimals of the asset.
+    #[getter]
+    fn amount(&self) -> u128 {
+        self.inner.amount.value
+    }
+
+    #[getter]
+    fn decimals(&self) -> u8 {
+        self.inner.amount.decimals
+    }
+
+    #[getter]
+    fn version(&self) -> u32 {
+        self.inner.version
+    }
+
+    #[getter]
+    fn asset(&self) -> String {
+        self.inner.amount.asset.clone()
+    }
+
+    #[getter]
+    fn nonce(&self) -> u64 {
+        self.inner.nonce
+    }
+
+    #[getter]
+    fn valid_until(&self) -> Option<u64> {
+        self.inner.valid_until
+    }
+
+    #[getter]
+    fn memo(&self) -> String {
+        self.inner.memo.clone()
+    }
+
+    #[getter]
+    fn domain(&self) -> String {
+        self.inner.domain.clone()
+    }
+
+    fn __reduce__(&self, py: Python) -> PyResult<(PyObject, (String,))> {
+        let from_json = PyType::new::<Self>(py).getattr("from_json")?;
+        Ok((from_json.into(), (self.to_json(),)))
+    }
+
+    fn __eq__(&self, other: PyRef<PyTransaction>) -> bool {
+        self.inner.message() == other.inner.message()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Transaction(sender={:?}, receiver={:?}, amount={}, asset={:?}, decimals={}, nonce={}, valid_until={:?}, domain={:?})",
+            self.inner.sender,
+            self.inner.receiver,
+            self.inner.amount.value,
+            self.inner.amount.asset,
+            self.inner.amount.decimals,
+            self.inner.nonce,
+            self.inner.valid_until,
+            self.inner.domain
+        )
+    }
+}
+
+/// A spend constraint. The auth answers are secrets of the client, so they
+/// never show up in `repr` and constraints can't be pickled; use `to_json()`
+/// to store one explicitly.
+#[pyclass(name = "Constraint", module = "libblindr")]
+pub struct PyConstraint {
+    pub(crate) inner: Constraint,
+}
+
+#[pymethods]
+impl PyConstraint {
+    /// `limits` are per-asset (asset, value, decimals) limits, the native
+    /// asset falls back to `withdrawal_limit`.
+    #[new]
+    #[pyo3(signature = (auth, withdrawal_limit, limits=Vec::new()))]
+    fn new(auth: Vec<(String, String)>, withdrawal_limit: u64, limits: Vec<(String, u128, u8)>) -> Self {
+        let limits = limits.into_iter().map(|(asset, value, decimals)| Amount { asset, value, decimals }).collect();
+        PyConstraint { inner: Constraint { limits, ..Constraint::new(Auth(auth), withdrawal_limit) } }
+    }
+
+    #[staticmethod]
+    fn from_json(s: &str) -> PyResult<Self> {
+        let inner = serde_json::from_str(s).map_err(json_error)?;
+        Ok(PyConstraint { inner })
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(&self.inner).unwrap()
+    }
+
+    /// The constraint hash the custodian knows the client by.
+    fn hash<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.inner.hash())
+    }
+
+    #[getter]
+    fn withdrawal_limit(&self) -> u64 {
+        self.inner.withdrawal_limit
+    }
+
+    #[getter]
+    fn limits(&self) -> Vec<(String, u128, u8)> {
+        self.inner.limits.iter().map(|limit| (limit.asset.clone(), limit.value, limit.decimals)).collect()
+    }
+
+    fn __repr__(&self) -> String {
+        let questions: Vec<&str> = self.inner.auth.0.iter().map(|(question, _)| question.as_str()).collect();
+        format!(
+            "Constraint(auth={:?}, withdrawal_limit={}, limits={:?})",
+            questions,
+            self.inner.withdrawal_limit,
+            self.limits()
+        )
+    }
+}
+
+/// A proof that a blind request's blinded message hides a transaction that
+/// fits a constraint with the given hash.
+#[pyclass(name = "Proof", module = "libblindr")]
+pub struct PyProof {
+    receipt: Receipt,
+}
+
+impl PyProof {
+    fn from_receipt_bytes(bytes: &[u8]) -> PyResult<Self> {
+        let receipt = blindr_zk_driver::proof::decode(bytes).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(PyProof { receipt })
+    }
+
+    fn receipt_bytes(&self, zstd: bool) -> PyResult<Vec<u8>> {
+        blindr_zk_driver::proof::encode(&self.receipt, Encoding { zstd }).map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    pub(crate) fn verify_bytes(
+        &self,
+        py: Python,
+        blinded_message: &[u8],
+        constraint_hash: &[u8],
+        policy: &VerifierPolicy,
+    ) -> PyResult<bool> {
+        let blinded_message = array_from_bytes(blinded_message, "blinded message")?;
+        let constraint_hash = array_from_bytes(constraint_hash, "constraint hash")?;
+        Ok(py.allow_threads(|| blindr_zk_driver::verify_with(&self.receipt, &blinded_message, &constraint_hash, policy)))
+    }
+}
+
+#[pymethods]
+impl PyProof {
+    /// Proves that the request's transaction fits the constraint. The GIL is
+    /// released while proving.
+    ///
+    /// `succinct` compresses the receipt to a few hundred KB (the `succinct`
+    /// feature, a local prover) and `groth16` further, for on-chain
+    /// verification (the `groth16` feature, needs docker).
+    #[staticmethod]
+    #[pyo3(signature = (request, constraint, succinct=false, groth16=false))]
+    fn prove(py: Python, request: PyRef<PyBlindRequest>, constraint: PyRef<PyConstraint>, succinct: bool, groth16: bool) -> PyResult<Self> {
+        let options = ProveOptions::new(succinct, false, groth16)?;
+        let (transaction, public_value, seed) = (request.transaction.clone(), request.public_value, request.seed);
+        let constraint = constraint.inner.clone();
+        let receipt = py
+            .allow_threads(move || options.compress(blindr_zk_driver::prove(&transaction, &constraint, &public_value, &seed)))
+            .map_err(PyValueError::new_err)?;
+        Ok(PyProof { receipt })
+    }
+
+    /// Proves on a background thread. The job's result is the proof in base64
+    /// (see `Proof.from_base64`), zstd compressed if `zstd`.
+    #[staticmethod]
+    #[pyo3(signature = (request, constraint, succinct=false, zstd=false, groth16=false))]
+    fn prove_async(
+        request: PyRef<PyBlindRequest>,
+        constraint: PyRef<PyConstraint>,
+        succinct: bool,
+        zstd: bool,
+        groth16: bool,
+    ) -> PyResult<ProveJob> {
+        let options = ProveOptions::new(succinct, zstd, groth16)?;
+        let (transaction, public_value, seed) = (request.transaction.clone(), request.public_value, request.seed);
+        let constraint = constraint.inner.clone();
+        Ok(ProveJob::spawn(move || prove_base64(&transaction, &constraint, &public_value, &seed, options)))
+    }
+
+    #[staticmethod]
+    pub(crate) fn from_bytes(proof: &[u8]) -> PyResult<Self> {
+        Self::from_receipt_bytes(proof)
+    }
+
+    #[staticmethod]
+    fn from_base64(proof: &str) -> PyResult<Self> {
+        let bytes = BASE64_STANDARD
+            .decode(proof)
+            .map_err(|e| PyValueError::new_err(format!("proof is not valid base64: {}", e)))?;
+        Self::from_receipt_bytes(&bytes)
+    }
+
+    /// The proof in blindr_zk::proof's format, zstd compressed if `zstd`.
+    #[pyo3(signature = (zstd=false))]
+    fn to_bytes<'py>(&self, py: Python<'py>, zstd: bool) -> PyResult<&'py PyBytes> {
+        Ok(PyBytes::new(py, &self.receipt_bytes(zstd)?))
+    }
+
+    #[pyo3(signature = (zstd=false))]
+    fn to_base64(&self, zstd: bool) -> PyResult<String> {
+        Ok(BASE64_STANDARD.encode(self.receipt_bytes(zstd)?))
+    }
+
+    /// Checks the proof and that its public journal commits to the blinded
+    /// message and constraint hash, at a time close to the local clock. The
+    /// GIL is released while verifying.
+    ///
+    /// Fake (dev mode) proofs are refused unless `allow_fake`, and then only
+    /// with RISC0_DEV_MODE set. `require` is "composite", "succinct" or
+    /// "groth16" to only accept that kind of proof.
+    #[pyo3(signature = (blinded_message, constraint_hash, allow_fake=false, require=None))]
+    fn verify(
+        &self,
+        py: Python,
+        blinded_message: &[u8],
+        constraint_hash: &[u8],
+        allow_fake: bool,
+        require: Option<&str>,
+    ) -> PyResult<bool> {
+        self.verify_bytes(py, blinded_message, constraint_hash, &verifier_policy(allow_fake, require)?)
+    }
+
+    fn __reduce__(&self, py: Python) -> PyResult<(PyObject, (PyObject,))> {
+        let from_bytes = PyType::new::<Self>(py).getattr("from_bytes")?;
+        Ok((from_bytes.into(), (PyBytes::new(py, &self.receipt_bytes(false)?).into(),)))
+    }
+
+    fn __repr__(&self) -> String {
+        match ImageRegistry::default().journal(&self.receipt) {
+            Some(journal) => format!(
+                "Proof(blinded_message={}, constraint_hash={}, proved_at={})",
+                short_hex(&journal.blinded_message),
+                short_hex(&journal.constraint_hash),
+                journal.proved_at
+            ),
+            None => "Proof(<unknown guest or malformed journal>)".to_string(),
+        }
+    }
+}
+
+pub(crate) fn verifier_policy(allow_fake: bool, require: Option<&str>) -> PyResult<VerifierPolicy> {
+    let require = require.map(|kind| kind.parse::<ReceiptKind>()).transpose().map_err(PyValueError::new_err)?;
+    Ok(VerifierPolicy { allow_fake, require, ..VerifierPolicy::default() })
+}
+
+/// How the prove functions shrink the receipt before it is uploaded. The
+/// default is the composite receipt, encoded as is.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct ProveOptions {
+    succinct: bool,
+    zstd: bool,
+    groth16: bool,
+}
+
+impl ProveOptions {
+    // refuses what this build can't do before anything is proved
+    pub(crate) fn new(succinct: bool, zstd: bool, groth16: bool) -> PyResult<Self> {
+        if succinct && groth16 {
+            return Err(PyValueError::new_err("succinct and groth16 are exclusive, groth16 compresses the receipt itself"));
+        }
+        if succinct && !cfg!(feature = "succinct") {
+            return Err(PyValueError::new_err("succinct proofs need libblindr's succinct feature"));
+        }
+        if groth16 && !cfg!(feature = "groth16") {
+            return Err(PyValueError::new_err("groth16 proofs need libblindr's groth16 feature"));
+        }
+        Ok(ProveOptions { succinct, zstd, groth16 })
+    }
+
+    fn compress(&self, receipt: Receipt) -> Result<Receipt, String> {
+        match (self.succinct, self.groth16) {
+            (true, _) => succinct(&receipt),
+            (_, true) => groth16(&receipt),
+            _ => Ok(receipt),
+        }
+    }
+}
+
+#[cfg(feature = "succinct")]
+fn succinct(receipt: &Receipt) -> Result<Receipt, String> {
+    blindr_zk_driver::proof::compress(receipt).map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "succinct"))]
+fn succinct(_: &Receipt) -> Result<Receipt, String> {
+    Err("succinct proofs need libblindr's succinct feature".to_string())
+}
+
+#[cfg(feature = "groth16")]
+fn groth16(receipt: &Receipt) -> Result<Receipt, String> {
+    let work_dir = tempfile::tempdir().map_err(|e| e.to_string())?;
+    blindr_zk_driver::groth16::to_groth16(receipt, work_dir.path()).map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "groth16"))]
+fn groth16(_: &Receipt) -> Result<Receipt, String> {
+    Err("groth16 proofs need libblindr's groth16 feature".to_string())
+}
+
+pub(crate) fn prove_base64(
+    transaction: &Transaction,
+    constraint: &Constraint,
+    public_value: &[u8; 32],
+    seed: &[u8; 32],
+    options: ProveOptions,
+) -> Result<String, String> {
+    let receipt = options.compress(blindr_zk_driver::prove(transaction, constraint, public_value, seed))?;
+    let proof = blindr_zk_driver::proof::encode(&receipt, Encoding { zstd: options.zstd }).map_err(|e| e.to_string())?;
+    Ok(BASE64_STANDARD.encode(proof))
+}
@@ -40,12 +40,14 @@ class MyAPIClient:
         return {"public_value": public_value, "public_key": public_key, "constraint_hash": constraint_hash}
 
     def sign_message(self, message, public_value, public_key, constraint):
-        # Assuming `prove_message_fits_constraint` does some processing and returns a proof object
-        proof = prove_message_fits_constraint(constraint, message)
-        constraint_hash = hash_spend_constraint(constraint)
-
         # Assuming `client_new_blind_request` returns a tuple (blinded_message, blind_request)
         blinded_message, blind_request = client_new_blind_request(message, public_value)
+
+        # The proof recomputes the blinded message from the blind request's seed
+        proof = prove_message_fits_constraint(
+            blind_request, constraint, message, public_value, succinct=self.config.succinct, zstd=self.config.zstd
+        )
+        constraint_hash = hash_spend_constraint(constraint)
         
         # Send the blinded message to the server for signing
         response = requests.post(f"{self.config.base_url}/blind-sign", json={
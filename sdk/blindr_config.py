@@ -1,7 +1,10 @@
 import os
 
 class BlindrConfig:
-    def __init__(self, base_url=None, api_key=None):
+    def __init__(self, base_url=None, api_key=None, succinct=False, zstd=True):
         self.base_url = base_url or os.getenv("SDK_BASE_URL")
         self.api_key = api_key or os.getenv("SDK_API_KEY")
+        # how proofs are shrunk before upload, see libblindr's Proof.prove
+        self.succinct = succinct
+        self.zstd = zstd
         
\ No newline at end of file